@@ -4,6 +4,7 @@ use serde_json::Value;
 use log::info;
 use server::stockdata::FetchRequest;
 use common::constants::BASE_URL;
+use position::money::{Money, Shares};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -60,6 +61,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             cmd if cmd.starts_with("position ") => {
                 handle_position_command(&client, base_url, &cmd[9..]).await?;
             },
+            cmd if cmd.starts_with("order ") => {
+                handle_order_command(&client, base_url, cmd.trim_start_matches("order ")).await?;
+            },
             cmd if cmd.starts_with("strategy ") => {
                 handle_strategy_command(&client, base_url, &cmd[9..]).await?;
             },
@@ -88,9 +92,36 @@ fn print_help() {
     println!("  position remove_portfolio <name> - 删除投资组合");
     println!("  position add <portfolio> <code> <amount> - 添加持仓");
     println!("  position remove <portfolio> <code> <amount> - 减少持仓");
+    println!("  position load_actions <path> - 从文件加载并应用公司行动");
+    println!("  position deposit <portfolio> <amount> - 入金");
+    println!("  position withdraw <portfolio> <amount> - 出金");
+    println!("  position cash_ledger <portfolio> - 查看现金流水");
+    println!("  position performance <portfolio> [from] [to] - 查看组合绩效 (日期格式YYYY-MM-DD)");
+    println!("  position import <portfolio> <path> - 导入券商交割单");
+    println!("  position reconcile <portfolio> <path> - 与券商持仓对账");
+    println!("  position paper <portfolio> <on|off> - 开启或关闭模拟交易");
+    println!("  position risk <portfolio> - 查看风控状态");
+    println!("  position exposure <portfolio> [top] - 查看行业、市值和板块暴露");
+    println!("  position margin <portfolio> [open|buy|short|cover|repay] ... - 融资融券: 查看状态、开通、融资买入、融券卖出、买券还券、还款");
+    println!("  position rebalance <portfolio> <code=weight,...> [execute] - 按目标权重调仓, 默认只预览");
+    println!("  position events <portfolio> - 查看组合事件记录");
+    println!("  position report <portfolio> <year> [csv_path] - 年度已实现收益和收入报告, 指定路径时导出CSV");
+    println!("  position cancel_tx <portfolio> <transaction_id> [reason] - 撤销交易");
+    println!("  position undo <portfolio> - 撤回最近一次操作");
+    println!("  order place <portfolio> <buy|sell> <code> <quantity> [limit_price] - 下单, 不指定价格为市价单");
+    println!("  order cancel <portfolio> <order_id> - 撤单");
+    println!("  order list <portfolio> [open] - 查询订单");
     println!("  strategy list           - 列出可用策略");
-    println!("  strategy run <name>     - 运行策略");
-    println!("  strategy backtest <name> - 回测策略");
+    println!("  strategy run <name> [portfolio] - 用最新行情运行策略并显示信号 (按组合持仓确定数量)");
+    println!("  strategy stop <name> [portfolio] - 停止运行中的策略");
+    println!("  strategy backtest <name> [start] [end] [capital] - 在历史快照上回测策略 (日期格式YYYY-MM-DD)");
+    println!("  strategy params <name>  - 查看策略参数及取值范围");
+    println!("  strategy walkforward <name> <is_bars> <oos_bars> <param ranges...> [--anchored] - 滚动前向分析, 对比样本内外表现");
+    println!("  strategy optimize <name> <param=min:max:step | param=v1,v2> ... [--random N] [--objective sharpe_ratio] - 并行回测参数组合并排名");
+    println!("  strategy set <name> <key=value> ... - 修改策略参数 (校验后生效)");
+    println!("  strategy indicators <code> - 在历史快照上计算技术指标序列");
+    println!("  strategy check-indicators [tolerance] - 核对自算指标与抓取的RSI/CCI/随机指标");
+    println!("  strategy pairs [min_correlation] [sector] [--cointegrated] - 筛选同行业的配对并做协整检验");
 }
 
 async fn check_server_status(client: &Client, base_url: &str) -> Result<(), Box<dyn std::error::Error>> {
@@ -189,7 +220,7 @@ async fn handle_position_command(client: &Client, base_url: &str, cmd: &str) ->
         let parts: Vec<&str> = cmd[14..].split_whitespace().collect();
         if parts.len() >= 2 {
             let name = parts[0];
-            let cash_balance: f64 = parts[1].parse()?;
+            let cash_balance: Money = parts[1].parse()?;
             
             let response = client.post(format!("{}/position/add_portfolio", base_url))
                 .json(&serde_json::json!({
@@ -230,7 +261,7 @@ async fn handle_position_command(client: &Client, base_url: &str, cmd: &str) ->
         if parts.len() >= 3 {
             let portfolio = parts[0];
             let code = parts[1];
-            let amount: f64 = parts[2].parse()?;
+            let amount: Shares = parts[2].parse()?;
             
             let response = client.post(format!("{}/position/add", base_url))
                 .json(&serde_json::json!({
@@ -253,7 +284,7 @@ async fn handle_position_command(client: &Client, base_url: &str, cmd: &str) ->
         if parts.len() >= 3 {
             let portfolio = parts[0];
             let code = parts[1];
-            let amount: f64 = parts[2].parse()?;
+            let amount: Shares = parts[2].parse()?;
             
             let response = client.post(format!("{}/position/remove", base_url))
                 .json(&serde_json::json!({
@@ -271,6 +302,372 @@ async fn handle_position_command(client: &Client, base_url: &str, cmd: &str) ->
         } else {
             println!("用法: position remove <portfolio> <code> <amount>");
         }
+    } else if let Some(args) = cmd.strip_prefix("load_actions ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if !parts.is_empty() {
+            let path = parts[0];
+            
+            let response = client.post(format!("{}/position/corporate_actions/load", base_url))
+                .json(&serde_json::json!({
+                    "path": path
+                }))
+                .send().await?;
+                
+            if response.status().is_success() {
+                let result: Value = response.json().await?;
+                println!("公司行动应用结果: {}", result);
+            } else {
+                println!("加载公司行动失败: {}", response.text().await?);
+            }
+        } else {
+            println!("用法: position load_actions <path>");
+        }
+    } else if let Some(args) = cmd.strip_prefix("deposit ").or_else(|| cmd.strip_prefix("withdraw ")) {
+        let action = if cmd.starts_with("deposit ") { "deposit" } else { "withdraw" };
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if parts.len() >= 2 {
+            let portfolio = parts[0];
+            let amount: Money = parts[1].parse()?;
+            
+            let response = client.post(format!("{}/position/cash/{}", base_url, action))
+                .json(&serde_json::json!({
+                    "portfolio": portfolio,
+                    "amount": amount
+                }))
+                .send().await?;
+                
+            if response.status().is_success() {
+                let result: Value = response.json().await?;
+                println!("资金操作成功: {}", result);
+            } else {
+                println!("资金操作失败: {}", response.text().await?);
+            }
+        } else {
+            println!("用法: position {} <portfolio> <amount>", action);
+        }
+    } else if let Some(args) = cmd.strip_prefix("cash_ledger ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if !parts.is_empty() {
+            let name = parts[0];
+            let response = client.get(format!("{}/position/cash/ledger", base_url))
+                .query(&[("name", name)])
+                .send().await?;
+            if response.status().is_success() {
+                let ledger: server::position::CashLedgerResponse = response.json().await?;
+                println!("现金余额: {}, 净投入: {}", ledger.cash_balance, ledger.net_contributions);
+                for entry in ledger.entries {
+                    println!("  {} {:?} {:.2} {}", entry.timestamp, entry.entry_type, entry.amount, entry.note.unwrap_or_default());
+                }
+            } else {
+                println!("查询现金流水失败: {}", response.text().await?);
+            }
+        } else {
+            println!("用法: position cash_ledger <portfolio>");
+        }
+    } else if let Some(args) = cmd.strip_prefix("performance ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if !parts.is_empty() {
+            let mut query = vec![("name", parts[0])];
+            if let Some(from) = parts.get(1) {
+                query.push(("from", from));
+            }
+            if let Some(to) = parts.get(2) {
+                query.push(("to", to));
+            }
+            
+            let response = client.get(format!("{}/position/performance", base_url))
+                .query(&query)
+                .send().await?;
+            if response.status().is_success() {
+                let report: Value = response.json().await?;
+                println!("组合绩效: {}", report);
+            } else {
+                println!("查询组合绩效失败: {}", response.text().await?);
+            }
+        } else {
+            println!("用法: position performance <portfolio> [from] [to]");
+        }
+    } else if let Some(args) = cmd.strip_prefix("import ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if parts.len() >= 2 {
+            let response = client.post(format!("{}/position/import", base_url))
+                .json(&serde_json::json!({
+                    "portfolio": parts[0],
+                    "path": parts[1]
+                }))
+                .send().await?;
+                
+            if response.status().is_success() {
+                let result: Value = response.json().await?;
+                println!("交割单导入结果: {}", result);
+            } else {
+                println!("导入交割单失败: {}", response.text().await?);
+            }
+        } else {
+            println!("用法: position import <portfolio> <path>");
+        }
+    } else if let Some(args) = cmd.strip_prefix("reconcile ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if parts.len() >= 2 {
+            let response = client.post(format!("{}/position/reconcile", base_url))
+                .json(&serde_json::json!({
+                    "portfolio": parts[0],
+                    "path": parts[1]
+                }))
+                .send().await?;
+                
+            if response.status().is_success() {
+                let report: Value = response.json().await?;
+                println!("对账结果: {}", report);
+            } else {
+                println!("对账失败: {}", response.text().await?);
+            }
+        } else {
+            println!("用法: position reconcile <portfolio> <path>");
+        }
+    } else if let Some(args) = cmd.strip_prefix("paper ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if parts.len() >= 2 {
+            let response = client.post(format!("{}/position/paper", base_url))
+                .json(&serde_json::json!({
+                    "name": parts[0],
+                    "enabled": parts[1] == "on"
+                }))
+                .send().await?;
+                
+            if response.status().is_success() {
+                let result: Value = response.json().await?;
+                println!("模拟交易设置成功: {}", result);
+            } else {
+                println!("模拟交易设置失败: {}", response.text().await?);
+            }
+        } else {
+            println!("用法: position paper <portfolio> <on|off>");
+        }
+    } else if let Some(args) = cmd.strip_prefix("risk ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if !parts.is_empty() {
+            let response = client.get(format!("{}/position/risk", base_url))
+                .query(&[("name", parts[0])])
+                .send().await?;
+            if response.status().is_success() {
+                let status: server::position::RiskStatusResponse = response.json().await?;
+                println!("当前回撤: {:.2}%, 当日成交: {:.2}", status.drawdown * 100.0, status.daily_turnover);
+                if status.breaches.is_empty() {
+                    println!("未发现风控违规");
+                }
+                for breach in status.breaches {
+                    println!("  {:?}: {}", breach.rule, breach.reason);
+                }
+            } else {
+                println!("查询风控状态失败: {}", response.text().await?);
+            }
+        } else {
+            println!("用法: position risk <portfolio>");
+        }
+    } else if let Some(args) = cmd.strip_prefix("exposure ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if !parts.is_empty() {
+            let mut query = vec![("name", parts[0])];
+            if let Some(top) = parts.get(1) {
+                query.push(("top", top));
+            }
+            let response = client.get(format!("{}/position/exposure", base_url))
+                .query(&query)
+                .send().await?;
+            if response.status().is_success() {
+                let report: position::exposure::ExposureReport = response.json().await?;
+                println!("总资产: {:.2}, 现金占比: {:.2}%", report.equity, report.cash_weight * 100.0);
+                for (title, buckets) in [("行业", &report.by_sector), ("市值", &report.by_market_cap), ("板块", &report.by_board)] {
+                    println!("{}:", title);
+                    for bucket in buckets {
+                        match bucket.active_weight {
+                            Some(active) => println!("  {} {:.2}% (相对股票池 {:+.2}%)", bucket.name, bucket.weight * 100.0, active * 100.0),
+                            None => println!("  {} {:.2}%", bucket.name, bucket.weight * 100.0),
+                        }
+                    }
+                }
+                println!("前{}大持仓合计: {:.2}%", report.top_holdings.len(), report.top_weight * 100.0);
+                for holding in report.top_holdings {
+                    println!("  {} {} {:.2}%", holding.code, holding.name, holding.weight * 100.0);
+                }
+                println!("赫芬达尔指数: {:.4}, 有效持股数: {:.1}", report.herfindahl, report.effective_holdings);
+            } else {
+                println!("查询暴露失败: {}", response.text().await?);
+            }
+        } else {
+            println!("用法: position exposure <portfolio> [top]");
+        }
+    } else if let Some(args) = cmd.strip_prefix("margin ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        let usage = "用法: position margin <portfolio> | open | <buy|short|cover> <code> <quantity> <price> | repay <amount>";
+        match parts.get(1).copied() {
+            None if !parts.is_empty() => {
+                let response = client.get(format!("{}/position/margin", base_url))
+                    .query(&[("name", parts[0])])
+                    .send().await?;
+                if response.status().is_success() {
+                    let status: position::margin::MarginStatus = response.json().await?;
+                    let ratio = status.maintenance_ratio.map(|r| format!("{:.2}%", r * 100.0)).unwrap_or_else(|| "-".to_string());
+                    println!("现金: {}, 多头市值: {:.2}, 融资负债: {}, 融券市值: {:.2}", status.cash, status.long_value, status.financing_debt, status.short_value);
+                    println!("维持担保比例: {}, 风险等级: {:?}, 保证金可用余额: {:.2}", ratio, status.level, status.available_margin);
+                    if status.shortfall > 0.0 {
+                        println!("需追加担保物: {:.2}", status.shortfall);
+                    }
+                    for short in status.shorts {
+                        println!("  融券 {} {}股 卖出均价 {} 现价 {:?}", short.code, short.amount, short.price, short.current_price);
+                    }
+                } else {
+                    println!("查询信用账户失败: {}", response.text().await?);
+                }
+            },
+            Some("open") => {
+                let response = client.post(format!("{}/position/margin/open", base_url))
+                    .json(&serde_json::json!({ "name": parts[0] }))
+                    .send().await?;
+                println!("开通融资融券: {}", response.text().await?);
+            },
+            Some(side @ ("buy" | "short" | "cover")) if parts.len() >= 5 => {
+                let quantity: Shares = parts[3].parse()?;
+                let price: Money = parts[4].parse()?;
+                let response = client.post(format!("{}/position/margin/{}", base_url, side))
+                    .json(&serde_json::json!({
+                        "portfolio": parts[0],
+                        "code": parts[2],
+                        "quantity": quantity,
+                        "price": price
+                    }))
+                    .send().await?;
+                println!("{}", response.text().await?);
+            },
+            Some("repay") if parts.len() >= 3 => {
+                let amount: Money = parts[2].parse()?;
+                let response = client.post(format!("{}/position/margin/repay", base_url))
+                    .json(&serde_json::json!({ "portfolio": parts[0], "amount": amount }))
+                    .send().await?;
+                println!("{}", response.text().await?);
+            },
+            _ => println!("{}", usage),
+        }
+    } else if let Some(args) = cmd.strip_prefix("rebalance ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if parts.len() >= 2 {
+            let mut targets = std::collections::HashMap::new();
+            for item in parts[1].split(',') {
+                if let Some((code, weight)) = item.split_once('=') {
+                    targets.insert(code.to_string(), weight.parse::<f64>()?);
+                }
+            }
+            
+            let response = client.post(format!("{}/position/rebalance", base_url))
+                .json(&serde_json::json!({
+                    "portfolio": parts[0],
+                    "targets": targets,
+                    "execute": parts.get(2) == Some(&"execute")
+                }))
+                .send().await?;
+                
+            if response.status().is_success() {
+                let result: server::position::RebalanceResponse = response.json().await?;
+                println!("总资产: {:.2}, 调仓后现金: {:.2}", result.plan.equity, result.plan.cash_after);
+                for trade in result.plan.trades {
+                    println!("  {:?} {} {} @ {:.2} ({:.2}% -> {:.2}%)", trade.side, trade.code, trade.quantity, trade.price, trade.current_weight * 100.0, trade.target_weight * 100.0);
+                }
+                for warning in result.plan.warnings {
+                    println!("  注意: {}", warning);
+                }
+                for execution in result.executions.iter().filter(|e| e.error.is_some()) {
+                    println!("  执行失败: {} {}", execution.code, execution.error.clone().unwrap_or_default());
+                }
+            } else {
+                println!("调仓失败: {}", response.text().await?);
+            }
+        } else {
+            println!("用法: position rebalance <portfolio> <code=weight,...> [execute]");
+        }
+    } else if let Some(args) = cmd.strip_prefix("report ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if parts.len() >= 2 {
+            let mut query = vec![("name", parts[0]), ("year", parts[1])];
+            if parts.len() >= 3 {
+                query.push(("format", "csv"));
+            }
+            let response = client.get(format!("{}/position/report/annual", base_url))
+                .query(&query)
+                .send().await?;
+            if !response.status().is_success() {
+                println!("生成年度报告失败: {}", response.text().await?);
+            } else if let Some(path) = parts.get(2) {
+                std::fs::write(path, response.text().await?)?;
+                println!("年度报告已导出到 {}", path);
+            } else {
+                let report: position::report::AnnualReport = response.json().await?;
+                println!("{}年 已实现盈亏: {}, 分红: {}, 利息: {}", report.year, report.realized_gain, report.dividend_income, report.interest);
+                println!("佣金: {}, 印花税: {}, 红利税: {}, 净收益: {}", report.fees, report.stamp_tax, report.dividend_tax, report.net_income);
+                for lot in report.realized {
+                    println!("  {} {}股 持有{}天 成本 {} 卖出 {} 盈亏 {}", lot.code, lot.quantity, lot.holding_days, lot.cost_basis, lot.proceeds, lot.gain);
+                }
+                for item in report.dividends {
+                    println!("  {} 分红 {} 税 {}", item.code, item.gross, item.tax);
+                }
+            }
+        } else {
+            println!("用法: position report <portfolio> <year> [csv_path]");
+        }
+    } else if let Some(args) = cmd.strip_prefix("events ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if !parts.is_empty() {
+            let response = client.get(format!("{}/position/events", base_url))
+                .query(&[("name", parts[0])])
+                .send().await?;
+            if response.status().is_success() {
+                let events: Vec<position::events::PortfolioEvent> = response.json().await?;
+                for event in events {
+                    println!("  {} {} {:?} {}", event.recorded_at, event.actor.unwrap_or_default(), event.kind, event.reason.unwrap_or_default());
+                }
+            } else {
+                println!("查询事件记录失败: {}", response.text().await?);
+            }
+        } else {
+            println!("用法: position events <portfolio>");
+        }
+    } else if let Some(args) = cmd.strip_prefix("cancel_tx ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if parts.len() >= 2 {
+            let response = client.post(format!("{}/position/transaction/cancel", base_url))
+                .json(&serde_json::json!({
+                    "portfolio": parts[0],
+                    "transaction_id": parts[1],
+                    "reason": parts.get(2)
+                }))
+                .send().await?;
+                
+            if response.status().is_success() {
+                println!("交易已撤销");
+            } else {
+                println!("撤销交易失败: {}", response.text().await?);
+            }
+        } else {
+            println!("用法: position cancel_tx <portfolio> <transaction_id> [reason]");
+        }
+    } else if let Some(args) = cmd.strip_prefix("undo ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if !parts.is_empty() {
+            let response = client.post(format!("{}/position/undo", base_url))
+                .json(&serde_json::json!({
+                    "portfolio": parts[0]
+                }))
+                .send().await?;
+                
+            if response.status().is_success() {
+                let result: Value = response.json().await?;
+                println!("撤回成功: {}", result);
+            } else {
+                println!("撤回失败: {}", response.text().await?);
+            }
+        } else {
+            println!("用法: position undo <portfolio>");
+        }
     } else {
         println!("未知的持仓命令");
     }
@@ -278,6 +675,139 @@ async fn handle_position_command(client: &Client, base_url: &str, cmd: &str) ->
     Ok(())
 }
 
+async fn handle_order_command(client: &Client, base_url: &str, cmd: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(args) = cmd.strip_prefix("place ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if parts.len() >= 4 {
+            let side = if parts[1] == "sell" { "Sell" } else { "Buy" };
+            let quantity: Shares = parts[3].parse()?;
+            let order_type = match parts.get(4) {
+                Some(price) => serde_json::json!({ "Limit": { "price": price.parse::<f64>()? } }),
+                None => serde_json::json!("Market"),
+            };
+            
+            let response = client.post(format!("{}/orders/place", base_url))
+                .json(&serde_json::json!({
+                    "portfolio": parts[0],
+                    "code": parts[2],
+                    "side": side,
+                    "order_type": order_type,
+                    "quantity": quantity
+                }))
+                .send().await?;
+                
+            if response.status().is_success() {
+                let result: Value = response.json().await?;
+                println!("下单成功: {}", result);
+            } else {
+                println!("下单失败: {}", response.text().await?);
+            }
+        } else {
+            println!("用法: order place <portfolio> <buy|sell> <code> <quantity> [limit_price]");
+        }
+    } else if let Some(args) = cmd.strip_prefix("cancel ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if parts.len() >= 2 {
+            let response = client.post(format!("{}/orders/cancel", base_url))
+                .json(&serde_json::json!({
+                    "portfolio": parts[0],
+                    "order_id": parts[1]
+                }))
+                .send().await?;
+                
+            if response.status().is_success() {
+                println!("撤单成功");
+            } else {
+                println!("撤单失败: {}", response.text().await?);
+            }
+        } else {
+            println!("用法: order cancel <portfolio> <order_id>");
+        }
+    } else if let Some(args) = cmd.strip_prefix("list ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if !parts.is_empty() {
+            let open = parts.get(1) == Some(&"open");
+            let response = client.get(format!("{}/orders/list", base_url))
+                .query(&[("portfolio", parts[0]), ("open", if open { "true" } else { "false" })])
+                .send().await?;
+            if response.status().is_success() {
+                let result: server::orders::ListOrdersResponse = response.json().await?;
+                for order in result.orders {
+                    println!("  {} {} {:?} {:?} {}/{} {:?}", order.id, order.code, order.side, order.order_type, order.filled_quantity, order.quantity, order.status);
+                }
+            } else {
+                println!("查询订单失败: {}", response.text().await?);
+            }
+        } else {
+            println!("用法: order list <portfolio> [open]");
+        }
+    } else {
+        println!("未知的订单命令");
+    }
+    
+    Ok(())
+}
+
+// 参数搜索设置
+struct SearchArgs {
+    ranges: serde_json::Map<String, Value>,
+    method: Value,
+    objective: Value,
+    anchored: bool,
+}
+
+// 解析参数范围: param=min:max[:step] 或 param=v1,v2, 以及 --random N, --objective name, --anchored
+fn parse_search_args(args: &[&str]) -> Result<SearchArgs, String> {
+    let mut search = SearchArgs {
+        ranges: serde_json::Map::new(),
+        method: serde_json::json!({ "type": "grid" }),
+        objective: Value::Null,
+        anchored: false,
+    };
+    let mut i = 0;
+    while i < args.len() {
+        match args[i] {
+            "--random" => {
+                let samples: usize = args.get(i + 1).and_then(|n| n.parse().ok()).ok_or("--random需要样本数")?;
+                search.method = serde_json::json!({ "type": "random", "samples": samples });
+                i += 2;
+                continue;
+            },
+            "--objective" => {
+                search.objective = Value::String(args.get(i + 1).ok_or("--objective需要优化目标")?.to_string());
+                i += 2;
+                continue;
+            },
+            "--anchored" => {
+                search.anchored = true;
+                i += 1;
+                continue;
+            },
+            _ => {},
+        }
+        let (key, spec) = args[i].split_once('=').ok_or_else(|| format!("参数范围格式错误: {}", args[i]))?;
+        let range = if spec.contains(':') {
+            let bounds: Vec<f64> = spec.split(':').map(|v| v.parse::<f64>()).collect::<Result<_, _>>()
+                .map_err(|_| format!("参数范围格式错误: {}", args[i]))?;
+            if bounds.len() < 2 {
+                return Err(format!("参数范围格式错误: {}, 应为min:max[:step]", args[i]));
+            }
+            serde_json::json!({ "type": "range", "min": bounds[0], "max": bounds[1], "step": bounds.get(2) })
+        } else {
+            let values: Vec<Value> = spec.split(',')
+                .map(|v| serde_json::from_str(v).unwrap_or_else(|_| Value::String(v.to_string())))
+                .collect();
+            serde_json::json!({ "type": "values", "values": values })
+        };
+        search.ranges.insert(key.to_string(), range);
+        i += 1;
+    }
+    if search.ranges.is_empty() {
+        return Err("没有指定参数范围".to_string());
+    }
+    Ok(search)
+}
+
 async fn handle_strategy_command(client: &Client, base_url: &str, cmd: &str) -> Result<(), Box<dyn std::error::Error>> {
     if cmd == "list" {
         let response = client.get(format!("{}/strategy/list", base_url)).send().await?;
@@ -287,35 +817,340 @@ async fn handle_strategy_command(client: &Client, base_url: &str, cmd: &str) ->
         } else {
             println!("获取策略列表失败: {}", response.text().await?);
         }
-    } else if cmd.starts_with("run ") {
-        let strategy_name = &cmd[4..];
+    } else if let Some(args) = cmd.strip_prefix("run ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if parts.is_empty() {
+            println!("用法: strategy run <name> [portfolio]");
+            return Ok(());
+        }
         
         let response = client.post(format!("{}/strategy/run", base_url))
             .json(&serde_json::json!({
-                "name": strategy_name
+                "name": parts[0],
+                "portfolio": parts.get(1)
             }))
             .send().await?;
             
         if response.status().is_success() {
-            println!("策略运行成功");
+            let result: Value = response.json().await?;
+            println!("{}", result["message"].as_str().unwrap_or("策略运行成功"));
+            for signal in result["signals"].as_array().into_iter().flatten() {
+                println!("  {} {} 数量: {} 价格: {} ({})",
+                    signal["action"].as_str().unwrap_or(""),
+                    signal["code"].as_str().unwrap_or(""),
+                    signal["amount"],
+                    signal["price"],
+                    signal["reason"].as_str().unwrap_or(""));
+            }
         } else {
             println!("策略运行失败: {}", response.text().await?);
         }
-    } else if cmd.starts_with("backtest ") {
-        let strategy_name = &cmd[9..];
+    } else if let Some(args) = cmd.strip_prefix("stop ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if parts.is_empty() {
+            println!("用法: strategy stop <name> [portfolio]");
+            return Ok(());
+        }
+
+        let response = client.post(format!("{}/strategy/stop", base_url))
+            .json(&serde_json::json!({
+                "name": parts[0],
+                "portfolio": parts.get(1)
+            }))
+            .send().await?;
+
+        if response.status().is_success() {
+            println!("策略已停止");
+        } else {
+            println!("停止策略失败: {}", response.text().await?);
+        }
+    } else if let Some(args) = cmd.strip_prefix("backtest ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if parts.is_empty() {
+            println!("用法: strategy backtest <name> [start] [end] [capital]");
+            return Ok(());
+        }
+        let initial_capital = match parts.get(3) {
+            Some(capital) => Some(capital.parse::<f64>()?),
+            None => None,
+        };
         
         let response = client.post(format!("{}/strategy/backtest", base_url))
             .json(&serde_json::json!({
-                "name": strategy_name
+                "name": parts[0],
+                "start_date": parts.get(1),
+                "end_date": parts.get(2),
+                "initial_capital": initial_capital
             }))
             .send().await?;
             
         if response.status().is_success() {
-            let result: Value = response.json().await?;
-            println!("回测结果: {}", result);
+            let result: strategy::models::BacktestResult = response.json().await?;
+            println!("回测区间: {} - {}", result.start_date, result.end_date);
+            println!("期末资金: {:.2}, 总收益: {:.2}%, 年化: {:.2}%, 夏普: {:.2}, 最大回撤: {:.2}%",
+                result.final_capital, result.total_return * 100.0, result.annualized_return * 100.0, result.sharpe_ratio, result.max_drawdown * 100.0);
+            println!("索提诺: {:.2}, 卡玛: {:.2}, 最长回撤: {}天, 持仓时间: {:.2}%, 年化换手: {:.2}",
+                result.sortino_ratio, result.calmar_ratio, result.max_drawdown_duration, result.exposure * 100.0, result.turnover);
+            println!("交易次数: {}, 胜率: {:.2}%, 盈亏比: {:.2}", result.total_trades, result.win_rate * 100.0, result.profit_factor);
+            for trade in result.trades {
+                match trade.exit_date {
+                    Some(exit_date) => println!("  {} {} -> {} {}股 盈亏 {:.2} {}", trade.code, trade.entry_date.date_naive(), exit_date.date_naive(), trade.entry_amount, trade.profit_loss.unwrap_or(0.0), trade.exit_reason.unwrap_or_default()),
+                    None => println!("  {} {} 持有中 {}股 @ {:.2}", trade.code, trade.entry_date.date_naive(), trade.entry_amount, trade.entry_price),
+                }
+            }
         } else {
             println!("策略回测失败: {}", response.text().await?);
         }
+    } else if let Some(args) = cmd.strip_prefix("optimize ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if parts.len() < 2 {
+            println!("用法: strategy optimize <name> <param=min:max:step | param=v1,v2> ... [--random N] [--objective sharpe_ratio]");
+            return Ok(());
+        }
+
+        let search = match parse_search_args(&parts[1..]) {
+            Ok(search) => search,
+            Err(e) => {
+                println!("{}", e);
+                return Ok(());
+            }
+        };
+
+        let response = client.post(format!("{}/strategy/optimize", base_url))
+            .json(&serde_json::json!({
+                "name": parts[0],
+                "ranges": search.ranges,
+                "method": search.method,
+                "objective": search.objective,
+            }))
+            .send().await?;
+        if !response.status().is_success() {
+            println!("参数优化失败: {}", response.text().await?);
+            return Ok(());
+        }
+        let started: Value = response.json().await?;
+        let job_id = started["job_id"].as_str().unwrap_or_default().to_string();
+        println!("参数优化任务 {} 已启动, 共 {} 组参数", job_id, started["total"]);
+
+        // 轮询进度直到完成
+        let result = loop {
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            let response = client.get(format!("{}/strategy/optimize/{}", base_url, job_id)).send().await?;
+            if !response.status().is_success() {
+                println!("查询优化进度失败: {}", response.text().await?);
+                return Ok(());
+            }
+            let result: Value = response.json().await?;
+            println!("进度: {}/{}", result["completed"], result["total"]);
+            if result["status"] == "finished" {
+                break result;
+            }
+        };
+
+        println!("{:<6} {:>10} {:>10} {:>10} {:>10} {:>6}  参数", "排名", "目标", "总收益", "夏普", "最大回撤", "交易");
+        for run in result["results"].as_array().into_iter().flatten() {
+            if let Some(error) = run["error"].as_str() {
+                println!("{:<6} 回测失败: {}  {}", run["rank"], error, run["params"]);
+                continue;
+            }
+            println!("{:<6} {:>10.4} {:>9.2}% {:>10.2} {:>9.2}% {:>6}  {}",
+                run["rank"],
+                run["score"].as_f64().unwrap_or(0.0),
+                run["total_return"].as_f64().unwrap_or(0.0) * 100.0,
+                run["sharpe_ratio"].as_f64().unwrap_or(0.0),
+                run["max_drawdown"].as_f64().unwrap_or(0.0) * 100.0,
+                run["total_trades"],
+                run["params"]);
+        }
+    } else if let Some(args) = cmd.strip_prefix("walkforward ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        let usage = "用法: strategy walkforward <name> <in_sample_bars> <out_of_sample_bars> <param=min:max:step | param=v1,v2> ... [--anchored] [--random N] [--objective sharpe_ratio]";
+        if parts.len() < 4 {
+            println!("{}", usage);
+            return Ok(());
+        }
+        let (in_sample_bars, out_of_sample_bars) = match (parts[1].parse::<usize>(), parts[2].parse::<usize>()) {
+            (Ok(is), Ok(oos)) => (is, oos),
+            _ => {
+                println!("{}", usage);
+                return Ok(());
+            }
+        };
+        let search = match parse_search_args(&parts[3..]) {
+            Ok(search) => search,
+            Err(e) => {
+                println!("{}", e);
+                return Ok(());
+            }
+        };
+
+        println!("滚动前向分析中...");
+        let response = client.post(format!("{}/strategy/walkforward", base_url))
+            .json(&serde_json::json!({
+                "name": parts[0],
+                "in_sample_bars": in_sample_bars,
+                "out_of_sample_bars": out_of_sample_bars,
+                "anchored": search.anchored,
+                "ranges": search.ranges,
+                "method": search.method,
+                "objective": search.objective,
+            }))
+            .send().await?;
+
+        if response.status().is_success() {
+            let result: Value = response.json().await?;
+            println!("{:<4} {:<12} {:<12} {:>10} {:>10} {:>9} {:>9}  参数", "窗口", "样本外开始", "样本外结束", "样本内目标", "样本外目标", "样本内收益", "样本外收益");
+            for (i, window) in result["windows"].as_array().into_iter().flatten().enumerate() {
+                println!("{:<4} {:<12} {:<12} {:>10.4} {:>10.4} {:>8.2}% {:>8.2}%  {}",
+                    i + 1,
+                    window["out_of_sample_start"].as_str().unwrap_or("").get(..10).unwrap_or(""),
+                    window["out_of_sample_end"].as_str().unwrap_or("").get(..10).unwrap_or(""),
+                    window["in_sample_score"].as_f64().unwrap_or(0.0),
+                    window["out_of_sample_score"].as_f64().unwrap_or(0.0),
+                    window["in_sample_return"].as_f64().unwrap_or(0.0) * 100.0,
+                    window["out_of_sample_return"].as_f64().unwrap_or(0.0) * 100.0,
+                    window["params"]);
+            }
+            let metrics = &result["out_of_sample"];
+            println!("样本外总收益: {:.2}%, 年化: {:.2}%, 夏普: {:.2}, 最大回撤: {:.2}%",
+                metrics["total_return"].as_f64().unwrap_or(0.0) * 100.0,
+                metrics["annualized_return"].as_f64().unwrap_or(0.0) * 100.0,
+                metrics["sharpe_ratio"].as_f64().unwrap_or(0.0),
+                metrics["max_drawdown"].as_f64().unwrap_or(0.0) * 100.0);
+            println!("平均目标 样本内: {:.4}, 样本外: {:.4}, 前向效率: {:.2}, 盈利窗口: {}/{}, 不同参数组: {}",
+                result["mean_in_sample_score"].as_f64().unwrap_or(0.0),
+                result["mean_out_of_sample_score"].as_f64().unwrap_or(0.0),
+                result["efficiency"].as_f64().unwrap_or(0.0),
+                result["profitable_windows"],
+                result["windows"].as_array().map(|w| w.len()).unwrap_or(0),
+                result["distinct_param_sets"]);
+        } else {
+            println!("滚动前向分析失败: {}", response.text().await?);
+        }
+    } else if let Some(name) = cmd.strip_prefix("params ") {
+        let response = client.get(format!("{}/strategy/{}/params", base_url, name.trim())).send().await?;
+
+        if response.status().is_success() {
+            let result: Value = response.json().await?;
+            println!("策略 {} 参数:", name.trim());
+            for spec in result["schema"].as_array().into_iter().flatten() {
+                let key = spec["name"].as_str().unwrap_or("");
+                println!("  {:<20} = {:<8} 范围 [{}, {}] 默认 {} - {}",
+                    key, result["params"][key].to_string(), spec["min"], spec["max"], spec["default"],
+                    spec["description"].as_str().unwrap_or(""));
+            }
+        } else {
+            println!("获取策略参数失败: {}", response.text().await?);
+        }
+    } else if let Some(args) = cmd.strip_prefix("set ") {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        if parts.len() < 2 {
+            println!("用法: strategy set <name> <key=value> ...");
+            return Ok(());
+        }
+        let mut params = serde_json::Map::new();
+        for pair in &parts[1..] {
+            let (key, value) = match pair.split_once('=') {
+                Some(pair) => pair,
+                None => {
+                    println!("参数格式错误: {}, 应为key=value", pair);
+                    return Ok(());
+                }
+            };
+            // 数字和布尔值按JSON解析, 其余按字符串传递
+            let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+            params.insert(key.to_string(), value);
+        }
+
+        let response = client.put(format!("{}/strategy/{}/params", base_url, parts[0]))
+            .json(&serde_json::json!({ "params": params }))
+            .send().await?;
+
+        if response.status().is_success() {
+            let result: Value = response.json().await?;
+            println!("{}", result["message"].as_str().unwrap_or("策略参数已更新"));
+        } else {
+            println!("修改策略参数失败: {}", response.text().await?);
+        }
+    } else if let Some(code) = cmd.strip_prefix("indicators ") {
+        let response = client.get(format!("{}/strategy/indicators", base_url))
+            .query(&[("code", code.trim())])
+            .send().await?;
+
+        if response.status().is_success() {
+            let result: Value = response.json().await?;
+            println!("{:<22} {:>10} {:>10} {:>8} {:>10} {:>8} {:>8}", "时间", "收盘", "SMA20", "RSI14", "MACD", "K", "CCI20");
+            for row in result["series"].as_array().into_iter().flatten() {
+                let format = |v: &Value| v.as_f64().map(|v| format!("{:.2}", v)).unwrap_or_else(|| "-".to_string());
+                println!("{:<22} {:>10} {:>10} {:>8} {:>10} {:>8} {:>8}",
+                    row["timestamp"].as_str().unwrap_or(""),
+                    format(&row["close"]),
+                    format(&row["sma_20"]),
+                    format(&row["rsi_14"]),
+                    format(&row["macd"]["macd"]),
+                    format(&row["stochastic"]["k"]),
+                    format(&row["cci_20"]));
+            }
+        } else {
+            println!("计算技术指标失败: {}", response.text().await?);
+        }
+    } else if cmd == "pairs" || cmd.starts_with("pairs ") {
+        let mut query: Vec<(&str, &str)> = Vec::new();
+        for arg in cmd.split_whitespace().skip(1) {
+            if arg == "--cointegrated" {
+                query.push(("cointegrated", "true"));
+            } else if arg.parse::<f64>().is_ok() {
+                query.push(("min_correlation", arg));
+            } else {
+                query.push(("sector", arg));
+            }
+        }
+        let response = client.get(format!("{}/strategy/pairs", base_url))
+            .query(&query)
+            .send().await?;
+
+        if response.status().is_success() {
+            let result: Value = response.json().await?;
+            println!("共 {} 个候选配对", result["total"]);
+            println!("{:<10} {:<10} {:<12} {:>8} {:>8} {:>8} {:>8} {:>8}", "股票A", "股票B", "行业", "相关系数", "对冲比例", "ADF", "显著性", "半衰期");
+            for pair in result["pairs"].as_array().into_iter().flatten() {
+                let test = &pair["cointegration"];
+                let format = |v: &Value| v.as_f64().map(|v| format!("{:.2}", v)).unwrap_or_else(|| "-".to_string());
+                println!("{:<10} {:<10} {:<12} {:>8} {:>8} {:>8} {:>8} {:>8}",
+                    pair["stock_a"].as_str().unwrap_or(""),
+                    pair["stock_b"].as_str().unwrap_or(""),
+                    pair["sector"].as_str().unwrap_or(""),
+                    format(&pair["correlation"]),
+                    format(&test["hedge_ratio"]),
+                    format(&test["adf_statistic"]),
+                    format(&test["significance"]),
+                    format(&test["half_life"]));
+            }
+        } else {
+            println!("筛选配对失败: {}", response.text().await?);
+        }
+    } else if cmd == "check-indicators" || cmd.starts_with("check-indicators ") {
+        let mut request = client.get(format!("{}/strategy/indicators/check", base_url));
+        if let Some(tolerance) = cmd.split_whitespace().nth(1) {
+            request = request.query(&[("tolerance", tolerance)]);
+        }
+        let response = request.send().await?;
+
+        if response.status().is_success() {
+            let result: Value = response.json().await?;
+            println!("核对指标 {} 个, 偏差超过 {} 的有 {} 个",
+                result["checked"], result["tolerance"], result["mismatched"]);
+            for check in result["mismatches"].as_array().into_iter().flatten() {
+                println!("  {} {}: 计算值 {:.2}, 抓取值 {:.2}, 样本 {} 天",
+                    check["code"].as_str().unwrap_or(""),
+                    check["indicator"].as_str().unwrap_or(""),
+                    check["computed"].as_f64().unwrap_or(0.0),
+                    check["scraped"].as_f64().unwrap_or(0.0),
+                    check["bars"]);
+            }
+        } else {
+            println!("核对技术指标失败: {}", response.text().await?);
+        }
     } else {
         println!("未知的策略命令");
     }
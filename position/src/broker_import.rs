@@ -0,0 +1,559 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use std::collections::HashMap;
+use crate::market::{exchange_offset, normalize_code};
+use crate::cash::{CashEntry, CashEntryType};
+use crate::events::PortfolioEventKind;
+use crate::models::{Portfolio, Transaction, TransactionType};
+use crate::money::{deserialize_shares, round_money, Money, Shares};
+use rust_decimal::prelude::ToPrimitive;
+
+// 交割单列映射
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    // 成交日期列
+    pub date: String,
+    // 成交时间列
+    pub time: Option<String>,
+    // 证券代码列
+    pub code: String,
+    // 证券名称列
+    pub name: Option<String>,
+    // 买卖方向列
+    pub side: String,
+    // 成交数量列
+    pub quantity: String,
+    // 成交价格列
+    pub price: String,
+    // 佣金列
+    pub commission: Option<String>,
+    // 印花税列
+    pub stamp_tax: Option<String>,
+    // 过户费列
+    pub transfer_fee: Option<String>,
+    // 其他费用列
+    pub other_fee: Option<String>,
+    // 成交编号列
+    pub trade_id: Option<String>,
+    // 日期格式
+    pub date_format: String,
+    // 买入关键字
+    pub buy_keywords: Vec<String>,
+    // 卖出关键字
+    pub sell_keywords: Vec<String>,
+    // 发生金额列, 用于银证转账
+    #[serde(default)]
+    pub cash_amount: Option<String>,
+    // 银行转入证券账户的关键字
+    #[serde(default)]
+    pub deposit_keywords: Vec<String>,
+    // 证券账户转出到银行的关键字
+    #[serde(default)]
+    pub withdrawal_keywords: Vec<String>,
+}
+
+impl Default for ColumnMapping {
+    // 常见券商交割单格式
+    fn default() -> Self {
+        Self {
+            date: "成交日期".to_string(),
+            time: Some("成交时间".to_string()),
+            code: "证券代码".to_string(),
+            name: Some("证券名称".to_string()),
+            side: "操作".to_string(),
+            quantity: "成交数量".to_string(),
+            price: "成交均价".to_string(),
+            commission: Some("手续费".to_string()),
+            stamp_tax: Some("印花税".to_string()),
+            transfer_fee: Some("过户费".to_string()),
+            other_fee: Some("其他杂费".to_string()),
+            trade_id: Some("成交编号".to_string()),
+            date_format: "%Y%m%d".to_string(),
+            buy_keywords: vec!["买入".to_string()],
+            sell_keywords: vec!["卖出".to_string()],
+            cash_amount: Some("成交金额".to_string()),
+            deposit_keywords: vec!["银行转证券".to_string(), "银证转入".to_string()],
+            withdrawal_keywords: vec!["证券转银行".to_string(), "银证转出".to_string()],
+        }
+    }
+}
+
+// 持仓文件列映射
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HoldingsMapping {
+    // 证券代码列
+    pub code: String,
+    // 证券名称列
+    pub name: Option<String>,
+    // 持仓数量列
+    pub quantity: String,
+    // 成本价列
+    pub cost: Option<String>,
+}
+
+impl Default for HoldingsMapping {
+    fn default() -> Self {
+        Self {
+            code: "证券代码".to_string(),
+            name: Some("证券名称".to_string()),
+            quantity: "股票余额".to_string(),
+            cost: Some("成本价".to_string()),
+        }
+    }
+}
+
+// 券商成交记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrokerTrade {
+    pub trade_id: String,
+    pub code: String,
+    pub name: Option<String>,
+    pub transaction_type: TransactionType,
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub quantity: Shares,
+    pub price: Money,
+    pub fee: Money,
+    pub tax: Money,
+    pub timestamp: DateTime<Utc>,
+}
+
+// 券商银证转账记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrokerCashTransfer {
+    pub transfer_id: String,
+    // 金额, 正数为转入, 负数为转出
+    pub amount: Money,
+    pub timestamp: DateTime<Utc>,
+}
+
+// 解析后的交割单
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BrokerStatement {
+    pub trades: Vec<BrokerTrade>,
+    // 银证转账
+    #[serde(default)]
+    pub transfers: Vec<BrokerCashTransfer>,
+    // 其他非买卖记录 (如红利入账) 的行数
+    pub skipped: usize,
+}
+
+// 券商持仓记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrokerHolding {
+    pub code: String,
+    pub name: Option<String>,
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub quantity: Shares,
+    pub cost: Option<Money>,
+}
+
+// 导入结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    // 导入的银证转账笔数
+    #[serde(default)]
+    pub transfers: usize,
+    pub duplicates: usize,
+    pub skipped: usize,
+    pub errors: Vec<String>,
+}
+
+// 对账时成本价允许的差异 (券商成本价通常保留三位小数)
+const COST_TOLERANCE: Money = Money::from_parts(1, 0, 0, false, 2);
+
+// 对账状态
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ReconciliationStatus {
+    Matched,
+    QuantityMismatch,
+    // 数量一致但成本价差异超过容差
+    CostMismatch,
+    MissingInBook,
+    MissingAtBroker,
+}
+
+// 单只证券的对账结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationItem {
+    pub code: String,
+    pub name: Option<String>,
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub broker_quantity: Shares,
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub book_quantity: Shares,
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub difference: Shares,
+    pub broker_cost: Option<Money>,
+    pub book_cost: Option<Money>,
+    pub status: ReconciliationStatus,
+}
+
+// 对账报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationReport {
+    pub portfolio: String,
+    pub matched: bool,
+    pub items: Vec<ReconciliationItem>,
+}
+
+// 表格中的一行, 按列名索引
+type Row = HashMap<String, String>;
+
+// 读取表格内容, 自动识别制表符或逗号分隔
+fn read_rows(content: &str) -> Result<(Vec<String>, Vec<Row>), String> {
+    let content = content.trim_start_matches('\u{feff}');
+    let header_line = content.lines().next().unwrap_or("");
+    let delimiter = if header_line.contains('\t') { b'\t' } else { b',' };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+
+    let headers: Vec<String> = reader.headers()
+        .map_err(|e| format!("读取表头失败: {}", e))?
+        .iter()
+        .map(|h| h.to_string())
+        .collect();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("读取数据行失败: {}", e))?;
+        let row: Row = headers.iter().cloned()
+            .zip(record.iter().map(|v| v.trim_start_matches('=').trim_matches('"').to_string()))
+            .collect();
+        rows.push(row);
+    }
+
+    Ok((headers, rows))
+}
+
+fn require_column(headers: &[String], column: &str) -> Result<(), String> {
+    if headers.iter().any(|h| h == column) {
+        Ok(())
+    } else {
+        Err(format!("缺少列: {}", column))
+    }
+}
+
+// 按文本直接解析为十进制, 避免浮点误差
+fn parse_number(value: Option<&String>) -> Money {
+    value.and_then(|v| v.trim().replace(',', "").parse::<Money>().ok()).unwrap_or_default()
+}
+
+fn optional_number(row: &Row, column: &Option<String>) -> Money {
+    column.as_ref().map(|c| parse_number(row.get(c))).unwrap_or_default()
+}
+
+fn parse_shares(value: Option<&String>) -> Shares {
+    parse_number(value).abs().trunc().to_i64().unwrap_or(0)
+}
+
+// 交割单中一行的类型: 买卖成交或银证转账 (金额方向)
+enum RowKind {
+    Trade(TransactionType),
+    Transfer(Money),
+}
+
+// 解析交割单
+pub fn parse_statement(content: &str, mapping: &ColumnMapping) -> Result<BrokerStatement, String> {
+    let (headers, rows) = read_rows(content)?;
+    for column in [&mapping.date, &mapping.code, &mapping.side, &mapping.quantity, &mapping.price] {
+        require_column(&headers, column)?;
+    }
+
+    let mut statement = BrokerStatement::default();
+
+    for (index, row) in rows.iter().enumerate() {
+        let side = row.get(&mapping.side).cloned().unwrap_or_default();
+        let matches = |keywords: &[String]| keywords.iter().any(|k| side.contains(k.as_str()));
+        let kind = if matches(&mapping.buy_keywords) {
+            RowKind::Trade(TransactionType::Buy)
+        } else if matches(&mapping.sell_keywords) {
+            RowKind::Trade(TransactionType::Sell)
+        } else if matches(&mapping.deposit_keywords) {
+            RowKind::Transfer(Money::ONE)
+        } else if matches(&mapping.withdrawal_keywords) {
+            RowKind::Transfer(-Money::ONE)
+        } else {
+            statement.skipped += 1;
+            continue;
+        };
+
+        let date_str = row.get(&mapping.date).cloned().unwrap_or_default();
+        let date = NaiveDate::parse_from_str(&date_str, &mapping.date_format)
+            .map_err(|e| format!("第{}行日期格式错误: {}: {}", index + 2, date_str, e))?;
+        let time = mapping.time.as_ref()
+            .and_then(|c| row.get(c))
+            .and_then(|t| NaiveTime::parse_from_str(t, "%H:%M:%S").ok())
+            .unwrap_or_else(|| NaiveTime::from_hms_opt(15, 0, 0).unwrap());
+        let timestamp = exchange_offset()
+            .from_local_datetime(&NaiveDateTime::new(date, time))
+            .unwrap()
+            .with_timezone(&Utc);
+        let row_id = mapping.trade_id.as_ref()
+            .and_then(|c| row.get(c))
+            .filter(|id| !id.is_empty())
+            .map(|id| format!("{}-{}", date_str, id));
+
+        let transaction_type = match kind {
+            RowKind::Trade(transaction_type) => transaction_type,
+            RowKind::Transfer(sign) => {
+                let amount = optional_number(row, &mapping.cash_amount).abs() * sign;
+                if amount.is_zero() {
+                    statement.skipped += 1;
+                } else {
+                    statement.transfers.push(BrokerCashTransfer {
+                        // 没有流水号时加上行号, 同一时间的相同金额转账不会被当作重复
+                        transfer_id: row_id.unwrap_or_else(|| format!("{}-{}-转账-{}-{}", date_str, time, amount, index + 2)),
+                        amount,
+                        timestamp,
+                    });
+                }
+                continue;
+            },
+        };
+
+        let code = normalize_code(row.get(&mapping.code).map(|c| c.as_str()).unwrap_or(""));
+        let quantity = parse_shares(row.get(&mapping.quantity));
+        let price = parse_number(row.get(&mapping.price));
+        if code.is_empty() || quantity <= 0 {
+            statement.skipped += 1;
+            continue;
+        }
+
+        // 没有成交编号时按成交要素生成
+        let trade_id = row_id
+            .unwrap_or_else(|| format!("{}-{}-{}-{:?}-{}-{}", date_str, time, code, transaction_type, quantity, price));
+
+        statement.trades.push(BrokerTrade {
+            trade_id,
+            code,
+            name: mapping.name.as_ref().and_then(|c| row.get(c)).cloned(),
+            transaction_type,
+            quantity,
+            price,
+            fee: optional_number(row, &mapping.commission)
+                + optional_number(row, &mapping.transfer_fee)
+                + optional_number(row, &mapping.other_fee),
+            tax: optional_number(row, &mapping.stamp_tax),
+            timestamp,
+        });
+    }
+
+    Ok(statement)
+}
+
+// 解析券商持仓文件
+pub fn parse_holdings(content: &str, mapping: &HoldingsMapping) -> Result<Vec<BrokerHolding>, String> {
+    let (headers, rows) = read_rows(content)?;
+    require_column(&headers, &mapping.code)?;
+    require_column(&headers, &mapping.quantity)?;
+
+    Ok(rows.iter()
+        .map(|row| BrokerHolding {
+            code: normalize_code(row.get(&mapping.code).map(|c| c.as_str()).unwrap_or("")),
+            name: mapping.name.as_ref().and_then(|c| row.get(c)).cloned(),
+            quantity: parse_shares(row.get(&mapping.quantity)),
+            cost: mapping.cost.as_ref().and_then(|c| row.get(c)).and_then(|v| v.trim().replace(',', "").parse().ok()),
+        })
+        .filter(|h| !h.code.is_empty())
+        .collect())
+}
+
+impl Portfolio {
+    // 导入券商成交, 已导入的成交编号会被跳过
+    pub fn import_trades(&mut self, statement: &BrokerStatement) -> ImportReport {
+        let mut report = ImportReport {
+            skipped: statement.skipped,
+            ..Default::default()
+        };
+
+        let mut trades: Vec<&BrokerTrade> = statement.trades.iter().collect();
+        trades.sort_by_key(|t| t.timestamp);
+        let mut transfers: Vec<&BrokerCashTransfer> = statement.transfers.iter().collect();
+        transfers.sort_by_key(|t| t.timestamp);
+        let mut transfers = transfers.into_iter().peekable();
+
+        for trade in trades {
+            // 成交前的银证转账先入账, 保证买入时现金充足
+            while let Some(transfer) = transfers.next_if(|t| t.timestamp <= trade.timestamp) {
+                self.import_transfer(transfer, &mut report);
+            }
+            if self.imported_trades.contains(&trade.trade_id) {
+                report.duplicates += 1;
+                continue;
+            }
+
+            let mut transaction = Transaction::new(trade.code.clone(), trade.transaction_type, trade.quantity, trade.price);
+            transaction.timestamp = trade.timestamp;
+            transaction.fee = Some(trade.fee);
+            transaction.tax = Some(trade.tax);
+            transaction.external_id = Some(trade.trade_id.clone());
+            transaction.note = Some("券商交割单导入".to_string());
+
+            match self.record_transaction(transaction) {
+                Ok(_) => {
+                    if let (Some(name), Some(position)) = (&trade.name, self.positions.get_mut(&trade.code)) {
+                        position.name = name.clone();
+                    }
+                    self.imported_trades.insert(trade.trade_id.clone());
+                    report.imported += 1;
+                },
+                Err(e) => report.errors.push(format!("{} {}: {}", trade.trade_id, trade.code, e)),
+            }
+        }
+
+        for transfer in transfers {
+            self.import_transfer(transfer, &mut report);
+        }
+
+        report
+    }
+
+    // 导入银证转账为入金或出金流水
+    fn import_transfer(&mut self, transfer: &BrokerCashTransfer, report: &mut ImportReport) {
+        if self.imported_trades.contains(&transfer.transfer_id) {
+            report.duplicates += 1;
+            return;
+        }
+        if transfer.amount < Money::ZERO && -transfer.amount > self.available_cash() {
+            report.errors.push(format!("{} 银证转账: 现金余额不足", transfer.transfer_id));
+            return;
+        }
+
+        let entry_type = if transfer.amount > Money::ZERO { CashEntryType::Deposit } else { CashEntryType::Withdrawal };
+        let mut entry = CashEntry::new(entry_type, round_money(transfer.amount));
+        entry.timestamp = transfer.timestamp;
+        entry.reference = Some(transfer.transfer_id.clone());
+        entry.note = Some("券商交割单导入".to_string());
+        self.cash_ledger.record(entry.clone());
+        self.log_event(PortfolioEventKind::Cash { entry });
+        self.imported_trades.insert(transfer.transfer_id.clone());
+        report.transfers += 1;
+    }
+
+    // 与券商持仓对账
+    pub fn reconcile(&self, holdings: &[BrokerHolding]) -> ReconciliationReport {
+        let mut items = Vec::new();
+
+        for holding in holdings {
+            let position = self.positions.get(&holding.code);
+            let book_quantity = position.map(|p| p.amount).unwrap_or(0);
+            let cost_differs = match (holding.cost, position) {
+                (Some(broker_cost), Some(position)) => (broker_cost - position.cost).abs() > COST_TOLERANCE,
+                _ => false,
+            };
+            let status = if position.is_none() && holding.quantity != 0 {
+                ReconciliationStatus::MissingInBook
+            } else if book_quantity != holding.quantity {
+                ReconciliationStatus::QuantityMismatch
+            } else if cost_differs {
+                ReconciliationStatus::CostMismatch
+            } else {
+                ReconciliationStatus::Matched
+            };
+
+            items.push(ReconciliationItem {
+                code: holding.code.clone(),
+                name: holding.name.clone(),
+                broker_quantity: holding.quantity,
+                book_quantity,
+                difference: book_quantity - holding.quantity,
+                broker_cost: holding.cost,
+                book_cost: position.map(|p| p.cost),
+                status,
+            });
+        }
+
+        for position in self.positions.values() {
+            if !holdings.iter().any(|h| h.code == position.code) {
+                items.push(ReconciliationItem {
+                    code: position.code.clone(),
+                    name: Some(position.name.clone()),
+                    broker_quantity: 0,
+                    book_quantity: position.amount,
+                    difference: position.amount,
+                    broker_cost: None,
+                    book_cost: Some(position.cost),
+                    status: ReconciliationStatus::MissingAtBroker,
+                });
+            }
+        }
+
+        ReconciliationReport {
+            portfolio: self.name.clone(),
+            matched: items.iter().all(|i| i.status == ReconciliationStatus::Matched),
+            items,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATEMENT: &str = "成交日期,成交时间,证券代码,证券名称,操作,成交数量,成交均价,成交金额,手续费,印花税,过户费,成交编号
+20240102,09:35:12,=\"600000\",浦发银行,证券买入,1000,7.12,7120.00,5.00,0.00,0.07,A001
+20240102,10:01:00,=\"600000\",浦发银行,银行转证券,0,0,10000.00,0.00,0.00,0.00,
+20240103,09:31:00,=\"600000\",浦发银行,证券买入,100,7.20,720.00,5.00,0.72,0.01,A003
+20240105,14:20:30,=\"600000\",浦发银行,证券卖出,400,7.50,3000.00,5.00,1.50,0.03,A002
+20240108,09:00:00,,,证券转银行,0,0,2000.00,0.00,0.00,0.00,
+";
+
+    #[test]
+    fn test_import_statement_skips_duplicates() {
+        let statement = parse_statement(STATEMENT, &ColumnMapping::default()).unwrap();
+        assert_eq!(statement.trades.len(), 3);
+        assert_eq!(statement.transfers.len(), 2);
+        assert_eq!(statement.skipped, 0);
+        assert_eq!(statement.trades[0].code, "600000");
+        assert_eq!(statement.trades[2].tax, Money::new(15, 1));
+        assert_eq!(statement.transfers[1].amount, Money::from(-2000));
+
+        let mut portfolio = Portfolio::new("test".to_string(), Money::from(100000));
+        let report = portfolio.import_trades(&statement);
+        assert_eq!(report.imported, 3);
+        assert_eq!(report.transfers, 2);
+        assert!(report.errors.is_empty());
+        assert_eq!(portfolio.positions["600000"].amount, 700);
+        assert_eq!(portfolio.positions["600000"].name, "浦发银行");
+        // 佣金、过户费和买卖印花税均从现金扣除, 银证转账计入入金和出金
+        assert_eq!(portfolio.cash_balance(), Money::new(10314267, 2));
+        assert_eq!(portfolio.cash_ledger.net_contributions(), Money::from(108000));
+
+        let report = portfolio.import_trades(&statement);
+        assert_eq!(report.imported, 0);
+        assert_eq!(report.transfers, 0);
+        assert_eq!(report.duplicates, 5);
+
+        let holdings = parse_holdings("证券代码\t证券名称\t股票余额\t成本价\n600000\t浦发银行\t700\t7.13\n601398\t工商银行\t100\t5.00\n", &HoldingsMapping::default()).unwrap();
+        let reconciliation = portfolio.reconcile(&holdings);
+        assert!(!reconciliation.matched);
+        assert_eq!(reconciliation.items[0].status, ReconciliationStatus::Matched);
+        assert_eq!(reconciliation.items[1].status, ReconciliationStatus::MissingInBook);
+
+        // 数量一致但成本价差异超过容差
+        let holdings = parse_holdings("证券代码\t证券名称\t股票余额\t成本价\n600000\t浦发银行\t700\t7.50\n", &HoldingsMapping::default()).unwrap();
+        let reconciliation = portfolio.reconcile(&holdings);
+        assert!(!reconciliation.matched);
+        assert_eq!(reconciliation.items[0].status, ReconciliationStatus::CostMismatch);
+    }
+
+    #[test]
+    fn test_transfers_without_id_are_distinct() {
+        let csv = "成交日期,成交时间,证券代码,证券名称,操作,成交数量,成交均价,成交金额,手续费,印花税,过户费,成交编号
+20240102,10:01:00,,,银行转证券,0,0,10000.00,0.00,0.00,0.00,
+20240102,10:01:00,,,银行转证券,0,0,10000.00,0.00,0.00,0.00,
+";
+        let statement = parse_statement(csv, &ColumnMapping::default()).unwrap();
+        assert_eq!(statement.transfers.len(), 2);
+        assert_ne!(statement.transfers[0].transfer_id, statement.transfers[1].transfer_id);
+
+        let mut portfolio = Portfolio::new("test".to_string(), Money::ZERO);
+        assert_eq!(portfolio.import_trades(&statement).transfers, 2);
+        assert_eq!(portfolio.cash_balance(), Money::from(20000));
+    }
+}
@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use crate::money::{deserialize_money, Money};
+
+// 现金流水类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum CashEntryType {
+    // 入金
+    Deposit,
+    // 出金
+    Withdrawal,
+    // 交易结算
+    TradeSettlement,
+    // 交易费用
+    Fee,
+    // 分红 (税后)
+    Dividend,
+    // 利息
+    Interest,
+    // 融资借入和还款
+    Financing,
+    // 调整
+    Adjustment,
+}
+
+impl CashEntryType {
+    // 是否为外部资金流动 (计算收益时需要剔除)
+    pub fn is_external(&self) -> bool {
+        matches!(self, CashEntryType::Deposit | CashEntryType::Withdrawal)
+    }
+}
+
+// 现金流水
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CashEntry {
+    // 流水ID
+    pub id: String,
+    // 流水类型
+    pub entry_type: CashEntryType,
+    // 金额, 正数为流入, 负数为流出
+    #[serde(deserialize_with = "deserialize_money")]
+    pub amount: Money,
+    // 发生时间
+    pub timestamp: DateTime<Utc>,
+    // 关联的交易ID
+    pub reference: Option<String>,
+    // 备注
+    pub note: Option<String>,
+}
+
+impl CashEntry {
+    pub fn new(entry_type: CashEntryType, amount: Money) -> Self {
+        Self {
+            id: format!("{}", uuid::Uuid::new_v4()),
+            entry_type,
+            amount,
+            timestamp: Utc::now(),
+            reference: None,
+            note: None,
+        }
+    }
+}
+
+// 现金账本
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CashLedger {
+    pub entries: Vec<CashEntry>,
+}
+
+impl CashLedger {
+    pub fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    // 记录一笔流水
+    pub fn record(&mut self, entry: CashEntry) {
+        self.entries.push(entry);
+    }
+
+    // 当前余额
+    pub fn balance(&self) -> Money {
+        self.entries.iter().map(|e| e.amount).sum()
+    }
+
+    // 指定时间点的余额
+    pub fn balance_as_of(&self, timestamp: DateTime<Utc>) -> Money {
+        self.entries.iter()
+            .filter(|e| e.timestamp <= timestamp)
+            .map(|e| e.amount)
+            .sum()
+    }
+
+    // 净投入资金 (入金减出金)
+    pub fn net_contributions(&self) -> Money {
+        self.entries.iter()
+            .filter(|e| e.entry_type.is_external())
+            .map(|e| e.amount)
+            .sum()
+    }
+
+    // 时间区间 (from, to] 内的外部资金净流入
+    pub fn external_flows_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Money {
+        self.entries.iter()
+            .filter(|e| e.entry_type.is_external() && e.timestamp > from && e.timestamp <= to)
+            .map(|e| e.amount)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{Portfolio, Transaction, TransactionType};
+    use crate::money::money;
+    use super::*;
+
+    #[test]
+    fn test_balance_derived_from_ledger() {
+        let mut portfolio = Portfolio::new("test".to_string(), Money::from(10000));
+        portfolio.deposit(Money::from(5000), None).unwrap();
+        assert!(portfolio.withdraw(Money::from(20000), None).is_err());
+        portfolio.withdraw(Money::from(3000), None).unwrap();
+
+        let mut buy = Transaction::new("600000".to_string(), TransactionType::Buy, 100, Money::from(10));
+        buy.fee = Some(Money::from(5));
+        portfolio.add_transaction(buy).unwrap();
+
+        assert_eq!(portfolio.cash_balance(), Money::from(10995));
+        assert_eq!(portfolio.cash_ledger.net_contributions(), Money::from(12000));
+
+        let fees: Money = portfolio.cash_ledger.entries.iter()
+            .filter(|e| e.entry_type == CashEntryType::Fee)
+            .map(|e| e.amount)
+            .sum();
+        assert_eq!(fees, Money::from(-5));
+
+        // 多笔小额流水累加没有浮点误差
+        for _ in 0..10 {
+            portfolio.deposit(money(0.1), None).unwrap();
+        }
+        assert_eq!(portfolio.cash_balance(), Money::from(10996));
+
+        // 盈亏剔除入金和出金
+        portfolio.positions.get_mut("600000").unwrap().current_price = Some(11.0);
+        assert!((portfolio.total_profit_loss() - 95.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_legacy_cash_balance_becomes_opening_deposit() {
+        // 旧版本保存的组合只有cash_balance字段
+        let json = r#"{
+            "name": "legacy",
+            "positions": {},
+            "cash_balance": 50000.0,
+            "created_at": "2024-01-02T01:00:00Z",
+            "last_update": "2024-01-03T01:00:00Z"
+        }"#;
+        let portfolio: Portfolio = serde_json::from_str(json).unwrap();
+        assert_eq!(portfolio.cash_balance(), Money::from(50000));
+        assert_eq!(portfolio.cash_ledger.net_contributions(), Money::from(50000));
+        assert_eq!(portfolio.cash_ledger.entries[0].timestamp, portfolio.created_at);
+
+        // 保存后再读取不会重复入金
+        let saved = serde_json::to_string(&portfolio).unwrap();
+        assert!(!saved.contains("cash_balance"));
+        let reloaded: Portfolio = serde_json::from_str(&saved).unwrap();
+        assert_eq!(reloaded.cash_ledger.entries.len(), 1);
+        assert_eq!(reloaded.cash_balance(), Money::from(50000));
+    }
+}
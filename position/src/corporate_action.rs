@@ -0,0 +1,276 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::fs;
+use std::io;
+use crate::lots::{open_lots, Lot};
+use crate::market::day_start;
+use crate::models::{Portfolio, Transaction, TransactionType};
+use crate::events::PortfolioEventKind;
+use crate::money::{decimal, round_money, Money, Shares};
+
+// 送股计税面值 (每股1元)
+const PAR_VALUE: Money = Money::ONE;
+
+// 公司行动类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum CorporateActionType {
+    // 现金分红, 每股派息金额 (税前)
+    CashDividend { per_share: Money },
+    // 送股, 每股送股数量 (按面值计征红利税)
+    StockDividend { ratio: f64 },
+    // 转增股, 每股转增数量 (不征税)
+    BonusShares { ratio: f64 },
+    // 拆股/并股, 1股变为ratio股
+    Split { ratio: f64 },
+}
+
+impl CorporateActionType {
+    // 派息金额和送转、拆并比例必须大于0
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            CorporateActionType::CashDividend { per_share } if per_share <= Money::ZERO => Err("每股派息金额必须大于0".to_string()),
+            CorporateActionType::StockDividend { ratio } if ratio <= 0.0 => Err("送股比例必须大于0".to_string()),
+            CorporateActionType::BonusShares { ratio } if ratio <= 0.0 => Err("转增比例必须大于0".to_string()),
+            CorporateActionType::Split { ratio } if ratio <= 0.0 => Err("拆并股比例必须大于0".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+// 公司行动
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorporateAction {
+    // 公司行动ID, 为空时按代码、日期和类型生成
+    #[serde(default)]
+    pub id: String,
+    // 股票代码
+    pub code: String,
+    // 行动类型
+    pub action_type: CorporateActionType,
+    // 除权除息日
+    pub ex_date: NaiveDate,
+    // 备注
+    pub note: Option<String>,
+}
+
+impl CorporateAction {
+    pub fn new(code: String, action_type: CorporateActionType, ex_date: NaiveDate) -> Self {
+        Self {
+            id: String::new(),
+            code,
+            action_type,
+            ex_date,
+            note: None,
+        }
+    }
+
+    // 用于去重的唯一标识
+    pub fn key(&self) -> String {
+        if !self.id.is_empty() {
+            return self.id.clone();
+        }
+
+        let kind = match self.action_type {
+            CorporateActionType::CashDividend { .. } => "dividend",
+            CorporateActionType::StockDividend { .. } => "stock_dividend",
+            CorporateActionType::BonusShares { .. } => "bonus_shares",
+            CorporateActionType::Split { .. } => "split",
+        };
+        format!("{}-{}-{}", self.code, self.ex_date, kind)
+    }
+
+    // 除权除息时间 (除权除息日开盘前)
+    pub fn ex_time(&self) -> DateTime<Utc> {
+        day_start(self.ex_date)
+    }
+}
+
+// 按持有期计算红利税率 (持股1个月以内20%, 1个月至1年10%, 超过1年免征)
+pub fn dividend_tax_rate(holding_days: i64) -> Money {
+    if holding_days <= 30 {
+        Money::new(2, 1)
+    } else if holding_days <= 365 {
+        Money::new(1, 1)
+    } else {
+        Money::ZERO
+    }
+}
+
+// 按批次持有期计算每股应税金额对应的税额
+fn holding_period_tax(lots: &[Lot], taxable_per_share: Money, ex_time: DateTime<Utc>) -> Money {
+    let tax: Money = lots.iter()
+        .map(|lot| {
+            let holding_days = (ex_time - lot.acquired_at).num_days();
+            Money::from(lot.amount) * taxable_per_share * dividend_tax_rate(holding_days)
+        })
+        .sum();
+    round_money(tax)
+}
+
+impl Portfolio {
+    // 应用公司行动, 返回生成的交易记录
+    pub fn apply_corporate_action(&mut self, action: &CorporateAction) -> Result<Vec<Transaction>, String> {
+        let transactions = self.execute_corporate_action(action)?;
+        self.log_event(PortfolioEventKind::CorporateAction { action: action.clone() });
+        Ok(transactions)
+    }
+
+    // 按公司行动更新持仓和现金, 不记录事件
+    pub(crate) fn execute_corporate_action(&mut self, action: &CorporateAction) -> Result<Vec<Transaction>, String> {
+        action.action_type.validate()?;
+        let key = action.key();
+        if self.applied_actions.contains(&key) {
+            return Err(format!("公司行动已应用: {}", key));
+        }
+
+        let ex_time = action.ex_time();
+
+        // 计算除权除息日前的持仓批次
+        let lots = match self.positions.get(&action.code) {
+            Some(position) if position.transactions.is_empty() => vec![Lot {
+                acquired_at: position.last_update,
+                amount: position.amount,
+                cost: position.cost,
+            }],
+            Some(position) => {
+                let before: Vec<Transaction> = position.transactions.iter()
+                    .filter(|t| t.timestamp < ex_time)
+                    .cloned()
+                    .collect();
+                open_lots(&before)
+            },
+            None => Vec::new(),
+        };
+
+        let shares: Shares = lots.iter().map(|l| l.amount).sum();
+        if shares <= 0 {
+            self.applied_actions.push(key);
+            return Ok(Vec::new());
+        }
+
+        let mut transaction = match action.action_type {
+            CorporateActionType::CashDividend { per_share } => {
+                let mut transaction = Transaction::new(action.code.clone(), TransactionType::Dividend, shares, per_share);
+                transaction.tax = Some(holding_period_tax(&lots, per_share, ex_time));
+                transaction
+            },
+            CorporateActionType::StockDividend { ratio } => {
+                let new_shares = (shares as f64 * ratio).floor() as Shares;
+                let mut transaction = Transaction::new(action.code.clone(), TransactionType::BonusShares, new_shares, Money::ZERO);
+                transaction.tax = Some(holding_period_tax(&lots, decimal(ratio) * PAR_VALUE, ex_time));
+                transaction
+            },
+            CorporateActionType::BonusShares { ratio } => {
+                let new_shares = (shares as f64 * ratio).floor() as Shares;
+                Transaction::new(action.code.clone(), TransactionType::BonusShares, new_shares, Money::ZERO)
+            },
+            CorporateActionType::Split { ratio } => {
+                let new_shares = (shares as f64 * ratio).floor() as Shares - shares;
+                Transaction::new(action.code.clone(), TransactionType::Split, new_shares, Money::ZERO)
+            },
+        };
+
+        transaction.timestamp = ex_time;
+        transaction.note = Some(action.note.clone().unwrap_or_else(|| format!("公司行动: {}", key)));
+
+        self.apply_transaction(transaction.clone())?;
+        self.applied_actions.push(key);
+
+        Ok(vec![transaction])
+    }
+}
+
+// 从JSON文件读取公司行动
+pub fn load_corporate_actions(filename: &str) -> io::Result<Vec<CorporateAction>> {
+    let file_content = fs::read_to_string(filename)?;
+    let actions: Vec<CorporateAction> = serde_json::from_str(&file_content)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    Ok(actions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::money;
+    use crate::cash::CashEntryType;
+    use chrono::Duration;
+
+    fn portfolio_with_buy(code: &str, amount: Shares, price: Money, bought_at: DateTime<Utc>) -> Portfolio {
+        let mut portfolio = Portfolio::new("test".to_string(), Money::from(100000));
+        let mut buy = Transaction::new(code.to_string(), TransactionType::Buy, amount, price);
+        buy.timestamp = bought_at;
+        portfolio.add_transaction(buy).unwrap();
+        portfolio
+    }
+
+    #[test]
+    fn test_cash_dividend_tax_by_holding_period() {
+        let ex_date = NaiveDate::from_ymd_opt(2024, 6, 20).unwrap();
+        let bought_at = day_start(ex_date) - Duration::days(10);
+        let mut portfolio = portfolio_with_buy("600000", 1000, Money::from(10), bought_at);
+        let cash_before = portfolio.cash_balance();
+
+        let action = CorporateAction::new("600000".to_string(), CorporateActionType::CashDividend { per_share: money(0.5) }, ex_date);
+        let transactions = portfolio.apply_corporate_action(&action).unwrap();
+
+        // 持有不足1个月, 按20%计税
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].tax, Some(Money::from(100)));
+        assert_eq!(portfolio.cash_balance() - cash_before, Money::from(400));
+        assert_eq!(portfolio.positions["600000"].amount, 1000);
+
+        // 重复应用应被拒绝
+        assert!(portfolio.apply_corporate_action(&action).is_err());
+    }
+
+    #[test]
+    fn test_bonus_shares_and_split_adjust_cost() {
+        let ex_date = NaiveDate::from_ymd_opt(2024, 6, 20).unwrap();
+        let bought_at = day_start(ex_date) - Duration::days(400);
+        let mut portfolio = portfolio_with_buy("000001", 1000, Money::from(20), bought_at);
+
+        // 10转10
+        let bonus = CorporateAction::new("000001".to_string(), CorporateActionType::BonusShares { ratio: 1.0 }, ex_date);
+        portfolio.apply_corporate_action(&bonus).unwrap();
+        let position = &portfolio.positions["000001"];
+        assert_eq!(position.amount, 2000);
+        assert_eq!(position.cost, Money::from(10));
+
+        // 2股并1股
+        let merge = CorporateAction::new("000001".to_string(), CorporateActionType::Split { ratio: 0.5 }, ex_date + Duration::days(30));
+        portfolio.apply_corporate_action(&merge).unwrap();
+        let position = &portfolio.positions["000001"];
+        assert_eq!(position.amount, 1000);
+        assert_eq!(position.cost, Money::from(20));
+    }
+
+    #[test]
+    fn test_stock_dividend_tax_and_invalid_actions() {
+        let ex_date = NaiveDate::from_ymd_opt(2024, 6, 20).unwrap();
+        let bought_at = day_start(ex_date) - Duration::days(10);
+        let mut portfolio = portfolio_with_buy("600000", 1000, Money::from(10), bought_at);
+
+        // 非正的派息金额和比例被拒绝, 不改变持仓
+        let invalid = [
+            CorporateActionType::CashDividend { per_share: money(-0.5) },
+            CorporateActionType::StockDividend { ratio: 0.0 },
+            CorporateActionType::BonusShares { ratio: -1.0 },
+            CorporateActionType::Split { ratio: 0.0 },
+        ];
+        for action_type in invalid {
+            let action = CorporateAction::new("600000".to_string(), action_type, ex_date);
+            assert!(portfolio.apply_corporate_action(&action).is_err());
+        }
+        assert_eq!(portfolio.positions["600000"].amount, 1000);
+        assert!(portfolio.applied_actions.is_empty());
+
+        // 10送2, 按面值计税: 200股 * 1元 * 20% = 40元, 记为税费
+        let action = CorporateAction::new("600000".to_string(), CorporateActionType::StockDividend { ratio: 0.2 }, ex_date);
+        portfolio.apply_corporate_action(&action).unwrap();
+        assert_eq!(portfolio.positions["600000"].amount, 1200);
+        let entry = portfolio.cash_ledger.entries.last().unwrap();
+        assert_eq!(entry.entry_type, CashEntryType::Fee);
+        assert_eq!(entry.amount, Money::from(-40));
+    }
+}
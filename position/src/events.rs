@@ -0,0 +1,496 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use crate::cash::CashEntry;
+use crate::corporate_action::CorporateAction;
+use crate::margin::{MarginAccount, MarginOperation};
+use crate::models::{Portfolio, Position, Transaction, TransactionType};
+use crate::money::Money;
+
+// 组合事件类型
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PortfolioEventKind {
+    // 创建组合
+    Created,
+    // 交易
+    Trade { transaction: Transaction },
+    // 修改交易
+    AmendTrade { transaction_id: String, transaction: Transaction },
+    // 撤销交易
+    CancelTrade { transaction_id: String },
+    // 现金流水 (入金、出金等)
+    Cash { entry: CashEntry },
+    // 公司行动
+    CorporateAction { action: CorporateAction },
+    // 直接添加持仓
+    AddPosition { position: Position },
+    // 直接移除持仓
+    RemovePosition { code: String },
+    // 融资融券操作
+    Margin { operation: MarginOperation },
+    // 撤回之前的事件
+    Revert { event_id: String },
+    // 期初快照, 旧版本数据加载时由当前状态生成, 不能撤回
+    Snapshot {
+        positions: Vec<Position>,
+        entries: Vec<CashEntry>,
+        #[serde(default)]
+        margin: Option<MarginAccount>,
+        #[serde(default)]
+        applied_actions: Vec<String>,
+    },
+}
+
+// 组合事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortfolioEvent {
+    // 事件ID
+    pub id: String,
+    // 事件内容
+    pub kind: PortfolioEventKind,
+    // 记录时间
+    pub recorded_at: DateTime<Utc>,
+    // 操作人
+    pub actor: Option<String>,
+    // 原因
+    pub reason: Option<String>,
+}
+
+impl PortfolioEvent {
+    pub fn new(kind: PortfolioEventKind) -> Self {
+        Self {
+            id: format!("{}", uuid::Uuid::new_v4()),
+            kind,
+            recorded_at: Utc::now(),
+            actor: None,
+            reason: None,
+        }
+    }
+}
+
+// 直接添加的持仓没有交易记录时, 按持仓成本生成一笔买入
+fn position_history(position: &Position) -> Vec<Transaction> {
+    if !position.transactions.is_empty() || position.amount <= 0 {
+        return position.transactions.clone();
+    }
+
+    let mut transaction = Transaction::new(position.code.clone(), TransactionType::Buy, position.amount, position.cost);
+    transaction.timestamp = position.last_update;
+    transaction.note = Some("初始持仓".to_string());
+    vec![transaction]
+}
+
+// 被撤回的事件ID (撤回事件本身也可以被撤回)
+fn reverted_events(events: &[PortfolioEvent]) -> HashSet<String> {
+    let mut reverted = HashSet::new();
+    for event in events.iter().rev() {
+        if reverted.contains(&event.id) {
+            continue;
+        }
+        if let PortfolioEventKind::Revert { event_id } = &event.kind {
+            reverted.insert(event_id.clone());
+        }
+    }
+    reverted
+}
+
+impl Portfolio {
+    // 追加事件
+    pub(crate) fn log_event(&mut self, kind: PortfolioEventKind) {
+        self.events.push(PortfolioEvent::new(kind));
+    }
+
+    // 旧版本数据没有事件记录, 用当前状态生成期初快照, 使重建和撤回不会丢失数据
+    pub(crate) fn seed_events(&mut self) {
+        if !self.events.is_empty() {
+            return;
+        }
+
+        let mut created = PortfolioEvent::new(PortfolioEventKind::Created);
+        created.recorded_at = self.created_at;
+        let mut snapshot = PortfolioEvent::new(PortfolioEventKind::Snapshot {
+            positions: self.positions.values().cloned().collect(),
+            entries: self.cash_ledger.entries.clone(),
+            margin: self.margin.clone(),
+            applied_actions: self.applied_actions.clone(),
+        });
+        snapshot.recorded_at = self.last_update.max(self.created_at);
+        snapshot.reason = Some("旧版本数据迁移".to_string());
+        self.events = vec![created, snapshot];
+    }
+
+    // 按事件记录重建组合, as_of指定时只使用该时间之前记录的事件
+    pub fn replay(name: &str, events: &[PortfolioEvent], as_of: Option<DateTime<Utc>>) -> Result<Portfolio, String> {
+        Portfolio::replay_events(name, events, as_of).map(|(portfolio, _)| portfolio)
+    }
+
+    // 重建组合并返回生效的全部交易 (含修改后的交易和公司行动生成的交易)
+    fn replay_events(name: &str, events: &[PortfolioEvent], as_of: Option<DateTime<Utc>>) -> Result<(Portfolio, Vec<Transaction>), String> {
+        let events: Vec<PortfolioEvent> = events.iter()
+            .filter(|e| as_of.is_none_or(|at| e.recorded_at <= at))
+            .cloned()
+            .collect();
+        let first = events.first().ok_or_else(|| "没有事件记录".to_string())?;
+
+        let mut portfolio = Portfolio::new(name.to_string(), Money::ZERO);
+        let mut history = Vec::new();
+        portfolio.events.clear();
+        portfolio.created_at = first.recorded_at;
+
+        let reverted = reverted_events(&events);
+        let effective: Vec<&PortfolioEvent> = events.iter().filter(|e| !reverted.contains(&e.id)).collect();
+
+        // 交易的最终版本, None表示已撤销
+        let mut corrections: HashMap<&str, Option<&Transaction>> = HashMap::new();
+        for event in &effective {
+            match &event.kind {
+                PortfolioEventKind::AmendTrade { transaction_id, transaction } => {
+                    corrections.insert(transaction_id, Some(transaction));
+                },
+                PortfolioEventKind::CancelTrade { transaction_id } => {
+                    corrections.insert(transaction_id, None);
+                },
+                _ => {},
+            }
+        }
+
+        for event in &effective {
+            match &event.kind {
+                PortfolioEventKind::Trade { transaction } => {
+                    let transaction = match corrections.get(transaction.id.as_str()) {
+                        Some(Some(amended)) => amended,
+                        Some(None) => continue,
+                        None => transaction,
+                    };
+                    portfolio.apply_transaction(transaction.clone())
+                        .map_err(|e| format!("重放交易{}失败: {}", transaction.id, e))?;
+                    history.push(transaction.clone());
+                },
+                PortfolioEventKind::Cash { entry } => {
+                    portfolio.cash_ledger.record(entry.clone());
+                },
+                PortfolioEventKind::CorporateAction { action } => {
+                    let transactions = portfolio.execute_corporate_action(action)
+                        .map_err(|e| format!("重放公司行动{}失败: {}", action.key(), e))?;
+                    history.extend(transactions);
+                },
+                PortfolioEventKind::AddPosition { position } => {
+                    portfolio.positions.insert(position.code.clone(), position.clone());
+                    history.extend(position_history(position));
+                },
+                PortfolioEventKind::RemovePosition { code } => {
+                    portfolio.positions.remove(code);
+                },
+                PortfolioEventKind::Margin { operation } => {
+                    portfolio.apply_margin(operation, event.recorded_at)
+                        .map_err(|e| format!("重放融资融券操作失败: {}", e))?;
+                },
+                PortfolioEventKind::Snapshot { positions, entries, margin, applied_actions } => {
+                    for position in positions {
+                        portfolio.positions.insert(position.code.clone(), position.clone());
+                        history.extend(position_history(position));
+                    }
+                    for entry in entries {
+                        portfolio.cash_ledger.record(entry.clone());
+                    }
+                    portfolio.margin = margin.clone();
+                    portfolio.applied_actions.extend(applied_actions.iter().cloned());
+                },
+                PortfolioEventKind::Created
+                | PortfolioEventKind::AmendTrade { .. }
+                | PortfolioEventKind::CancelTrade { .. }
+                | PortfolioEventKind::Revert { .. } => {},
+            }
+            portfolio.last_update = event.recorded_at;
+        }
+
+        portfolio.events = events;
+        Ok((portfolio, history))
+    }
+
+    // 全部生效的交易记录, 包括已清仓股票的交易
+    pub fn transaction_history(&self) -> Result<Vec<Transaction>, String> {
+        // 旧版本数据没有事件记录, 只能使用当前持仓的交易
+        if self.events.is_empty() {
+            return Ok(self.positions.values().flat_map(position_history).collect());
+        }
+
+        let (_, mut history) = Portfolio::replay_events(&self.name, &self.events, None)?;
+        history.sort_by_key(|t| t.timestamp);
+        Ok(history)
+    }
+
+    // 查看指定时间的组合状态
+    pub fn as_of(&self, at: DateTime<Utc>) -> Result<Portfolio, String> {
+        Portfolio::replay(&self.name, &self.events, Some(at))
+    }
+
+    // 用事件记录重建持仓和现金, 保留估值等非事件状态
+    fn rebuild(&mut self) -> Result<(), String> {
+        let (rebuilt, history) = Portfolio::replay_events(&self.name, &self.events, None)?;
+
+        let mut positions = rebuilt.positions;
+        for position in positions.values_mut() {
+            if let Some(old) = self.positions.get(&position.code) {
+                position.name = old.name.clone();
+                position.sector = old.sector.clone();
+                position.current_price = old.current_price;
+                position.valued_at = old.valued_at;
+            }
+        }
+
+        let mut margin = rebuilt.margin;
+        if let (Some(margin), Some(old)) = (margin.as_mut(), self.margin.as_ref()) {
+            margin.level = old.level;
+            for short in margin.shorts.values_mut() {
+                short.current_price = old.shorts.get(&short.code).and_then(|s| s.current_price);
+            }
+        }
+
+        self.positions = positions;
+        self.margin = margin;
+        self.cash_ledger = rebuilt.cash_ledger;
+        self.applied_actions = rebuilt.applied_actions;
+
+        // 订单成交和已导入的交割单记录只保留仍然生效的交易和流水
+        let transactions: HashMap<&str, &Transaction> = history.iter().map(|t| (t.id.as_str(), t)).collect();
+        for order in self.orders.iter_mut() {
+            order.sync_fills(&transactions);
+        }
+        let imported: HashSet<&str> = history.iter()
+            .filter_map(|t| t.external_id.as_deref())
+            .chain(self.cash_ledger.entries.iter().filter_map(|e| e.reference.as_deref()))
+            .collect();
+        self.imported_trades.retain(|id| imported.contains(id.as_str()));
+
+        self.last_update = Utc::now();
+        Ok(())
+    }
+
+    // 追加更正事件并重建, 重建失败时撤回该事件
+    fn correct(&mut self, kind: PortfolioEventKind, actor: Option<String>, reason: Option<String>) -> Result<PortfolioEvent, String> {
+        let mut event = PortfolioEvent::new(kind);
+        event.actor = actor;
+        event.reason = reason;
+        self.events.push(event.clone());
+
+        if let Err(e) = self.rebuild() {
+            self.events.pop();
+            return Err(e);
+        }
+        Ok(event)
+    }
+
+    fn has_trade(&self, transaction_id: &str) -> bool {
+        self.events.iter().any(|e| matches!(&e.kind, PortfolioEventKind::Trade { transaction } if transaction.id == transaction_id))
+    }
+
+    // 查找交易的当前版本 (含修改), 已撤销的交易返回None
+    pub fn find_transaction(&self, transaction_id: &str) -> Option<Transaction> {
+        let reverted = reverted_events(&self.events);
+        let mut current = None;
+        for event in self.events.iter().filter(|e| !reverted.contains(&e.id)) {
+            match &event.kind {
+                PortfolioEventKind::Trade { transaction } if transaction.id == transaction_id => {
+                    current = Some(transaction.clone());
+                },
+                PortfolioEventKind::AmendTrade { transaction_id: id, transaction } if id == transaction_id => {
+                    current = Some(transaction.clone());
+                },
+                PortfolioEventKind::CancelTrade { transaction_id: id } if id == transaction_id => {
+                    current = None;
+                },
+                _ => {},
+            }
+        }
+        current
+    }
+
+    // 修改交易
+    pub fn amend_transaction(
+        &mut self,
+        transaction_id: &str,
+        mut transaction: Transaction,
+        actor: Option<String>,
+        reason: Option<String>,
+    ) -> Result<PortfolioEvent, String> {
+        if !self.has_trade(transaction_id) {
+            return Err("交易不存在".to_string());
+        }
+        transaction.id = transaction_id.to_string();
+        self.correct(PortfolioEventKind::AmendTrade { transaction_id: transaction_id.to_string(), transaction }, actor, reason)
+    }
+
+    // 撤销交易
+    pub fn cancel_transaction(&mut self, transaction_id: &str, actor: Option<String>, reason: Option<String>) -> Result<PortfolioEvent, String> {
+        if !self.has_trade(transaction_id) {
+            return Err("交易不存在".to_string());
+        }
+        self.correct(PortfolioEventKind::CancelTrade { transaction_id: transaction_id.to_string() }, actor, reason)
+    }
+
+    // 撤回最近一个仍然生效的事件
+    pub fn undo_last(&mut self, actor: Option<String>) -> Result<PortfolioEvent, String> {
+        let reverted = reverted_events(&self.events);
+        let target = self.events.iter().rev()
+            .find(|e| !reverted.contains(&e.id) && !matches!(e.kind, PortfolioEventKind::Created | PortfolioEventKind::Snapshot { .. } | PortfolioEventKind::Revert { .. }))
+            .ok_or_else(|| "没有可撤回的事件".to_string())?;
+
+        let kind = PortfolioEventKind::Revert { event_id: target.id.clone() };
+        self.correct(kind, actor, Some("撤回".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broker_import::{BrokerCashTransfer, BrokerStatement, BrokerTrade};
+    use crate::money::money;
+    use crate::order::{Order, OrderSide, OrderStatus, OrderType};
+
+    #[test]
+    fn test_amend_cancel_and_undo() {
+        let mut portfolio = Portfolio::new("test".to_string(), Money::from(100000));
+        let buy = Transaction::new("600000".to_string(), TransactionType::Buy, 1000, Money::from(10));
+        let buy_id = buy.id.clone();
+        portfolio.add_transaction(buy).unwrap();
+        let before = Utc::now();
+
+        // 撤回最近的入金
+        portfolio.deposit(Money::from(5000), None).unwrap();
+        portfolio.undo_last(Some("ops".to_string())).unwrap();
+        assert_eq!(portfolio.cash_balance(), Money::from(90000));
+
+        // 修改成交价格
+        let amended = Transaction::new("600000".to_string(), TransactionType::Buy, 1000, Money::from(9));
+        portfolio.amend_transaction(&buy_id, amended, Some("ops".to_string()), Some("价格录错".to_string())).unwrap();
+        assert_eq!(portfolio.positions["600000"].cost, Money::from(9));
+        assert_eq!(portfolio.cash_balance(), Money::from(91000));
+
+        // 过去时间点的组合状态不受更正影响
+        let past = portfolio.as_of(before).unwrap();
+        assert_eq!(past.positions["600000"].cost, Money::from(10));
+        assert_eq!(past.cash_balance(), Money::from(90000));
+
+        portfolio.cancel_transaction(&buy_id, None, None).unwrap();
+        assert!(!portfolio.positions.contains_key("600000"));
+        assert_eq!(portfolio.cash_balance(), Money::from(100000));
+        assert!(portfolio.cancel_transaction("missing", None, None).is_err());
+    }
+
+    #[test]
+    fn test_cancel_order_fill() {
+        let mut portfolio = Portfolio::new("test".to_string(), Money::from(100000));
+        let order = portfolio.place_order(Order::new("600000".to_string(), OrderSide::Buy, OrderType::Limit { price: 10.0 }, 1000));
+        let first = portfolio.fill_order(&order.id, 600, money(9.9), Money::from(5), Money::ZERO, Utc::now()).unwrap();
+        let second = portfolio.fill_order(&order.id, 400, Money::from(10), Money::from(5), Money::ZERO, Utc::now()).unwrap();
+        assert_eq!(portfolio.orders[0].status, OrderStatus::Filled);
+
+        // 撤销一笔成交后订单恢复为部分成交, 可以继续成交
+        portfolio.cancel_transaction(&second.id, None, None).unwrap();
+        let order = &portfolio.orders[0];
+        assert_eq!(order.status, OrderStatus::PartiallyFilled);
+        assert_eq!(order.filled_quantity, 600);
+        assert_eq!(order.average_price, Some(money(9.9)));
+        assert_eq!(order.fills.len(), 1);
+        assert_eq!(portfolio.positions["600000"].amount, 600);
+
+        // 修改成交价格同步到订单
+        let mut amended = first.clone();
+        amended.price = money(9.8);
+        portfolio.amend_transaction(&first.id, amended, None, None).unwrap();
+        assert_eq!(portfolio.orders[0].average_price, Some(money(9.8)));
+
+        portfolio.cancel_transaction(&first.id, None, None).unwrap();
+        let order = &portfolio.orders[0];
+        assert_eq!(order.status, OrderStatus::New);
+        assert_eq!(order.filled_quantity, 0);
+        assert_eq!(order.average_price, None);
+    }
+
+    #[test]
+    fn test_cancel_imported_trade() {
+        let mut portfolio = Portfolio::new("test".to_string(), Money::ZERO);
+        let at = Utc::now();
+        let statement = BrokerStatement {
+            trades: vec![BrokerTrade {
+                trade_id: "T001".to_string(),
+                code: "600000".to_string(),
+                name: None,
+                transaction_type: TransactionType::Buy,
+                quantity: 1000,
+                price: Money::from(10),
+                fee: Money::from(5),
+                tax: Money::ZERO,
+                timestamp: at,
+            }],
+            transfers: vec![BrokerCashTransfer {
+                transfer_id: "B001".to_string(),
+                amount: Money::from(20000),
+                timestamp: at - chrono::Duration::minutes(1),
+            }],
+            skipped: 0,
+        };
+        let report = portfolio.import_trades(&statement);
+        assert_eq!((report.imported, report.transfers), (1, 1));
+
+        // 撤销导入的成交后可以重新导入, 转账仍被视为重复
+        let transaction_id = portfolio.positions["600000"].transactions[0].id.clone();
+        portfolio.cancel_transaction(&transaction_id, None, None).unwrap();
+        assert!(!portfolio.imported_trades.contains("T001"));
+        assert!(portfolio.imported_trades.contains("B001"));
+        let report = portfolio.import_trades(&statement);
+        assert_eq!((report.imported, report.transfers, report.duplicates), (1, 0, 1));
+        assert_eq!(portfolio.positions["600000"].amount, 1000);
+
+        // 撤回转账入金后转账记录也可以重新导入
+        let mut portfolio = Portfolio::new("test".to_string(), Money::ZERO);
+        let transfers = BrokerStatement { trades: Vec::new(), ..statement };
+        portfolio.import_trades(&transfers);
+        portfolio.undo_last(None).unwrap();
+        assert!(portfolio.imported_trades.is_empty());
+        assert_eq!(portfolio.import_trades(&transfers).transfers, 1);
+    }
+
+    #[test]
+    fn test_legacy_portfolio_seeds_snapshot() {
+        // 旧版本保存的组合没有事件记录
+        let json = r#"{
+            "name": "legacy",
+            "positions": {
+                "600000": {
+                    "code": "600000",
+                    "name": "浦发银行",
+                    "amount": 1000.0,
+                    "cost": 10.0,
+                    "current_price": 11.0,
+                    "last_update": "2024-01-03T01:00:00Z",
+                    "transactions": []
+                }
+            },
+            "cash_balance": 50000.0,
+            "created_at": "2024-01-02T01:00:00Z",
+            "last_update": "2024-01-03T01:00:00Z"
+        }"#;
+        let mut portfolio: Portfolio = serde_json::from_str(json).unwrap();
+        assert_eq!(portfolio.events.len(), 2);
+        assert_eq!(portfolio.transaction_history().unwrap().len(), 1);
+
+        // 期初快照不能撤回
+        assert!(portfolio.undo_last(None).is_err());
+
+        // 新的交易可以撤回, 期初持仓和现金保留
+        let buy = Transaction::new("600519".to_string(), TransactionType::Buy, 10, Money::from(1000));
+        portfolio.add_transaction(buy).unwrap();
+        portfolio.undo_last(None).unwrap();
+        assert_eq!(portfolio.positions.len(), 1);
+        assert_eq!(portfolio.positions["600000"].amount, 1000);
+        assert_eq!(portfolio.positions["600000"].current_price, Some(11.0));
+        assert_eq!(portfolio.cash_balance(), Money::from(50000));
+
+        // 保存后再读取不会重复生成快照
+        let saved = serde_json::to_string(&portfolio).unwrap();
+        let reloaded: Portfolio = serde_json::from_str(&saved).unwrap();
+        assert_eq!(reloaded.events.len(), portfolio.events.len());
+    }
+}
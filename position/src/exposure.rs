@@ -0,0 +1,241 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::market::board;
+use crate::models::Portfolio;
+use crate::money::to_f64;
+use crate::valuation::Quote;
+
+// 未知分类的名称
+const UNKNOWN: &str = "未知";
+
+// 默认统计的前N大持仓
+pub const DEFAULT_TOP_N: usize = 5;
+
+// 单个分类的暴露
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExposureBucket {
+    pub name: String,
+    pub value: f64,
+    // 占总资产权重
+    pub weight: f64,
+    // 已加载行情的股票池中的权重
+    pub universe_weight: Option<f64>,
+    // 相对股票池的超配权重
+    pub active_weight: Option<f64>,
+}
+
+// 单只股票的权重
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HoldingWeight {
+    pub code: String,
+    pub name: String,
+    pub weight: f64,
+}
+
+// 组合暴露报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExposureReport {
+    pub portfolio: String,
+    pub equity: f64,
+    pub cash_weight: f64,
+    pub by_sector: Vec<ExposureBucket>,
+    pub by_market_cap: Vec<ExposureBucket>,
+    pub by_board: Vec<ExposureBucket>,
+    // 前N大持仓
+    pub top_holdings: Vec<HoldingWeight>,
+    // 前N大持仓合计权重
+    pub top_weight: f64,
+    // 赫芬达尔指数 (按持仓市值计算)
+    pub herfindahl: f64,
+    // 有效持股数量 (赫芬达尔指数的倒数)
+    pub effective_holdings: f64,
+}
+
+// 市值分档 (人民币): 大盘1000亿以上, 中盘200亿以上, 小盘50亿以上, 其余为微盘
+pub fn market_cap_bucket(market_cap: Option<f64>) -> &'static str {
+    match market_cap {
+        Some(cap) if cap >= 1.0e11 => "大盘",
+        Some(cap) if cap >= 2.0e10 => "中盘",
+        Some(cap) if cap >= 5.0e9 => "小盘",
+        Some(cap) if cap > 0.0 => "微盘",
+        _ => UNKNOWN,
+    }
+}
+
+// 用已加载报价的全部股票 (股票池, 不是指数成分股) 按市值加权计算行业权重
+pub fn universe_sector_weights(quotes: &HashMap<String, Quote>) -> HashMap<String, f64> {
+    let mut weights: HashMap<String, f64> = HashMap::new();
+    for quote in quotes.values() {
+        if let (Some(sector), Some(cap)) = (&quote.sector, quote.market_cap) {
+            *weights.entry(sector.clone()).or_default() += cap;
+        }
+    }
+
+    let total: f64 = weights.values().sum();
+    if total > 0.0 {
+        for weight in weights.values_mut() {
+            *weight /= total;
+        }
+    }
+    weights
+}
+
+fn buckets(values: HashMap<String, f64>, equity: f64, universe: Option<&HashMap<String, f64>>) -> Vec<ExposureBucket> {
+    let mut buckets: Vec<ExposureBucket> = values.into_iter()
+        .map(|(name, value)| {
+            let weight = value / equity;
+            let universe_weight = universe.map(|u| u.get(&name).copied().unwrap_or(0.0));
+            ExposureBucket {
+                name,
+                value,
+                weight,
+                universe_weight,
+                active_weight: universe_weight.map(|u| weight - u),
+            }
+        })
+        .collect();
+
+    // 股票池中有而组合中没有的分类显示为低配
+    if let Some(universe) = universe {
+        for (name, weight) in universe {
+            if !buckets.iter().any(|b| &b.name == name) {
+                buckets.push(ExposureBucket {
+                    name: name.clone(),
+                    value: 0.0,
+                    weight: 0.0,
+                    universe_weight: Some(*weight),
+                    active_weight: Some(-weight),
+                });
+            }
+        }
+    }
+
+    buckets.sort_by(|a, b| b.weight.partial_cmp(&a.weight).unwrap().then(a.name.cmp(&b.name)));
+    buckets
+}
+
+impl Portfolio {
+    // 计算行业、市值和板块暴露及集中度
+    pub fn exposure(
+        &self,
+        quotes: &HashMap<String, Quote>,
+        universe_sectors: Option<&HashMap<String, f64>>,
+        top_n: usize,
+    ) -> ExposureReport {
+        let equity = self.total_market_value();
+        let mut sectors: HashMap<String, f64> = HashMap::new();
+        let mut caps: HashMap<String, f64> = HashMap::new();
+        let mut boards: HashMap<String, f64> = HashMap::new();
+        let mut holdings = Vec::new();
+
+        for position in self.positions.values() {
+            let value = match position.market_value() {
+                Some(value) => value,
+                None => continue,
+            };
+            let quote = quotes.get(&position.code);
+
+            let sector = quote.and_then(|q| q.sector.clone())
+                .or_else(|| position.sector.clone())
+                .unwrap_or_else(|| UNKNOWN.to_string());
+            *sectors.entry(sector).or_default() += value;
+            *caps.entry(market_cap_bucket(quote.and_then(|q| q.market_cap)).to_string()).or_default() += value;
+            *boards.entry(format!("{:?}", board(&position.code))).or_default() += value;
+            holdings.push((position, value));
+        }
+
+        if equity <= 0.0 {
+            return ExposureReport {
+                portfolio: self.name.clone(),
+                equity,
+                cash_weight: 0.0,
+                by_sector: Vec::new(),
+                by_market_cap: Vec::new(),
+                by_board: Vec::new(),
+                top_holdings: Vec::new(),
+                top_weight: 0.0,
+                herfindahl: 0.0,
+                effective_holdings: 0.0,
+            };
+        }
+
+        holdings.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        let top_holdings: Vec<HoldingWeight> = holdings.iter()
+            .take(top_n)
+            .map(|(p, value)| HoldingWeight {
+                code: p.code.clone(),
+                name: p.name.clone(),
+                weight: value / equity,
+            })
+            .collect();
+
+        let invested: f64 = holdings.iter().map(|(_, v)| v).sum();
+        let herfindahl = if invested > 0.0 {
+            holdings.iter().map(|(_, v)| (v / invested).powi(2)).sum()
+        } else {
+            0.0
+        };
+
+        ExposureReport {
+            portfolio: self.name.clone(),
+            equity,
+            cash_weight: to_f64(self.cash_balance()) / equity,
+            by_sector: buckets(sectors, equity, universe_sectors),
+            by_market_cap: buckets(caps, equity, None),
+            by_board: buckets(boards, equity, None),
+            top_weight: top_holdings.iter().map(|h| h.weight).sum(),
+            top_holdings,
+            herfindahl,
+            effective_holdings: if herfindahl > 0.0 { 1.0 / herfindahl } else { 0.0 },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Transaction, TransactionType};
+    use crate::money::Money;
+
+    fn quote(code: &str, sector: &str, market_cap: f64) -> (String, Quote) {
+        (code.to_string(), Quote {
+            code: code.to_string(),
+            name: None,
+            price: 10.0,
+            change_percent: None,
+            volume: None,
+            sector: Some(sector.to_string()),
+            market_cap: Some(market_cap),
+        })
+    }
+
+    #[test]
+    fn test_exposure_report() {
+        let mut portfolio = Portfolio::new("test".to_string(), Money::from(100000));
+        for (code, amount) in [("600000", 3000), ("601398", 1000), ("300750", 2000)] {
+            portfolio.add_transaction(Transaction::new(code.to_string(), TransactionType::Buy, amount, Money::from(10))).unwrap();
+        }
+        let quotes: HashMap<String, Quote> = [
+            quote("600000", "金融", 2.0e11),
+            quote("601398", "金融", 1.5e12),
+            quote("300750", "电子", 9.0e10),
+            quote("000001", "地产", 2.0e11),
+        ].into_iter().collect();
+        portfolio.mark_to_market(&quotes, chrono::Utc::now());
+
+        let universe = universe_sector_weights(&quotes);
+        let report = portfolio.exposure(&quotes, Some(&universe), 2);
+
+        assert!((report.cash_weight - 0.4).abs() < 1e-9);
+        let finance = &report.by_sector[0];
+        assert_eq!(finance.name, "金融");
+        assert!((finance.weight - 0.4).abs() < 1e-9);
+        assert!(report.by_sector.iter().any(|b| b.name == "地产" && b.weight == 0.0));
+        assert!((report.top_weight - 0.5).abs() < 1e-9);
+
+        // 持仓权重 1/2, 1/3, 1/6
+        assert!((report.herfindahl - (0.25 + 1.0 / 9.0 + 1.0 / 36.0)).abs() < 1e-9);
+        assert!(report.by_board.iter().any(|b| b.name == "ChiNext"));
+        assert_eq!(market_cap_bucket(Some(9.0e10)), "中盘");
+    }
+}
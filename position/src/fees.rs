@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use crate::order::OrderSide;
+use crate::money::{decimal, round_money, Money};
+
+// 交易费用标准
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    // 佣金费率
+    pub commission_rate: f64,
+    // 最低佣金
+    pub min_commission: Money,
+    // 印花税率 (仅卖出收取)
+    pub stamp_tax_rate: f64,
+    // 过户费率
+    pub transfer_fee_rate: f64,
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        Self {
+            commission_rate: 0.00025,
+            min_commission: Money::from(5),
+            stamp_tax_rate: 0.0005,
+            transfer_fee_rate: 0.00001,
+        }
+    }
+}
+
+impl FeeSchedule {
+    // 不收取任何费用和税
+    pub fn zero() -> Self {
+        Self {
+            commission_rate: 0.0,
+            min_commission: Money::ZERO,
+            stamp_tax_rate: 0.0,
+            transfer_fee_rate: 0.0,
+        }
+    }
+
+    // 计算成交金额对应的费用和税, 各项分别四舍五入到分, 返回 (佣金加过户费, 印花税)
+    pub fn costs(&self, side: OrderSide, value: Money) -> (Money, Money) {
+        if value <= Money::ZERO {
+            return (Money::ZERO, Money::ZERO);
+        }
+
+        let commission = round_money(value * decimal(self.commission_rate)).max(self.min_commission);
+        let fee = commission + round_money(value * decimal(self.transfer_fee_rate));
+        let tax = match side {
+            OrderSide::Buy => Money::ZERO,
+            OrderSide::Sell => round_money(value * decimal(self.stamp_tax_rate)),
+        };
+        (fee, tax)
+    }
+}
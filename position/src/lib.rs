@@ -1,2 +1,19 @@
+pub mod money;
 pub mod models;
-pub mod position_manager; 
\ No newline at end of file
+pub mod position_manager;
+pub mod market;
+pub mod lots;
+pub mod corporate_action;
+pub mod cash;
+pub mod valuation;
+pub mod performance;
+pub mod broker_import;
+pub mod order;
+pub mod fees;
+pub mod paper;
+pub mod risk;
+pub mod rebalance;
+pub mod events;
+pub mod exposure;
+pub mod report;
+pub mod margin;
@@ -0,0 +1,93 @@
+use chrono::{DateTime, Utc};
+use crate::models::{Transaction, TransactionType};
+use crate::money::{Money, Shares};
+
+// 持仓批次 (按先进先出规则匹配)
+#[derive(Debug, Clone)]
+pub struct Lot {
+    // 买入时间
+    pub acquired_at: DateTime<Utc>,
+    // 批次数量
+    pub amount: Shares,
+    // 批次单位成本
+    pub cost: Money,
+}
+
+// 卖出时匹配的批次
+#[derive(Debug, Clone)]
+pub struct ClosedLot {
+    // 匹配的买入批次, amount为本次卖出的数量
+    pub lot: Lot,
+    // 卖出交易
+    pub sell: Transaction,
+}
+
+// 根据交易记录计算剩余持仓批次
+pub fn open_lots(transactions: &[Transaction]) -> Vec<Lot> {
+    match_lots(transactions).0
+}
+
+// 按先进先出匹配卖出批次, 返回 (剩余批次, 已卖出批次)
+// 送转股与拆并股按比例调整已有批次, 持有期沿用原批次
+pub fn match_lots(transactions: &[Transaction]) -> (Vec<Lot>, Vec<ClosedLot>) {
+    let mut sorted: Vec<&Transaction> = transactions.iter().collect();
+    sorted.sort_by_key(|t| t.timestamp);
+
+    let mut lots: Vec<Lot> = Vec::new();
+    let mut closed: Vec<ClosedLot> = Vec::new();
+
+    for transaction in sorted {
+        match transaction.transaction_type {
+            TransactionType::Buy => {
+                lots.push(Lot {
+                    acquired_at: transaction.timestamp,
+                    amount: transaction.amount,
+                    cost: transaction.price,
+                });
+            },
+            TransactionType::Sell => {
+                let mut remaining = transaction.amount;
+                while remaining > 0 && !lots.is_empty() {
+                    let matched = lots[0].amount.min(remaining);
+                    closed.push(ClosedLot {
+                        lot: Lot { amount: matched, ..lots[0].clone() },
+                        sell: transaction.clone(),
+                    });
+                    remaining -= matched;
+                    if lots[0].amount > matched {
+                        lots[0].amount -= matched;
+                    } else {
+                        lots.remove(0);
+                    }
+                }
+            },
+            TransactionType::BonusShares | TransactionType::Split => {
+                let total: Shares = lots.iter().map(|l| l.amount).sum();
+                if total <= 0 {
+                    continue;
+                }
+
+                // 按比例分配新增股数, 取整余数计入最后一个批次, 批次总成本不变
+                let new_total = total + transaction.amount;
+                let mut allocated = 0;
+                let count = lots.len();
+                for (i, lot) in lots.iter_mut().enumerate() {
+                    let amount = if i + 1 == count {
+                        new_total - allocated
+                    } else {
+                        lot.amount * new_total / total
+                    };
+                    allocated += amount;
+                    if amount > 0 {
+                        lot.cost = lot.cost * Money::from(lot.amount) / Money::from(amount);
+                    }
+                    lot.amount = amount;
+                }
+                lots.retain(|l| l.amount > 0);
+            },
+            TransactionType::Dividend => {},
+        }
+    }
+
+    (lots, closed)
+}
@@ -0,0 +1,614 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashMap;
+use crate::cash::{CashEntry, CashEntryType};
+use crate::events::PortfolioEventKind;
+use crate::fees::FeeSchedule;
+use crate::market::trade_date;
+use crate::models::{Portfolio, Position, Transaction, TransactionType};
+use crate::money::{decimal, deserialize_shares, round_money, to_f64, Money, Shares};
+use crate::order::OrderSide;
+use crate::risk::format_breaches;
+
+// 计息天数基数 (年化利率按360天计)
+const DAYS_PER_YEAR: i64 = 360;
+
+// 融资融券设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarginConfig {
+    // 融资年利率
+    pub financing_rate: f64,
+    // 融券年费率
+    pub lending_rate: f64,
+    // 保证金比例 (融资买入和融券卖出占用保证金的比例)
+    pub initial_margin: f64,
+    // 担保证券折算率
+    pub collateral_haircut: f64,
+    // 维持担保比例警戒线
+    pub warning_ratio: f64,
+    // 追加担保物线
+    pub call_ratio: f64,
+    // 强制平仓线
+    pub liquidation_ratio: f64,
+    // 交易费用
+    #[serde(default)]
+    pub fees: FeeSchedule,
+}
+
+impl Default for MarginConfig {
+    fn default() -> Self {
+        Self {
+            financing_rate: 0.06,
+            lending_rate: 0.08,
+            initial_margin: 1.0,
+            collateral_haircut: 0.7,
+            warning_ratio: 1.5,
+            call_ratio: 1.3,
+            liquidation_ratio: 1.1,
+            fees: FeeSchedule::default(),
+        }
+    }
+}
+
+// 信用账户风险等级, 按维持担保比例划分
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MarginLevel {
+    // 正常
+    #[default]
+    Normal,
+    // 低于警戒线
+    Warning,
+    // 低于追保线, 需要追加担保物
+    Call,
+    // 低于平仓线, 可能被强制平仓
+    Liquidation,
+}
+
+// 融券负债
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShortPosition {
+    pub code: String,
+    // 未偿还的融券数量
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub amount: Shares,
+    // 融券卖出均价
+    pub price: Money,
+    // 当前价格
+    pub current_price: Option<f64>,
+    // 首次融券卖出时间
+    pub opened_at: DateTime<Utc>,
+}
+
+impl ShortPosition {
+    // 融券卖出所得, 只能用于买券还券
+    pub fn proceeds(&self) -> Money {
+        round_money(Money::from(self.amount) * self.price)
+    }
+
+    // 融券市值, 未估值时按卖出均价计算
+    pub fn market_value(&self) -> f64 {
+        self.amount as f64 * self.current_price.unwrap_or_else(|| to_f64(self.price))
+    }
+}
+
+// 信用账户
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarginAccount {
+    pub config: MarginConfig,
+    // 融资负债
+    pub financing_debt: Money,
+    // 融券负债
+    pub shorts: HashMap<String, ShortPosition>,
+    // 已计息到的日期
+    pub accrued_through: NaiveDate,
+    // 最近一次检查的风险等级, 用于判断是否越过阈值
+    #[serde(default)]
+    pub level: MarginLevel,
+}
+
+impl MarginAccount {
+    fn new(config: MarginConfig, opened_at: DateTime<Utc>) -> Self {
+        Self {
+            config,
+            financing_debt: Money::ZERO,
+            shorts: HashMap::new(),
+            accrued_through: trade_date(opened_at),
+            level: MarginLevel::Normal,
+        }
+    }
+
+    // 融券卖出所得合计
+    pub fn short_proceeds(&self) -> Money {
+        self.shorts.values().map(|s| s.proceeds()).sum()
+    }
+
+    // 融券市值合计
+    pub fn short_value(&self) -> f64 {
+        self.shorts.values().map(|s| s.market_value()).sum()
+    }
+
+    // 按当前价格计算的负债 (融资本金加融券市值)
+    pub fn liabilities(&self) -> f64 {
+        to_f64(self.financing_debt) + self.short_value()
+    }
+
+    fn has_liabilities(&self) -> bool {
+        !self.financing_debt.is_zero() || !self.shorts.is_empty()
+    }
+
+    // 按维持担保比例判断风险等级
+    pub fn level_of(&self, ratio: Option<f64>) -> MarginLevel {
+        match ratio {
+            Some(r) if r < self.config.liquidation_ratio => MarginLevel::Liquidation,
+            Some(r) if r < self.config.call_ratio => MarginLevel::Call,
+            Some(r) if r < self.config.warning_ratio => MarginLevel::Warning,
+            _ => MarginLevel::Normal,
+        }
+    }
+}
+
+// 融资融券操作, 作为事件记录以便重建信用账户
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MarginOperation {
+    // 开通信用账户
+    Open { config: MarginConfig },
+    // 融资借入, 随融资买入发生
+    Borrow { amount: Money, transaction_id: String },
+    // 直接还款
+    Repay { amount: Money },
+    // 融券卖出
+    ShortSell { code: String, quantity: Shares, price: Money, fee: Money, tax: Money },
+    // 买券还券
+    Cover { code: String, quantity: Shares, price: Money, fee: Money },
+    // 计收利息和融券费用
+    Interest { through: NaiveDate, financing: Money, lending: Money },
+}
+
+// 信用账户状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarginStatus {
+    pub portfolio: String,
+    pub cash: Money,
+    // 多头持仓市值 (未估值按成本)
+    pub long_value: f64,
+    pub financing_debt: Money,
+    pub short_value: f64,
+    // 维持担保比例, 没有负债时为None
+    pub maintenance_ratio: Option<f64>,
+    // 保证金可用余额
+    pub available_margin: f64,
+    pub level: MarginLevel,
+    // 恢复到警戒线需要追加的担保物
+    pub shortfall: f64,
+    pub shorts: Vec<ShortPosition>,
+}
+
+// 风险等级变化提醒
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarginAlert {
+    pub portfolio: String,
+    pub previous: MarginLevel,
+    pub level: MarginLevel,
+    pub maintenance_ratio: Option<f64>,
+    pub shortfall: f64,
+}
+
+impl MarginAlert {
+    // 风险是否上升
+    pub fn worsened(&self) -> bool {
+        self.level > self.previous
+    }
+
+    pub fn message(&self) -> String {
+        let ratio = self.maintenance_ratio
+            .map(|r| format!("{:.2}%", r * 100.0))
+            .unwrap_or_else(|| "无负债".to_string());
+        let mut message = format!("{} 维持担保比例{}, 风险等级 {:?} -> {:?}", self.portfolio, ratio, self.previous, self.level);
+        if self.shortfall > 0.0 {
+            message.push_str(&format!(", 需追加担保物{:.2}元", self.shortfall));
+        }
+        message
+    }
+}
+
+// 多头持仓估值, 未估值时按成本计算
+fn long_value(position: &Position) -> f64 {
+    position.market_value().unwrap_or_else(|| to_f64(position.total_cost()))
+}
+
+impl Portfolio {
+    pub fn margin_account(&self) -> Result<&MarginAccount, String> {
+        self.margin.as_ref().ok_or_else(|| "未开通融资融券".to_string())
+    }
+
+    // 融资负债和融券市值合计, 普通账户为0
+    pub fn margin_liabilities(&self) -> f64 {
+        self.margin.as_ref().map(|m| m.liabilities()).unwrap_or(0.0)
+    }
+
+    // 融资本金和融券卖出所得合计 (按成本计算的负债)
+    pub fn margin_cost(&self) -> Money {
+        self.margin.as_ref().map(|m| m.financing_debt + m.short_proceeds()).unwrap_or_default()
+    }
+
+    // 开通融资融券
+    pub fn open_margin(&mut self, config: MarginConfig) -> Result<(), String> {
+        if self.margin.is_some() {
+            return Err("已开通融资融券".to_string());
+        }
+        self.execute_margin(MarginOperation::Open { config })
+    }
+
+    // 执行操作并记录事件
+    fn execute_margin(&mut self, operation: MarginOperation) -> Result<(), String> {
+        self.apply_margin(&operation, Utc::now())?;
+        self.log_event(PortfolioEventKind::Margin { operation });
+        Ok(())
+    }
+
+    fn record_margin_cash(&mut self, entry_type: CashEntryType, amount: Money, at: DateTime<Utc>, note: &str) {
+        if amount.is_zero() {
+            return;
+        }
+        let mut entry = CashEntry::new(entry_type, amount);
+        entry.timestamp = at;
+        entry.note = Some(note.to_string());
+        self.cash_ledger.record(entry);
+    }
+
+    // 按操作更新信用账户和现金, 不记录事件
+    pub(crate) fn apply_margin(&mut self, operation: &MarginOperation, at: DateTime<Utc>) -> Result<(), String> {
+        if let MarginOperation::Open { config } = operation {
+            self.margin = Some(MarginAccount::new(config.clone(), at));
+            return Ok(());
+        }
+        if self.margin.is_none() {
+            return Err("未开通融资融券".to_string());
+        }
+
+        match operation {
+            MarginOperation::Open { .. } => {},
+            MarginOperation::Borrow { amount, .. } => {
+                self.record_margin_cash(CashEntryType::Financing, *amount, at, "融资借入");
+                if let Some(margin) = self.margin.as_mut() {
+                    margin.financing_debt += *amount;
+                }
+            },
+            MarginOperation::Repay { amount } => {
+                let debt = self.margin.as_ref().map(|m| m.financing_debt).unwrap_or_default();
+                if *amount > debt {
+                    return Err("还款金额超过融资负债".to_string());
+                }
+                if *amount > self.available_cash() {
+                    return Err("现金余额不足".to_string());
+                }
+                self.record_margin_cash(CashEntryType::Financing, -*amount, at, "融资还款");
+                if let Some(margin) = self.margin.as_mut() {
+                    margin.financing_debt -= *amount;
+                }
+            },
+            MarginOperation::ShortSell { code, quantity, price, fee, tax } => {
+                let value = round_money(Money::from(*quantity) * *price);
+                self.record_margin_cash(CashEntryType::TradeSettlement, value, at, "融券卖出");
+                self.record_margin_cash(CashEntryType::Fee, -(*fee + *tax), at, "融券卖出费用");
+                if let Some(margin) = self.margin.as_mut() {
+                    let short = margin.shorts.entry(code.clone()).or_insert_with(|| ShortPosition {
+                        code: code.clone(),
+                        amount: 0,
+                        price: *price,
+                        current_price: None,
+                        opened_at: at,
+                    });
+                    let amount = short.amount + *quantity;
+                    short.price = (Money::from(short.amount) * short.price + Money::from(*quantity) * *price) / Money::from(amount);
+                    short.amount = amount;
+                }
+            },
+            MarginOperation::Cover { code, quantity, price, fee } => {
+                let held = self.margin.as_ref().and_then(|m| m.shorts.get(code)).map(|s| s.amount).unwrap_or(0);
+                if held == 0 {
+                    return Err("没有该股票的融券负债".to_string());
+                }
+                if *quantity > held {
+                    return Err("还券数量超过融券负债".to_string());
+                }
+                let value = round_money(Money::from(*quantity) * *price);
+                if value + *fee > self.cash_balance() {
+                    return Err("现金余额不足".to_string());
+                }
+                self.record_margin_cash(CashEntryType::TradeSettlement, -value, at, "买券还券");
+                self.record_margin_cash(CashEntryType::Fee, -*fee, at, "买券还券费用");
+                if let Some(margin) = self.margin.as_mut() {
+                    if held == *quantity {
+                        margin.shorts.remove(code);
+                    } else if let Some(short) = margin.shorts.get_mut(code) {
+                        short.amount -= *quantity;
+                    }
+                }
+            },
+            MarginOperation::Interest { through, financing, lending } => {
+                self.record_margin_cash(CashEntryType::Interest, -*financing, at, "融资利息");
+                self.record_margin_cash(CashEntryType::Interest, -*lending, at, "融券费用");
+                if let Some(margin) = self.margin.as_mut() {
+                    margin.accrued_through = *through;
+                }
+            },
+        }
+
+        self.last_update = Utc::now();
+        Ok(())
+    }
+
+    // 计收截至指定日期的融资利息和融券费用, 返回本次计收的金额
+    pub fn accrue_margin_interest(&mut self, through: NaiveDate) -> Result<Money, String> {
+        let margin = self.margin_account()?;
+        let days = (through - margin.accrued_through).num_days();
+        if days <= 0 {
+            return Ok(Money::ZERO);
+        }
+
+        // 没有负债的期间不产生利息, 只推进计息日期
+        if !margin.has_liabilities() {
+            if let Some(margin) = self.margin.as_mut() {
+                margin.accrued_through = through;
+            }
+            return Ok(Money::ZERO);
+        }
+
+        let factor = |rate: f64| decimal(rate) * Money::from(days) / Money::from(DAYS_PER_YEAR);
+        let financing = round_money(margin.financing_debt * factor(margin.config.financing_rate));
+        let lending = round_money(decimal(margin.short_value()) * factor(margin.config.lending_rate));
+
+        self.execute_margin(MarginOperation::Interest { through, financing, lending })?;
+        Ok(financing + lending)
+    }
+
+    // 保证金可用余额 (简化算法):
+    // 现金(不含融券卖出所得) + (多头市值 - 融资负债) x 折算率(亏损按100%) - (融资负债 + 融券市值) x 保证金比例
+    fn available_margin(&self, margin: &MarginAccount) -> f64 {
+        let free_cash = to_f64(self.available_cash());
+        let long_value: f64 = self.positions.values().map(long_value).sum();
+        let net_long = long_value - to_f64(margin.financing_debt);
+        let collateral = free_cash + if net_long > 0.0 { net_long * margin.config.collateral_haircut } else { net_long };
+        collateral - margin.liabilities() * margin.config.initial_margin
+    }
+
+    // 开仓前检查保证金和风控
+    fn check_margin_trade(&self, code: &str, side: OrderSide, quantity: Shares, price: Money) -> Result<(), String> {
+        let margin = self.margin_account()?;
+        if quantity <= 0 || price <= Money::ZERO {
+            return Err("数量和价格必须大于0".to_string());
+        }
+
+        let required = to_f64(Money::from(quantity) * price) * margin.config.initial_margin;
+        let available = self.available_margin(margin);
+        if required > available {
+            return Err(format!("保证金可用余额{:.2}不足, 需要{:.2}", available, required));
+        }
+
+        let breaches = self.check_risk(code, side, quantity, to_f64(price), None);
+        if !breaches.is_empty() {
+            return Err(format_breaches(&breaches));
+        }
+        Ok(())
+    }
+
+    // 融资买入, 买入金额由融资借入, 费用由现金支付
+    pub fn margin_buy(&mut self, code: &str, quantity: Shares, price: Money) -> Result<Transaction, String> {
+        self.check_margin_trade(code, OrderSide::Buy, quantity, price)?;
+        // 负债变化前先计收已发生的利息, 利息扣除后再检查费用
+        self.accrue_margin_interest(trade_date(Utc::now()))?;
+
+        let margin = self.margin_account()?;
+        let value = round_money(Money::from(quantity) * price);
+        let (fee, _) = margin.config.fees.costs(OrderSide::Buy, value);
+        if fee > self.available_cash() {
+            return Err("现金余额不足以支付费用".to_string());
+        }
+
+        let mut transaction = Transaction::new(code.to_string(), TransactionType::Buy, quantity, price);
+        transaction.fee = Some(fee);
+        transaction.note = Some("融资买入".to_string());
+
+        // 借款和买入都成功后才记录事件, 买入失败时恢复借款前的现金和负债
+        let borrow = MarginOperation::Borrow { amount: value, transaction_id: transaction.id.clone() };
+        let (cash_ledger, margin) = (self.cash_ledger.clone(), self.margin.clone());
+        self.apply_margin(&borrow, Utc::now())?;
+        if let Err(e) = self.apply_transaction(transaction.clone()) {
+            self.cash_ledger = cash_ledger;
+            self.margin = margin;
+            return Err(e);
+        }
+        self.log_event(PortfolioEventKind::Margin { operation: borrow });
+        self.log_event(PortfolioEventKind::Trade { transaction: transaction.clone() });
+        Ok(transaction)
+    }
+
+    // 融券卖出
+    pub fn short_sell(&mut self, code: &str, quantity: Shares, price: Money) -> Result<ShortPosition, String> {
+        self.check_margin_trade(code, OrderSide::Sell, quantity, price)?;
+        let margin = self.margin_account()?;
+        let (fee, tax) = margin.config.fees.costs(OrderSide::Sell, round_money(Money::from(quantity) * price));
+
+        self.accrue_margin_interest(trade_date(Utc::now()))?;
+        self.execute_margin(MarginOperation::ShortSell { code: code.to_string(), quantity, price, fee, tax })?;
+        self.margin_account().map(|m| m.shorts[code].clone())
+    }
+
+    // 买券还券
+    pub fn cover_short(&mut self, code: &str, quantity: Shares, price: Money) -> Result<(), String> {
+        let margin = self.margin_account()?;
+        if quantity <= 0 || price <= Money::ZERO {
+            return Err("数量和价格必须大于0".to_string());
+        }
+        let (fee, _) = margin.config.fees.costs(OrderSide::Buy, round_money(Money::from(quantity) * price));
+
+        self.accrue_margin_interest(trade_date(Utc::now()))?;
+        self.execute_margin(MarginOperation::Cover { code: code.to_string(), quantity, price, fee })
+    }
+
+    // 直接还款
+    pub fn repay_financing(&mut self, amount: Money) -> Result<(), String> {
+        self.margin_account()?;
+        let amount = round_money(amount);
+        if amount <= Money::ZERO {
+            return Err("还款金额必须大于0".to_string());
+        }
+
+        self.accrue_margin_interest(trade_date(Utc::now()))?;
+        self.execute_margin(MarginOperation::Repay { amount })
+    }
+
+    // 按当前价格计算信用账户状态
+    pub fn margin_status(&self) -> Result<MarginStatus, String> {
+        let margin = self.margin_account()?;
+        let cash = self.cash_balance();
+        let long_value: f64 = self.positions.values().map(long_value).sum();
+        let liabilities = margin.liabilities();
+        let assets = to_f64(cash) + long_value;
+
+        let maintenance_ratio = if margin.has_liabilities() && liabilities > 0.0 {
+            Some(assets / liabilities)
+        } else {
+            None
+        };
+        let level = margin.level_of(maintenance_ratio);
+        let shortfall = if level >= MarginLevel::Call {
+            (liabilities * margin.config.warning_ratio - assets).max(0.0)
+        } else {
+            0.0
+        };
+
+        let mut shorts: Vec<ShortPosition> = margin.shorts.values().cloned().collect();
+        shorts.sort_by(|a, b| a.code.cmp(&b.code));
+
+        Ok(MarginStatus {
+            portfolio: self.name.clone(),
+            cash,
+            long_value,
+            financing_debt: margin.financing_debt,
+            short_value: margin.short_value(),
+            maintenance_ratio,
+            available_margin: self.available_margin(margin),
+            level,
+            shortfall,
+            shorts,
+        })
+    }
+
+    // 检查风险等级, 等级变化时返回提醒
+    pub fn check_margin(&mut self) -> Option<MarginAlert> {
+        let status = self.margin_status().ok()?;
+        let margin = self.margin.as_mut()?;
+        if status.level == margin.level {
+            return None;
+        }
+
+        let previous = margin.level;
+        margin.level = status.level;
+        Some(MarginAlert {
+            portfolio: self.name.clone(),
+            previous,
+            level: status.level,
+            maintenance_ratio: status.maintenance_ratio,
+            shortfall: status.shortfall,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market::day_start;
+    use crate::valuation::Quote;
+
+    fn quotes(price: f64) -> HashMap<String, Quote> {
+        ["600000", "601398"].iter().map(|code| (code.to_string(), Quote {
+            code: code.to_string(),
+            name: None,
+            price,
+            change_percent: None,
+            volume: None,
+            sector: None,
+            market_cap: None,
+        })).collect()
+    }
+
+    #[test]
+    fn test_margin_buy_short_and_margin_call() {
+        let mut portfolio = Portfolio::new("test".to_string(), Money::from(100000));
+        assert!(portfolio.short_sell("601398", 1000, Money::from(10)).is_err());
+
+        let config = MarginConfig { fees: FeeSchedule::zero(), ..MarginConfig::default() };
+        portfolio.open_margin(config).unwrap();
+
+        // 100000现金作为保证金, 融资买入50000, 融券卖出50000
+        portfolio.margin_buy("600000", 5000, Money::from(10)).unwrap();
+        portfolio.short_sell("601398", 5000, Money::from(10)).unwrap();
+        assert_eq!(portfolio.cash_balance(), Money::from(150000));
+        // 融券卖出所得不能出金或买入
+        assert_eq!(portfolio.available_cash(), Money::from(100000));
+        assert!(portfolio.withdraw(Money::from(100001), None).is_err());
+        let buy = Transaction::new("600519".to_string(), TransactionType::Buy, 10100, Money::from(10));
+        assert!(portfolio.add_transaction(buy).unwrap_err().contains("现金余额不足"));
+        assert!(portfolio.margin_buy("600000", 1000, Money::from(10)).is_err());
+
+        // 净资产不含负债
+        portfolio.mark_to_market(&quotes(10.0), Utc::now());
+        assert!((portfolio.total_market_value() - 100000.0).abs() < 1e-6);
+        let status = portfolio.margin_status().unwrap();
+        assert_eq!(status.maintenance_ratio, Some(2.0));
+        assert!(status.available_margin.abs() < 1e-6);
+        assert!(portfolio.check_margin().is_none());
+
+        // 按日计息
+        let today = portfolio.margin_account().unwrap().accrued_through;
+        let interest = portfolio.accrue_margin_interest(today + chrono::Duration::days(10)).unwrap();
+        // 50000 x 6% x 10 / 360 + 50000 x 8% x 10 / 360
+        assert_eq!(interest, Money::from(83) + Money::new(33, 2) + Money::from(111) + Money::new(11, 2));
+        assert_eq!(portfolio.accrue_margin_interest(today + chrono::Duration::days(10)).unwrap(), Money::ZERO);
+
+        // 多头下跌、空头上涨: (149805.56 + 5000 x 4) / (50000 + 5000 x 16) = 130.6%
+        let mut prices = quotes(4.0);
+        prices.get_mut("601398").unwrap().price = 16.0;
+        portfolio.mark_to_market(&prices, Utc::now());
+        let alert = portfolio.check_margin().unwrap();
+        assert!(alert.worsened());
+        assert_eq!(alert.level, MarginLevel::Warning);
+
+        prices.get_mut("601398").unwrap().price = 18.0;
+        portfolio.mark_to_market(&prices, Utc::now());
+        let alert = portfolio.check_margin().unwrap();
+        assert_eq!(alert.level, MarginLevel::Call);
+        assert!(alert.shortfall > 0.0);
+
+        // 买券还券和还款后恢复正常, 信用账户可由事件重建
+        portfolio.cover_short("601398", 5000, Money::from(18)).unwrap();
+        portfolio.repay_financing(Money::from(50000)).unwrap();
+        assert!(portfolio.margin_account().unwrap().shorts.is_empty());
+        assert_eq!(portfolio.check_margin().unwrap().level, MarginLevel::Normal);
+
+        let rebuilt = Portfolio::replay("test", &portfolio.events, None).unwrap();
+        assert_eq!(rebuilt.cash_balance(), portfolio.cash_balance());
+        assert_eq!(rebuilt.margin_account().unwrap().financing_debt, Money::ZERO);
+        assert!(portfolio.repay_financing(Money::from(1)).is_err());
+    }
+
+    #[test]
+    fn test_valuation_accrues_interest() {
+        let mut portfolio = Portfolio::new("test".to_string(), Money::from(100000));
+        let config = MarginConfig { fees: FeeSchedule::zero(), ..MarginConfig::default() };
+        portfolio.open_margin(config).unwrap();
+        portfolio.margin_buy("600000", 3600, Money::from(10)).unwrap();
+        // 先记录融资借入再记录买入, 重放时现金充足
+        let kinds: Vec<&PortfolioEventKind> = portfolio.events.iter().rev().take(2).map(|e| &e.kind).collect();
+        assert!(matches!(kinds[..], [PortfolioEventKind::Trade { .. }, PortfolioEventKind::Margin { operation: MarginOperation::Borrow { .. } }]));
+
+        // 估值时计收截至估值日的利息: 36000 x 6% x 10 / 360 = 60
+        let today = portfolio.margin_account().unwrap().accrued_through;
+        let cash = portfolio.cash_balance();
+        portfolio.mark_to_market(&quotes(10.0), day_start(today + chrono::Duration::days(10)));
+        assert_eq!(cash - portfolio.cash_balance(), Money::from(60));
+        assert_eq!(portfolio.margin_account().unwrap().accrued_through, today + chrono::Duration::days(10));
+    }
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone, Utc};
+use crate::money::Shares;
+
+// 交易所所在时区 (北京时间, UTC+8)
+pub fn exchange_offset() -> FixedOffset {
+    FixedOffset::east_opt(8 * 3600).unwrap()
+}
+
+// 获取时间点对应的交易日期 (按北京时间)
+pub fn trade_date(timestamp: DateTime<Utc>) -> NaiveDate {
+    timestamp.with_timezone(&exchange_offset()).date_naive()
+}
+
+// 获取交易日期开始时刻 (北京时间0点) 对应的UTC时间
+pub fn day_start(date: NaiveDate) -> DateTime<Utc> {
+    exchange_offset()
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+// 规范化证券代码: 去除表格导出时附带的引号和等号, 并补齐6位数字代码
+pub fn normalize_code(raw: &str) -> String {
+    let code = raw.trim()
+        .trim_start_matches('=')
+        .trim_matches('"')
+        .trim_matches('\'')
+        .trim();
+
+    if !code.is_empty() && code.len() < 6 && code.chars().all(|c| c.is_ascii_digit()) {
+        format!("{:0>6}", code)
+    } else {
+        code.to_uppercase()
+    }
+}
+
+// 每手股数
+pub const LOT_SIZE: Shares = 100;
+
+// 向下取整到整手
+pub fn round_lot(quantity: f64) -> Shares {
+    (quantity / LOT_SIZE as f64).floor() as Shares * LOT_SIZE
+}
+
+// 涨跌停幅度: ST股5%, 创业板和科创板20%, 北交所30%, 其余主板10%
+pub fn price_limit(code: &str, name: Option<&str>) -> f64 {
+    if name.is_some_and(|n| n.contains("ST")) {
+        return 0.05;
+    }
+    match board(code) {
+        Board::ChiNext | Board::Star => 0.20,
+        Board::Beijing => 0.30,
+        _ => 0.10,
+    }
+}
+
+// 上市板块
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Board {
+    // 沪市主板
+    ShanghaiMain,
+    // 深市主板
+    ShenzhenMain,
+    // 创业板
+    ChiNext,
+    // 科创板
+    Star,
+    // 北交所
+    Beijing,
+    Other,
+}
+
+// 按证券代码判断所属板块
+pub fn board(code: &str) -> Board {
+    let digits = code.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    if ["688", "689"].iter().any(|p| digits.starts_with(p)) {
+        Board::Star
+    } else if ["300", "301"].iter().any(|p| digits.starts_with(p)) {
+        Board::ChiNext
+    } else if digits.starts_with("60") {
+        Board::ShanghaiMain
+    } else if ["000", "001", "002", "003"].iter().any(|p| digits.starts_with(p)) {
+        Board::ShenzhenMain
+    } else if ["4", "8", "92"].iter().any(|p| digits.starts_with(p)) {
+        Board::Beijing
+    } else {
+        Board::Other
+    }
+}
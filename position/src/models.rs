@@ -1,6 +1,14 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use chrono::{DateTime, Utc};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use crate::cash::{CashEntry, CashEntryType, CashLedger};
+use crate::performance::NavPoint;
+use crate::order::{Order, OrderSide};
+use crate::events::{PortfolioEvent, PortfolioEventKind};
+use crate::risk::{RiskLimits, format_breaches};
+use crate::paper::PaperConfig;
+use crate::margin::MarginAccount;
+use crate::money::{deserialize_optional_money, deserialize_shares, money, round_money, to_f64, Money, Shares};
 
 // 持仓记录
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -9,12 +17,19 @@ pub struct Position {
     pub code: String,
     // 股票名称
     pub name: String,
+    // 所属行业
+    #[serde(default)]
+    pub sector: Option<String>,
     // 持仓数量
-    pub amount: f64,
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub amount: Shares,
     // 持仓成本
-    pub cost: f64,
+    pub cost: Money,
     // 当前价格
     pub current_price: Option<f64>,
+    // 估值时间
+    #[serde(default)]
+    pub valued_at: Option<DateTime<Utc>>,
     // 最后更新时间
     pub last_update: DateTime<Utc>,
     // 交易记录
@@ -22,13 +37,15 @@ pub struct Position {
 }
 
 impl Position {
-    pub fn new(code: String, name: String, amount: f64, cost: f64) -> Self {
+    pub fn new(code: String, name: String, amount: Shares, cost: Money) -> Self {
         Self {
             code,
             name,
+            sector: None,
             amount,
             cost,
             current_price: None,
+            valued_at: None,
             last_update: Utc::now(),
             transactions: Vec::new(),
         }
@@ -48,26 +65,26 @@ impl Position {
     
     // 计算当前市值
     pub fn market_value(&self) -> Option<f64> {
-        self.current_price.map(|price| price * self.amount)
+        self.current_price.map(|price| price * self.amount as f64)
     }
     
     // 计算持仓成本
-    pub fn total_cost(&self) -> f64 {
-        self.cost * self.amount
+    pub fn total_cost(&self) -> Money {
+        round_money(self.cost * Money::from(self.amount))
     }
     
     // 计算盈亏
     pub fn profit_loss(&self) -> Option<f64> {
-        self.market_value().map(|value| value - self.total_cost())
+        self.market_value().map(|value| value - to_f64(self.total_cost()))
     }
     
     // 计算盈亏比例
     pub fn profit_loss_percent(&self) -> Option<f64> {
-        if self.total_cost() == 0.0 {
+        if self.total_cost().is_zero() {
             return None;
         }
         
-        self.profit_loss().map(|pl| pl / self.total_cost() * 100.0)
+        self.profit_loss().map(|pl| pl / to_f64(self.total_cost()) * 100.0)
     }
     
     // 添加交易记录
@@ -78,28 +95,41 @@ impl Position {
     
     // 根据交易记录更新持仓
     fn update_position_from_transactions(&mut self) {
-        let mut total_amount = 0.0;
-        let mut total_cost = 0.0;
+        let mut total_amount: Shares = 0;
+        let mut total_cost = Money::ZERO;
         
-        for transaction in &self.transactions {
+        let mut transactions: Vec<&Transaction> = self.transactions.iter().collect();
+        transactions.sort_by_key(|t| t.timestamp);
+        
+        for transaction in transactions {
             match transaction.transaction_type {
                 TransactionType::Buy => {
                     total_amount += transaction.amount;
-                    total_cost += transaction.amount * transaction.price;
+                    total_cost += transaction.total_value();
                 },
                 TransactionType::Sell => {
+                    // 按平均成本结转卖出部分的成本
+                    if total_amount > 0 {
+                        total_cost -= total_cost * Money::from(transaction.amount) / Money::from(total_amount);
+                    }
                     total_amount -= transaction.amount;
-                    // 卖出时不减少总成本, 而是在计算平均成本时调整分母
+                },
+                TransactionType::BonusShares | TransactionType::Split => {
+                    // 送转股与拆并股只改变数量, 总成本不变
+                    total_amount += transaction.amount;
+                },
+                TransactionType::Dividend => {
+                    // 现金分红不影响持仓数量和成本
                 }
             }
         }
         
         self.amount = total_amount;
         
-        if total_amount > 0.0 {
-            self.cost = total_cost / total_amount;
+        if total_amount > 0 {
+            self.cost = total_cost / Money::from(total_amount);
         } else {
-            self.cost = 0.0;
+            self.cost = Money::ZERO;
         }
         
         self.last_update = Utc::now();
@@ -111,6 +141,12 @@ impl Position {
 pub enum TransactionType {
     Buy,
     Sell,
+    // 现金分红
+    Dividend,
+    // 送股/转增股
+    BonusShares,
+    // 拆股/并股
+    Split,
 }
 
 // 交易记录
@@ -123,19 +159,26 @@ pub struct Transaction {
     // 交易类型
     pub transaction_type: TransactionType,
     // 交易数量
-    pub amount: f64,
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub amount: Shares,
     // 交易价格
-    pub price: f64,
+    pub price: Money,
     // 交易时间
     pub timestamp: DateTime<Utc>,
     // 交易费用
-    pub fee: Option<f64>,
+    #[serde(default, deserialize_with = "deserialize_optional_money")]
+    pub fee: Option<Money>,
+    // 税费 (红利税等)
+    #[serde(default, deserialize_with = "deserialize_optional_money")]
+    pub tax: Option<Money>,
     // 交易备注
     pub note: Option<String>,
+    // 外部成交编号 (券商导入)
+    pub external_id: Option<String>,
 }
 
 impl Transaction {
-    pub fn new(code: String, transaction_type: TransactionType, amount: f64, price: f64) -> Self {
+    pub fn new(code: String, transaction_type: TransactionType, amount: Shares, price: Money) -> Self {
         Self {
             id: format!("{}", uuid::Uuid::new_v4()),
             code,
@@ -144,47 +187,184 @@ impl Transaction {
             price,
             timestamp: Utc::now(),
             fee: None,
+            tax: None,
             note: None,
+            external_id: None,
         }
     }
     
-    // 计算交易金额
-    pub fn total_value(&self) -> f64 {
-        self.amount * self.price
+    // 计算交易金额 (四舍五入到分)
+    pub fn total_value(&self) -> Money {
+        round_money(Money::from(self.amount) * self.price)
     }
 }
 
 // 投资组合
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub struct Portfolio {
     // 投资组合名称
     pub name: String,
     // 持仓列表
     pub positions: HashMap<String, Position>,
-    // 现金余额
-    pub cash_balance: f64,
+    // 现金账本
+    #[serde(default)]
+    pub cash_ledger: CashLedger,
     // 创建时间
     pub created_at: DateTime<Utc>,
     // 最后更新时间
     pub last_update: DateTime<Utc>,
+    // 最近估值时间
+    #[serde(default)]
+    pub valued_at: Option<DateTime<Utc>>,
+    // 已应用的公司行动
+    #[serde(default)]
+    pub applied_actions: Vec<String>,
+    // 每日净值记录
+    #[serde(default)]
+    pub nav_history: Vec<NavPoint>,
+    // 已导入的券商成交编号
+    #[serde(default)]
+    pub imported_trades: HashSet<String>,
+    // 委托订单
+    #[serde(default)]
+    pub orders: Vec<Order>,
+    // 模拟交易设置, 设置后订单由模拟撮合自动成交
+    #[serde(default)]
+    pub paper: Option<PaperConfig>,
+    // 风控限制
+    #[serde(default)]
+    pub risk_limits: RiskLimits,
+    // 事件记录, 持仓和现金可由事件重建
+    #[serde(default)]
+    pub events: Vec<PortfolioEvent>,
+    // 信用账户, 开通融资融券后设置
+    #[serde(default)]
+    pub margin: Option<MarginAccount>,
+}
+
+impl Serialize for Portfolio {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Portfolio::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Portfolio {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // 旧版本只保存cash_balance字段, 没有现金账本
+        #[derive(Deserialize)]
+        struct Saved {
+            #[serde(flatten, with = "Portfolio")]
+            portfolio: Portfolio,
+            #[serde(default)]
+            cash_balance: Option<f64>,
+        }
+
+        let Saved { mut portfolio, cash_balance } = Saved::deserialize(deserializer)?;
+        if let Some(cash_balance) = cash_balance.map(money).filter(|c| !c.is_zero()) {
+            if portfolio.cash_ledger.entries.is_empty() {
+                // 旧版本的现金余额转为一笔期初入金
+                let mut entry = CashEntry::new(CashEntryType::Deposit, cash_balance);
+                entry.timestamp = portfolio.created_at;
+                entry.note = Some("期初现金余额".to_string());
+                portfolio.cash_ledger.record(entry);
+            }
+        }
+        portfolio.seed_events();
+        Ok(portfolio)
+    }
 }
 
 impl Portfolio {
-    pub fn new(name: String, cash_balance: f64) -> Self {
+    pub fn new(name: String, cash_balance: Money) -> Self {
+        let mut cash_ledger = CashLedger::new();
+        let mut events = vec![PortfolioEvent::new(PortfolioEventKind::Created)];
+        if cash_balance > Money::ZERO {
+            let mut entry = CashEntry::new(CashEntryType::Deposit, cash_balance);
+            entry.note = Some("初始资金".to_string());
+            cash_ledger.record(entry.clone());
+            events.push(PortfolioEvent::new(PortfolioEventKind::Cash { entry }));
+        }
+        
         Self {
             name,
             positions: HashMap::new(),
-            cash_balance,
+            cash_ledger,
             created_at: Utc::now(),
             last_update: Utc::now(),
+            valued_at: None,
+            applied_actions: Vec::new(),
+            nav_history: Vec::new(),
+            imported_trades: HashSet::new(),
+            orders: Vec::new(),
+            paper: None,
+            risk_limits: RiskLimits::default(),
+            events,
+            margin: None,
         }
     }
+    
+    // 现金余额 (由现金账本汇总)
+    pub fn cash_balance(&self) -> Money {
+        self.cash_ledger.balance()
+    }
+
+    // 可用现金: 融券卖出所得只能用于买券还券, 不能出金或买入
+    pub fn available_cash(&self) -> Money {
+        self.cash_balance() - self.margin.as_ref().map(|m| m.short_proceeds()).unwrap_or_default()
+    }
+    
+    // 入金
+    pub fn deposit(&mut self, amount: Money, note: Option<String>) -> Result<CashEntry, String> {
+        let amount = round_money(amount);
+        if amount <= Money::ZERO {
+            return Err("入金金额必须大于0".to_string());
+        }
+        
+        let mut entry = CashEntry::new(CashEntryType::Deposit, amount);
+        entry.note = note;
+        self.cash_ledger.record(entry.clone());
+        self.log_event(PortfolioEventKind::Cash { entry: entry.clone() });
+        self.last_update = Utc::now();
+        Ok(entry)
+    }
+    
+    // 出金
+    pub fn withdraw(&mut self, amount: Money, note: Option<String>) -> Result<CashEntry, String> {
+        let amount = round_money(amount);
+        if amount <= Money::ZERO {
+            return Err("出金金额必须大于0".to_string());
+        }
+        if amount > self.available_cash() {
+            return Err("现金余额不足".to_string());
+        }
+        
+        let mut entry = CashEntry::new(CashEntryType::Withdrawal, -amount);
+        entry.note = note;
+        self.cash_ledger.record(entry.clone());
+        self.log_event(PortfolioEventKind::Cash { entry: entry.clone() });
+        self.last_update = Utc::now();
+        Ok(entry)
+    }
+    
+    // 记录交易产生的现金流水
+    fn record_transaction_cash(&mut self, entry_type: CashEntryType, amount: Money, transaction: &Transaction) {
+        let amount = round_money(amount);
+        if amount.is_zero() {
+            return;
+        }
+        
+        let mut entry = CashEntry::new(entry_type, amount);
+        entry.timestamp = transaction.timestamp;
+        entry.reference = Some(transaction.id.clone());
+        self.cash_ledger.record(entry);
+    }
 
     pub fn info(&self) -> String {
         let position_infos = self.positions.values().map(|p| p.info()).collect::<Vec<String>>().join("\n");
         format!("组合名称: {}, 现金余额: {}, 持仓数量: {}, 总市值: {}, 总成本: {}, 总盈亏: {}, 总盈亏比例: {}\n持仓信息:\n{}",
             self.name,
-            self.cash_balance,
+            self.cash_balance(),
             self.positions.len(),
             self.total_market_value(),
             self.total_cost(),
@@ -194,41 +374,42 @@ impl Portfolio {
         )
     }
     
-    // 获取总市值
+    // 获取总市值 (信用账户扣除融资融券负债)
     pub fn total_market_value(&self) -> f64 {
         let positions_value: f64 = self.positions.values()
             .filter_map(|p| p.market_value())
             .sum();
         
-        positions_value + self.cash_balance
+        positions_value + to_f64(self.cash_balance()) - self.margin_liabilities()
     }
     
     // 获取总成本
-    pub fn total_cost(&self) -> f64 {
-        let positions_cost: f64 = self.positions.values()
+    pub fn total_cost(&self) -> Money {
+        let positions_cost: Money = self.positions.values()
             .map(|p| p.total_cost())
             .sum();
         
-        positions_cost + self.cash_balance
+        positions_cost + self.cash_balance() - self.margin_cost()
     }
     
-    // 计算总盈亏
+    // 计算总盈亏 (剔除入金和出金)
     pub fn total_profit_loss(&self) -> f64 {
-        self.total_market_value() - self.total_cost()
+        self.total_market_value() - to_f64(self.cash_ledger.net_contributions())
     }
     
-    // 计算总盈亏比例
+    // 计算总盈亏比例 (相对净投入资金)
     pub fn total_profit_loss_percent(&self) -> Option<f64> {
-        let total_cost = self.total_cost();
-        if total_cost == 0.0 {
+        let net_contributions = to_f64(self.cash_ledger.net_contributions());
+        if net_contributions <= 0.0 {
             return None;
         }
         
-        Some(self.total_profit_loss() / total_cost * 100.0)
+        Some(self.total_profit_loss() / net_contributions * 100.0)
     }
     
     // 添加持仓
     pub fn add_position(&mut self, position: Position) {
+        self.log_event(PortfolioEventKind::AddPosition { position: position.clone() });
         self.positions.insert(position.code.clone(), position);
         self.last_update = Utc::now();
     }
@@ -237,25 +418,50 @@ impl Portfolio {
     pub fn remove_position(&mut self, code: &str) -> Option<Position> {
         let position = self.positions.remove(code);
         if position.is_some() {
+            self.log_event(PortfolioEventKind::RemovePosition { code: code.to_string() });
             self.last_update = Utc::now();
         }
         position
     }
     
-    // 添加交易
+    // 添加交易, 买卖交易需先通过风控检查
     pub fn add_transaction(&mut self, transaction: Transaction) -> Result<(), String> {
+        if let Some(side) = OrderSide::from_transaction_type(transaction.transaction_type) {
+            let breaches = self.check_risk(&transaction.code, side, transaction.amount, to_f64(transaction.price), None);
+            if !breaches.is_empty() {
+                return Err(format_breaches(&breaches));
+            }
+        }
+
+        self.record_transaction(transaction)
+    }
+
+    // 记录已成交的交易, 不做风控检查 (用于券商成交和已通过风控的订单成交)
+    pub fn record_transaction(&mut self, transaction: Transaction) -> Result<(), String> {
+        self.apply_transaction(transaction.clone())?;
+        self.log_event(PortfolioEventKind::Trade { transaction });
+        Ok(())
+    }
+
+    // 按交易更新持仓和现金, 不记录事件
+    pub(crate) fn apply_transaction(&mut self, transaction: Transaction) -> Result<(), String> {
         let code = transaction.code.clone();
         
         match transaction.transaction_type {
             TransactionType::Buy => {
                 // 检查现金余额是否足够
-                let total_cost = transaction.total_value();
-                if total_cost > self.cash_balance {
+                let total_value = transaction.total_value();
+                let fee = transaction.fee.unwrap_or_default();
+                // 部分交割单的买入也有印花税等税费
+                let tax = transaction.tax.unwrap_or_default();
+                if total_value + fee + tax > self.available_cash() {
                     return Err("现金余额不足".to_string());
                 }
                 
                 // 扣除现金
-                self.cash_balance -= total_cost;
+                self.record_transaction_cash(CashEntryType::TradeSettlement, -total_value, &transaction);
+                self.record_transaction_cash(CashEntryType::Fee, -fee, &transaction);
+                self.record_transaction_cash(CashEntryType::Fee, -tax, &transaction);
                 
                 // 更新持仓
                 if let Some(position) = self.positions.get_mut(&code) {
@@ -284,17 +490,43 @@ impl Portfolio {
                 
                 // 增加现金
                 let total_value = transaction.total_value();
-                self.cash_balance += total_value;
+                self.record_transaction_cash(CashEntryType::TradeSettlement, total_value, &transaction);
+                self.record_transaction_cash(CashEntryType::Fee, -transaction.fee.unwrap_or_default(), &transaction);
+                // 卖出印花税
+                self.record_transaction_cash(CashEntryType::Fee, -transaction.tax.unwrap_or_default(), &transaction);
                 
                 // 更新持仓
                 if let Some(position) = self.positions.get_mut(&code) {
                     position.add_transaction(transaction);
                     
                     // 如果持仓数量为0, 则移除持仓
-                    if position.amount == 0.0 {
+                    if position.amount == 0 {
                         self.positions.remove(&code);
                     }
                 }
+            },
+            TransactionType::Dividend => {
+                if !self.positions.contains_key(&code) {
+                    return Err("没有该股票的持仓".to_string());
+                }
+                
+                // 增加税后分红现金
+                let net_dividend = transaction.total_value() - transaction.tax.unwrap_or_default();
+                self.record_transaction_cash(CashEntryType::Dividend, net_dividend, &transaction);
+                if let Some(position) = self.positions.get_mut(&code) {
+                    position.add_transaction(transaction);
+                }
+            },
+            TransactionType::BonusShares | TransactionType::Split => {
+                if !self.positions.contains_key(&code) {
+                    return Err("没有该股票的持仓".to_string());
+                }
+                
+                // 扣除送股红利税, 记为税费支出
+                self.record_transaction_cash(CashEntryType::Fee, -transaction.tax.unwrap_or_default(), &transaction);
+                if let Some(position) = self.positions.get_mut(&code) {
+                    position.add_transaction(transaction);
+                }
             }
         }
         
@@ -0,0 +1,135 @@
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::RoundingStrategy;
+use serde::{Deserialize, Deserializer};
+
+pub use rust_decimal::Decimal;
+
+// 金额 (人民币元), 使用十进制定点数避免浮点误差
+pub type Money = Decimal;
+
+// 股数
+pub type Shares = i64;
+
+// 金额精度: 分
+pub const MONEY_SCALE: u32 = 2;
+
+// 按交易所规则四舍五入到分
+pub fn round_money(value: Money) -> Money {
+    value.round_dp_with_strategy(MONEY_SCALE, RoundingStrategy::MidpointAwayFromZero)
+}
+
+// 浮点金额转为十进制并四舍五入到分
+pub fn money(value: f64) -> Money {
+    round_money(decimal(value))
+}
+
+// 浮点数转为十进制, 不做舍入 (用于价格、每股分红等)
+pub fn decimal(value: f64) -> Decimal {
+    // 按最短表示转换, 10.1不会变成10.0999999...
+    Decimal::from_f64(value)
+        .and_then(|d| d.round_sf(15))
+        .unwrap_or_default()
+        .normalize()
+}
+
+// 十进制转为浮点数 (用于估值、绩效等统计计算)
+pub fn to_f64(value: Money) -> f64 {
+    value.to_f64().unwrap_or(0.0)
+}
+
+// 兼容旧数据的金额反序列化: 旧版本的浮点金额四舍五入到分
+pub fn deserialize_money<'de, D>(deserializer: D) -> Result<Money, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    <Money as Deserialize>::deserialize(deserializer).map(round_money)
+}
+
+// 可选金额的反序列化, 规则同deserialize_money
+pub fn deserialize_optional_money<'de, D>(deserializer: D) -> Result<Option<Money>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    <Option<Money> as Deserialize>::deserialize(deserializer).map(|value| value.map(round_money))
+}
+
+// 兼容旧数据的股数反序列化: 接受整数或整数值的浮点数 (如1000.0)
+pub fn deserialize_shares<'de, D>(deserializer: D) -> Result<Shares, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Int(i64),
+        Float(f64),
+    }
+
+    match Raw::deserialize(deserializer)? {
+        Raw::Int(value) => Ok(value),
+        Raw::Float(value) if value.fract() == 0.0 && value.abs() < i64::MAX as f64 => Ok(value as i64),
+        Raw::Float(value) => Err(serde::de::Error::custom(format!("股数必须为整数: {}", value))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Portfolio, Position, Transaction, TransactionType};
+
+    #[test]
+    fn test_rounding_and_legacy_json() {
+        assert_eq!(money(0.1) + money(0.2), money(0.3));
+        assert_eq!(round_money(Decimal::new(12345, 3)), Decimal::new(1235, 2));
+        assert_eq!(decimal(10.1).to_string(), "10.1");
+
+        // 旧版本以浮点数保存股数、价格和金额
+        let transaction = r#"{
+            "id": "t1",
+            "code": "600000",
+            "transaction_type": "Buy",
+            "amount": 1000.0,
+            "price": 10.12,
+            "timestamp": "2024-01-02T01:00:00Z",
+            "fee": 5.000000001,
+            "note": null
+        }"#;
+        let legacy: Transaction = serde_json::from_str(transaction).unwrap();
+        assert!(matches!(legacy.transaction_type, TransactionType::Buy));
+        assert_eq!(legacy.amount, 1000);
+        assert_eq!(legacy.price, Decimal::new(1012, 2));
+        assert_eq!(legacy.fee, Some(Decimal::from(5)));
+        assert_eq!(legacy.tax, None);
+
+        let position = format!(r#"{{
+            "code": "600000",
+            "name": "浦发银行",
+            "amount": 1000.0,
+            "cost": 10.125,
+            "current_price": 10.5,
+            "last_update": "2024-01-02T01:00:00Z",
+            "transactions": [{}]
+        }}"#, transaction);
+        let legacy: Position = serde_json::from_str(&position).unwrap();
+        assert_eq!(legacy.amount, 1000);
+        assert_eq!(legacy.cost, Decimal::new(10125, 3));
+        assert_eq!(legacy.current_price, Some(10.5));
+        assert_eq!(legacy.transactions[0].price, Decimal::new(1012, 2));
+
+        let portfolio = format!(r#"{{
+            "name": "legacy",
+            "positions": {{"600000": {}}},
+            "cash_balance": 10995.000000001,
+            "created_at": "2024-01-01T01:00:00Z",
+            "last_update": "2024-01-02T01:00:00Z"
+        }}"#, position);
+        let legacy: Portfolio = serde_json::from_str(&portfolio).unwrap();
+        assert_eq!(legacy.cash_balance(), Decimal::from(10995));
+        assert_eq!(legacy.positions["600000"].amount, 1000);
+        assert_eq!(legacy.positions["600000"].cost, Decimal::new(10125, 3));
+
+        // 非整数股数不能加载
+        let fractional = transaction.replace("1000.0", "10.5");
+        assert!(serde_json::from_str::<Transaction>(&fractional).is_err());
+    }
+}
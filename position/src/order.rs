@@ -0,0 +1,466 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use crate::market::trade_date;
+use crate::models::{Portfolio, Transaction, TransactionType};
+use crate::risk::format_breaches;
+use crate::fees::FeeSchedule;
+use std::collections::HashMap;
+use crate::money::{decimal, deserialize_shares, round_money, to_f64, Money, Shares};
+
+// 买卖方向
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+impl OrderSide {
+    // 买卖交易对应的方向, 其他交易类型返回None
+    pub fn from_transaction_type(transaction_type: TransactionType) -> Option<Self> {
+        match transaction_type {
+            TransactionType::Buy => Some(OrderSide::Buy),
+            TransactionType::Sell => Some(OrderSide::Sell),
+            _ => None,
+        }
+    }
+
+    pub fn transaction_type(&self) -> TransactionType {
+        match self {
+            OrderSide::Buy => TransactionType::Buy,
+            OrderSide::Sell => TransactionType::Sell,
+        }
+    }
+}
+
+// 订单类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum OrderType {
+    // 市价单
+    Market,
+    // 限价单
+    Limit { price: f64 },
+    // 止损单, 触发后按市价成交
+    Stop { stop_price: f64 },
+    // 止损限价单, 触发后按限价成交
+    StopLimit { stop_price: f64, limit_price: f64 },
+}
+
+impl OrderType {
+    // 限价 (市价单和止损单没有限价)
+    pub fn limit_price(&self) -> Option<f64> {
+        match self {
+            OrderType::Limit { price } => Some(*price),
+            OrderType::StopLimit { limit_price, .. } => Some(*limit_price),
+            _ => None,
+        }
+    }
+
+    // 触发价
+    pub fn stop_price(&self) -> Option<f64> {
+        match self {
+            OrderType::Stop { stop_price } | OrderType::StopLimit { stop_price, .. } => Some(*stop_price),
+            _ => None,
+        }
+    }
+}
+
+// 订单有效期
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum TimeInForce {
+    // 当日有效
+    #[default]
+    Day,
+    // 撤销前有效
+    Gtc,
+}
+
+// 订单状态
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum OrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Rejected,
+    Expired,
+}
+
+impl OrderStatus {
+    // 是否仍可成交
+    pub fn is_active(&self) -> bool {
+        matches!(self, OrderStatus::New | OrderStatus::PartiallyFilled)
+    }
+}
+
+// 成交回报
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fill {
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub quantity: Shares,
+    pub price: Money,
+    pub fee: Money,
+    pub tax: Money,
+    pub timestamp: DateTime<Utc>,
+    // 生成的交易记录ID
+    pub transaction_id: String,
+}
+
+// 订单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Order {
+    // 订单ID
+    pub id: String,
+    // 股票代码
+    pub code: String,
+    // 买卖方向
+    pub side: OrderSide,
+    // 订单类型
+    pub order_type: OrderType,
+    // 有效期
+    #[serde(default)]
+    pub time_in_force: TimeInForce,
+    // 委托数量
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub quantity: Shares,
+    // 参考价格 (最新行情或估算), 市价单按此估算金额做风控检查
+    #[serde(default)]
+    pub reference_price: Option<f64>,
+    // 所属行业, 用于新建仓的行业权重检查
+    #[serde(default)]
+    pub sector: Option<String>,
+    // 已成交数量
+    #[serde(default, deserialize_with = "deserialize_shares")]
+    pub filled_quantity: Shares,
+    // 成交均价
+    #[serde(default)]
+    pub average_price: Option<Money>,
+    // 订单状态
+    pub status: OrderStatus,
+    // 止损单是否已触发
+    #[serde(default)]
+    pub triggered: bool,
+    // 拒绝原因
+    #[serde(default)]
+    pub reason: Option<String>,
+    // 成交明细
+    #[serde(default)]
+    pub fills: Vec<Fill>,
+    // 创建时间
+    pub created_at: DateTime<Utc>,
+    // 最后更新时间
+    pub updated_at: DateTime<Utc>,
+    // 备注
+    pub note: Option<String>,
+}
+
+impl Order {
+    pub fn new(code: String, side: OrderSide, order_type: OrderType, quantity: Shares) -> Self {
+        Self {
+            id: format!("{}", uuid::Uuid::new_v4()),
+            code,
+            side,
+            order_type,
+            time_in_force: TimeInForce::Day,
+            quantity,
+            reference_price: None,
+            sector: None,
+            filled_quantity: 0,
+            average_price: None,
+            status: OrderStatus::New,
+            triggered: false,
+            reason: None,
+            fills: Vec::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            note: None,
+        }
+    }
+
+    // 估算成交价格: 限价、触发价, 否则为参考价格
+    pub fn estimated_price(&self) -> Option<f64> {
+        self.order_type.limit_price()
+            .or(self.order_type.stop_price())
+            .or(self.reference_price.filter(|p| *p > 0.0))
+    }
+
+    // 未成交数量
+    pub fn remaining(&self) -> Shares {
+        self.quantity - self.filled_quantity
+    }
+
+    // 按重建后仍然生效的交易更新成交记录, 已撤销的成交被移除, 修改的成交同步价格和费用
+    pub(crate) fn sync_fills(&mut self, transactions: &HashMap<&str, &Transaction>) {
+        self.fills.retain(|f| transactions.contains_key(f.transaction_id.as_str()));
+        for fill in self.fills.iter_mut() {
+            let transaction = transactions[fill.transaction_id.as_str()];
+            fill.quantity = transaction.amount;
+            fill.price = transaction.price;
+            fill.fee = transaction.fee.unwrap_or_default();
+            fill.tax = transaction.tax.unwrap_or_default();
+            fill.timestamp = transaction.timestamp;
+        }
+
+        self.filled_quantity = self.fills.iter().map(|f| f.quantity).sum();
+        self.average_price = if self.filled_quantity > 0 {
+            let value: Money = self.fills.iter().map(|f| f.price * Money::from(f.quantity)).sum();
+            Some(value / Money::from(self.filled_quantity))
+        } else {
+            None
+        };
+        // 已撤单、拒绝或过期的订单保持原状态
+        if self.status.is_active() || self.status == OrderStatus::Filled {
+            self.status = if self.filled_quantity == 0 {
+                OrderStatus::New
+            } else if self.remaining() <= 0 {
+                OrderStatus::Filled
+            } else {
+                OrderStatus::PartiallyFilled
+            };
+        }
+    }
+
+    pub fn reject(&mut self, reason: String) {
+        self.status = OrderStatus::Rejected;
+        self.reason = Some(reason);
+        self.updated_at = Utc::now();
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.quantity <= 0 {
+            return Err("委托数量必须大于0".to_string());
+        }
+        let prices = [self.order_type.limit_price(), self.order_type.stop_price()];
+        if prices.iter().flatten().any(|p| *p <= 0.0) {
+            return Err("委托价格必须大于0".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Portfolio {
+    // 进行中的订单
+    pub fn open_orders(&self) -> Vec<&Order> {
+        self.orders.iter().filter(|o| o.status.is_active()).collect()
+    }
+
+    // 估算订单费用使用的费率 (模拟交易设置的费率, 否则为默认费率)
+    pub fn fee_schedule(&self) -> FeeSchedule {
+        self.paper.as_ref().map(|p| p.fees.clone()).unwrap_or_default()
+    }
+
+    // 买入金额加估算的费用和税
+    fn buy_cost(&self, price: f64, quantity: Shares) -> Money {
+        let value = round_money(decimal(price) * Money::from(quantity));
+        let (fee, tax) = self.fee_schedule().costs(OrderSide::Buy, value);
+        value + fee + tax
+    }
+
+    // 挂单占用的现金: 按限价、触发价或参考价格估算, 含估算费用
+    pub fn reserved_cash(&self) -> Money {
+        self.open_orders().iter()
+            .filter(|o| o.side == OrderSide::Buy)
+            .filter_map(|o| o.estimated_price().map(|p| self.buy_cost(p, o.remaining())))
+            .sum()
+    }
+
+    // 挂单占用的持仓数量
+    pub fn reserved_amount(&self, code: &str) -> Shares {
+        self.open_orders().iter()
+            .filter(|o| o.side == OrderSide::Sell && o.code == code)
+            .map(|o| o.remaining())
+            .sum()
+    }
+
+    // 下单, 校验或风控检查失败的订单以拒绝状态保存
+    pub fn place_order(&mut self, mut order: Order) -> Order {
+        // 估算成交价格: 限价或触发价, 市价单使用参考价格, 已持仓的按现价或成本
+        let price = order.estimated_price()
+            .or_else(|| self.positions.get(&order.code).and_then(|p| p.current_price.or(Some(to_f64(p.cost)))));
+        // 市价单记录估算价格, 挂单期间按此占用现金
+        if order.estimated_price().is_none() {
+            order.reference_price = price;
+        }
+
+        let check = order.validate().and_then(|_| match order.side {
+            OrderSide::Buy => {
+                match price {
+                    Some(price) if self.buy_cost(price, order.quantity) > self.available_cash() - self.reserved_cash() => {
+                        Err("可用现金不足".to_string())
+                    },
+                    _ => Ok(()),
+                }
+            },
+            OrderSide::Sell => {
+                let held = self.positions.get(&order.code).map(|p| p.amount).unwrap_or(0);
+                if order.quantity > held - self.reserved_amount(&order.code) {
+                    Err("可用持仓不足".to_string())
+                } else {
+                    Ok(())
+                }
+            },
+        });
+
+        // 没有价格无法估算金额, 风控检查会失效
+        let check = check.and_then(|_| {
+            let price = price.ok_or_else(|| "市价单缺少参考价格, 无法进行风控检查".to_string())?;
+            let breaches = self.check_risk(&order.code, order.side, order.quantity, price, order.sector.as_deref());
+            if breaches.is_empty() {
+                Ok(())
+            } else {
+                Err(format_breaches(&breaches))
+            }
+        });
+
+        if let Err(reason) = check {
+            order.reject(reason);
+        }
+
+        self.orders.push(order.clone());
+        self.last_update = Utc::now();
+        order
+    }
+
+    // 撤单
+    pub fn cancel_order(&mut self, order_id: &str) -> Result<Order, String> {
+        let order = self.orders.iter_mut()
+            .find(|o| o.id == order_id)
+            .ok_or_else(|| "订单不存在".to_string())?;
+        if !order.status.is_active() {
+            return Err(format!("订单状态为{:?}, 无法撤销", order.status));
+        }
+
+        order.status = OrderStatus::Cancelled;
+        order.updated_at = Utc::now();
+        Ok(order.clone())
+    }
+
+    // 订单成交, 生成交易记录并更新订单状态
+    pub fn fill_order(
+        &mut self,
+        order_id: &str,
+        quantity: Shares,
+        price: Money,
+        fee: Money,
+        tax: Money,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Transaction, String> {
+        let order = self.orders.iter()
+            .find(|o| o.id == order_id)
+            .ok_or_else(|| "订单不存在".to_string())?;
+        if !order.status.is_active() {
+            return Err(format!("订单状态为{:?}, 无法成交", order.status));
+        }
+        if quantity <= 0 || quantity > order.remaining() {
+            return Err("成交数量超出未成交数量".to_string());
+        }
+        if let Some(limit) = order.order_type.limit_price().map(decimal) {
+            match order.side {
+                OrderSide::Buy if price > limit => return Err(format!("成交价格{}高于限价{}", price, limit)),
+                OrderSide::Sell if price < limit => return Err(format!("成交价格{}低于限价{}", price, limit)),
+                _ => {},
+            }
+        }
+
+        let mut transaction = Transaction::new(order.code.clone(), order.side.transaction_type(), quantity, price);
+        transaction.timestamp = timestamp;
+        transaction.fee = Some(fee);
+        transaction.tax = Some(tax);
+        transaction.note = Some(format!("订单成交 {}", order.id));
+        self.record_transaction(transaction.clone())?;
+
+        let order = self.orders.iter_mut().find(|o| o.id == order_id).unwrap();
+        let filled_value = order.average_price.unwrap_or_default() * Money::from(order.filled_quantity) + price * Money::from(quantity);
+        order.filled_quantity += quantity;
+        order.average_price = Some(filled_value / Money::from(order.filled_quantity));
+        order.status = if order.remaining() == 0 {
+            OrderStatus::Filled
+        } else {
+            OrderStatus::PartiallyFilled
+        };
+        order.fills.push(Fill {
+            quantity,
+            price,
+            fee,
+            tax,
+            timestamp,
+            transaction_id: transaction.id.clone(),
+        });
+        order.updated_at = Utc::now();
+
+        Ok(transaction)
+    }
+
+    // 当日有效订单在交易日结束后过期, 返回过期的订单数量
+    pub fn expire_orders(&mut self, at: DateTime<Utc>) -> usize {
+        let today = trade_date(at);
+        let mut expired = 0;
+
+        for order in self.orders.iter_mut() {
+            if order.status.is_active()
+                && order.time_in_force == TimeInForce::Day
+                && trade_date(order.created_at) < today
+            {
+                order.status = OrderStatus::Expired;
+                order.updated_at = at;
+                expired += 1;
+            }
+        }
+
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::money;
+
+    #[test]
+    fn test_order_lifecycle() {
+        let mut portfolio = Portfolio::new("test".to_string(), Money::from(10000));
+
+        let rejected = portfolio.place_order(Order::new("600000".to_string(), OrderSide::Buy, OrderType::Limit { price: 10.0 }, 2000));
+        assert_eq!(rejected.status, OrderStatus::Rejected);
+
+        let order = portfolio.place_order(Order::new("600000".to_string(), OrderSide::Buy, OrderType::Limit { price: 10.0 }, 500));
+        assert_eq!(order.status, OrderStatus::New);
+        // 占用现金含佣金5元和过户费0.05元
+        assert_eq!(portfolio.reserved_cash(), money(5005.05));
+
+        // 买入成交价格不能高于限价
+        assert!(portfolio.fill_order(&order.id, 200, money(10.01), Money::from(5), Money::ZERO, Utc::now()).is_err());
+        portfolio.fill_order(&order.id, 200, money(9.9), Money::from(5), Money::ZERO, Utc::now()).unwrap();
+        assert_eq!(portfolio.orders[1].status, OrderStatus::PartiallyFilled);
+        assert!(portfolio.fill_order(&order.id, 400, money(9.9), Money::from(5), Money::ZERO, Utc::now()).is_err());
+
+        portfolio.fill_order(&order.id, 300, Money::from(10), Money::from(5), Money::ZERO, Utc::now()).unwrap();
+        let filled = &portfolio.orders[1];
+        assert_eq!(filled.status, OrderStatus::Filled);
+        assert_eq!(filled.average_price, Some(money(9.96)));
+        assert_eq!(portfolio.positions["600000"].amount, 500);
+        assert!(portfolio.cancel_order(&order.id).is_err());
+
+        // 卖单不能超过未被占用的持仓
+        let sell = portfolio.place_order(Order::new("600000".to_string(), OrderSide::Sell, OrderType::Market, 400));
+        assert_eq!(sell.status, OrderStatus::New);
+        let sell_more = portfolio.place_order(Order::new("600000".to_string(), OrderSide::Sell, OrderType::Market, 200));
+        assert_eq!(sell_more.status, OrderStatus::Rejected);
+
+        assert_eq!(portfolio.cancel_order(&sell.id).unwrap().status, OrderStatus::Cancelled);
+        assert_eq!(portfolio.expire_orders(Utc::now() + chrono::Duration::days(1)), 0);
+
+        // 市价买单按参考价格占用现金, 合计超过可用现金的第二笔被拒绝
+        let cash = portfolio.available_cash();
+        let mut first = Order::new("600519".to_string(), OrderSide::Buy, OrderType::Market, 400);
+        first.reference_price = Some(10.0);
+        assert_eq!(portfolio.place_order(first).status, OrderStatus::New);
+        assert!(portfolio.reserved_cash() > Money::from(4000));
+        let mut second = Order::new("600519".to_string(), OrderSide::Buy, OrderType::Market, 200);
+        second.reference_price = Some(10.0);
+        // 单独一笔可以买入, 与第一笔合计超过可用现金
+        assert!(Money::from(2100) < cash && Money::from(6000) > cash);
+        let second = portfolio.place_order(second);
+        assert_eq!(second.status, OrderStatus::Rejected);
+        assert_eq!(second.reason.as_deref(), Some("可用现金不足"));
+    }
+}
@@ -0,0 +1,309 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use crate::fees::FeeSchedule;
+use crate::market::{price_limit, round_lot};
+use crate::models::Portfolio;
+use crate::order::{OrderSide, OrderStatus};
+use crate::valuation::Quote;
+use crate::money::{deserialize_shares, money, Money, Shares};
+
+// 模拟交易设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperConfig {
+    // 滑点 (基点)
+    pub slippage_bps: f64,
+    // 每次行情更新可成交的成交量比例
+    pub participation_rate: f64,
+    // 交易费用
+    #[serde(default)]
+    pub fees: FeeSchedule,
+}
+
+impl Default for PaperConfig {
+    fn default() -> Self {
+        Self {
+            slippage_bps: 5.0,
+            participation_rate: 0.1,
+            fees: FeeSchedule::default(),
+        }
+    }
+}
+
+// 模拟撮合回报
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionReport {
+    pub portfolio: String,
+    pub order_id: String,
+    pub code: String,
+    pub side: OrderSide,
+    pub status: OrderStatus,
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub quantity: Shares,
+    pub price: Money,
+    pub reason: Option<String>,
+}
+
+// 模拟交易所, 用每次行情更新撮合模拟账户的挂单
+#[derive(Debug, Clone, Default)]
+pub struct PaperExchange {
+    // 上次行情的累计成交量, 用于计算本次更新的新增成交量
+    last_volume: HashMap<String, f64>,
+}
+
+impl PaperExchange {
+    pub fn new() -> Self {
+        Self { last_volume: HashMap::new() }
+    }
+
+    // 计算本次行情更新各股票的新增成交量
+    fn volume_deltas(&mut self, quotes: &HashMap<String, Quote>) -> HashMap<String, f64> {
+        let mut deltas = HashMap::new();
+        for quote in quotes.values() {
+            if let Some(volume) = quote.volume {
+                // 累计成交量变小说明已进入新的交易日
+                let delta = match self.last_volume.get(&quote.code) {
+                    Some(last) if volume >= *last => volume - last,
+                    _ => volume,
+                };
+                self.last_volume.insert(quote.code.clone(), volume);
+                deltas.insert(quote.code.clone(), delta);
+            }
+        }
+        deltas
+    }
+
+    // 用最新行情撮合所有模拟账户的挂单
+    pub fn match_orders(
+        &mut self,
+        portfolios: &mut [Portfolio],
+        quotes: &HashMap<String, Quote>,
+        at: DateTime<Utc>,
+    ) -> Vec<ExecutionReport> {
+        let deltas = self.volume_deltas(quotes);
+        // 各账户共享同一份新增成交量, 记录已被撮合占用的股数
+        let mut consumed: HashMap<String, f64> = HashMap::new();
+        let mut reports = Vec::new();
+
+        for portfolio in portfolios.iter_mut() {
+            let config = match &portfolio.paper {
+                Some(config) => config.clone(),
+                None => continue,
+            };
+
+            let order_ids: Vec<String> = portfolio.open_orders().iter().map(|o| o.id.clone()).collect();
+            for order_id in order_ids {
+                let order = portfolio.orders.iter().find(|o| o.id == order_id).unwrap().clone();
+                let quote = match quotes.get(&order.code) {
+                    Some(quote) if quote.price > 0.0 => quote,
+                    _ => continue,
+                };
+                let price = quote.price;
+
+                // 止损单在价格穿越触发价后生效
+                if let Some(stop_price) = order.order_type.stop_price() {
+                    if !order.triggered {
+                        let hit = match order.side {
+                            OrderSide::Buy => price >= stop_price,
+                            OrderSide::Sell => price <= stop_price,
+                        };
+                        if !hit {
+                            continue;
+                        }
+                        if let Some(o) = portfolio.orders.iter_mut().find(|o| o.id == order_id) {
+                            o.triggered = true;
+                            o.updated_at = at;
+                        }
+                    }
+                }
+
+                let limit = order.order_type.limit_price();
+                let marketable = match (order.side, limit) {
+                    (OrderSide::Buy, Some(limit)) => price <= limit,
+                    (OrderSide::Sell, Some(limit)) => price >= limit,
+                    (_, None) => true,
+                };
+                if !marketable {
+                    continue;
+                }
+
+                // 涨停无法买入, 跌停无法卖出
+                let band = quote.previous_close().map(|prev_close| {
+                    let pct = price_limit(&order.code, quote.name.as_deref());
+                    (round_price(prev_close * (1.0 - pct)), round_price(prev_close * (1.0 + pct)))
+                });
+                if let Some((lower, upper)) = band {
+                    let reason = match order.side {
+                        OrderSide::Buy if price >= upper => Some("涨停无法买入"),
+                        OrderSide::Sell if price <= lower => Some("跌停无法卖出"),
+                        _ => None,
+                    };
+                    if let Some(reason) = reason {
+                        reports.push(reject(portfolio, &order_id, reason.to_string()));
+                        continue;
+                    }
+                }
+
+                // 按滑点调整成交价, 不超过限价和涨跌停价
+                let slippage = config.slippage_bps / 10000.0;
+                let mut fill_price = match order.side {
+                    OrderSide::Buy => price * (1.0 + slippage),
+                    OrderSide::Sell => price * (1.0 - slippage),
+                };
+                if let Some(limit) = limit {
+                    fill_price = match order.side {
+                        OrderSide::Buy => fill_price.min(limit),
+                        OrderSide::Sell => fill_price.max(limit),
+                    };
+                }
+                if let Some((lower, upper)) = band {
+                    fill_price = fill_price.clamp(lower, upper);
+                }
+                let fill_price = money(fill_price);
+
+                // 按本账户的成交量比例扣除已占用股数后部分成交, 部分成交按整手
+                let remaining = order.remaining();
+                let used = consumed.get(&order.code).copied().unwrap_or(0.0);
+                let capacity = deltas.get(&order.code).map(|v| v * config.participation_rate - used);
+                let mut quantity = capacity.map(|c| (c.max(0.0) as Shares).min(remaining)).unwrap_or(remaining);
+                if quantity < remaining {
+                    quantity = round_lot(quantity as f64);
+                }
+                if quantity <= 0 {
+                    continue;
+                }
+
+                let value = fill_price * Money::from(quantity);
+                let (fee, tax) = config.fees.costs(order.side, value);
+                if order.side == OrderSide::Buy && value + fee + tax > portfolio.available_cash() {
+                    reports.push(reject(portfolio, &order_id, "可用现金不足".to_string()));
+                    continue;
+                }
+
+                match portfolio.fill_order(&order_id, quantity, fill_price, fee, tax, at) {
+                    Ok(_) => {
+                        *consumed.entry(order.code.clone()).or_insert(0.0) += quantity as f64;
+                        let status = portfolio.orders.iter().find(|o| o.id == order_id).unwrap().status;
+                        reports.push(ExecutionReport {
+                            portfolio: portfolio.name.clone(),
+                            order_id: order_id.clone(),
+                            code: order.code.clone(),
+                            side: order.side,
+                            status,
+                            quantity,
+                            price: fill_price,
+                            reason: None,
+                        });
+                    },
+                    Err(e) => reports.push(reject(portfolio, &order_id, e)),
+                }
+            }
+        }
+
+        reports
+    }
+}
+
+// 价格保留两位小数
+fn round_price(price: f64) -> f64 {
+    (price * 100.0).round() / 100.0
+}
+
+fn reject(portfolio: &mut Portfolio, order_id: &str, reason: String) -> ExecutionReport {
+    let order = portfolio.orders.iter_mut().find(|o| o.id == order_id).unwrap();
+    order.reject(reason.clone());
+    ExecutionReport {
+        portfolio: portfolio.name.clone(),
+        order_id: order.id.clone(),
+        code: order.code.clone(),
+        side: order.side,
+        status: order.status,
+        quantity: 0,
+        price: Money::ZERO,
+        reason: Some(reason),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order::{Order, OrderType};
+
+    fn quote(price: f64, change_percent: f64, volume: f64) -> HashMap<String, Quote> {
+        let mut quotes = HashMap::new();
+        quotes.insert("600000".to_string(), Quote {
+            code: "600000".to_string(),
+            name: None,
+            price,
+            change_percent: Some(change_percent),
+            volume: Some(volume),
+            sector: None,
+            market_cap: None,
+        });
+        quotes
+    }
+
+    #[test]
+    fn test_paper_matching() {
+        let mut portfolio = Portfolio::new("paper".to_string(), Money::from(100000));
+        portfolio.paper = Some(PaperConfig { slippage_bps: 0.0, ..Default::default() });
+        let limit = portfolio.place_order(Order::new("600000".to_string(), OrderSide::Buy, OrderType::Limit { price: 10.0 }, 1000));
+        let mut portfolios = vec![portfolio];
+        let mut exchange = PaperExchange::new();
+
+        // 价格高于限价不成交
+        let reports = exchange.match_orders(&mut portfolios, &quote(10.2, 2.0, 1000.0), Utc::now());
+        assert!(reports.is_empty());
+
+        // 新增成交量5000, 按10%只能成交500股
+        let reports = exchange.match_orders(&mut portfolios, &quote(9.9, -1.0, 6000.0), Utc::now());
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].quantity, 500);
+        assert_eq!(reports[0].status, OrderStatus::PartiallyFilled);
+
+        let reports = exchange.match_orders(&mut portfolios, &quote(9.9, -1.0, 100000.0), Utc::now());
+        assert_eq!(reports[0].status, OrderStatus::Filled);
+        assert_eq!(portfolios[0].positions["600000"].amount, 1000);
+        assert_eq!(portfolios[0].orders[0].id, limit.id);
+
+        // 跌停时卖单被拒绝
+        portfolios[0].place_order(Order::new("600000".to_string(), OrderSide::Sell, OrderType::Stop { stop_price: 9.5 }, 500));
+        let reports = exchange.match_orders(&mut portfolios, &quote(9.0, -10.0, 200000.0), Utc::now());
+        assert_eq!(reports[0].status, OrderStatus::Rejected);
+        assert_eq!(portfolios[0].positions["600000"].amount, 1000);
+    }
+
+    #[test]
+    fn test_participation_per_portfolio() {
+        let mut low = Portfolio::new("low".to_string(), Money::from(100000));
+        low.paper = Some(PaperConfig { slippage_bps: 0.0, participation_rate: 0.1, ..Default::default() });
+        let mut order = Order::new("600000".to_string(), OrderSide::Buy, OrderType::Market, 1000);
+        order.reference_price = Some(10.0);
+        low.place_order(order);
+        let mut high = Portfolio::new("high".to_string(), Money::from(100000));
+        high.paper = Some(PaperConfig { slippage_bps: 0.0, participation_rate: 0.5, ..Default::default() });
+        let mut order = Order::new("600000".to_string(), OrderSide::Buy, OrderType::Market, 5000);
+        order.reference_price = Some(10.0);
+        high.place_order(order);
+        let mut portfolios = vec![low, high];
+        let mut exchange = PaperExchange::new();
+
+        // 新增成交量5000: 第一个账户按10%成交500股, 第二个账户按50%扣除已占用的500股后成交2000股
+        let reports = exchange.match_orders(&mut portfolios, &quote(10.0, 0.0, 5000.0), Utc::now());
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].quantity, 500);
+        assert_eq!(reports[1].quantity, 2000);
+
+        // 部分成交的卖单按整手成交: 新增成交量2550按50%可成交1275股, 只成交1200股
+        for portfolio in portfolios.iter_mut() {
+            let id = portfolio.orders[0].id.clone();
+            portfolio.cancel_order(&id).unwrap();
+        }
+        portfolios[1].place_order(Order::new("600000".to_string(), OrderSide::Sell, OrderType::Market, 1500));
+        let reports = exchange.match_orders(&mut portfolios, &quote(10.0, 0.0, 7550.0), Utc::now());
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].side, OrderSide::Sell);
+        assert_eq!(reports[0].quantity, 1200);
+        assert_eq!(portfolios[1].positions["600000"].amount, 800);
+    }
+}
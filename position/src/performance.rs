@@ -0,0 +1,375 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::{BTreeMap, HashMap};
+use crate::lots::open_lots;
+use crate::market::{day_start, trade_date};
+use log::warn;
+use crate::models::{Portfolio, Transaction};
+use crate::money::to_f64;
+
+// 每年交易日数量, 用于年化
+pub const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+
+// 按日期排列的价格序列
+pub type PriceSeries = BTreeMap<NaiveDate, f64>;
+
+// 每日净值记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NavPoint {
+    // 交易日期
+    pub date: NaiveDate,
+    // 组合总资产 (现金加持仓市值)
+    pub nav: f64,
+    // 当日外部资金净流入 (入金减出金)
+    pub net_flow: f64,
+    // 记录时间
+    pub timestamp: DateTime<Utc>,
+}
+
+// 组合绩效报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerformanceReport {
+    pub name: String,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub days: usize,
+    pub start_nav: f64,
+    pub end_nav: f64,
+    // 区间内外部资金净流入
+    pub net_flows: f64,
+    // 时间加权收益率
+    pub time_weighted_return: f64,
+    // 年化时间加权收益率
+    pub annualized_return: f64,
+    // 资金加权收益率 (年化内部收益率)
+    pub money_weighted_return: Option<f64>,
+    // 年化波动率
+    pub volatility: f64,
+    // 最大回撤
+    pub max_drawdown: f64,
+    // 夏普比率
+    pub sharpe_ratio: f64,
+    // 基准代码
+    pub benchmark: Option<String>,
+    // 基准区间收益率
+    pub benchmark_return: Option<f64>,
+    // 超额收益率
+    pub excess_return: Option<f64>,
+}
+
+impl Portfolio {
+    // 记录当日净值, 同一交易日只保留最后一次记录
+    // 存在未估值的持仓时不记录, 返回是否记录成功
+    pub fn record_nav(&mut self, at: DateTime<Utc>) -> bool {
+        if self.positions.values().any(|p| p.current_price.is_none()) {
+            return false;
+        }
+
+        let date = trade_date(at);
+        let start = day_start(date) - chrono::Duration::nanoseconds(1);
+        let point = NavPoint {
+            date,
+            nav: self.total_market_value(),
+            net_flow: to_f64(self.cash_ledger.external_flows_between(start, at)),
+            timestamp: at,
+        };
+
+        match self.nav_history.iter_mut().find(|p| p.date == date) {
+            Some(existing) => *existing = point,
+            None => {
+                self.nav_history.push(point);
+                self.nav_history.sort_by_key(|p| p.date);
+            }
+        }
+
+        true
+    }
+
+    // 用历史行情快照补齐缺失日期的净值, 返回补齐的天数
+    // 按日期重放全部交易记录和现金账本, 包括之后已清仓的股票
+    pub fn backfill_nav(&mut self, snapshots: &[(DateTime<Utc>, HashMap<String, f64>)]) -> usize {
+        let history = match self.transaction_history() {
+            Ok(history) => history,
+            Err(e) => {
+                warn!("组合{}重放交易记录失败, 只使用当前持仓的交易: {}", self.name, e);
+                self.positions.values().flat_map(|p| p.transactions.clone()).collect()
+            },
+        };
+        let mut by_code: HashMap<&str, Vec<Transaction>> = HashMap::new();
+        for transaction in &history {
+            by_code.entry(transaction.code.as_str()).or_default().push(transaction.clone());
+        }
+
+        let mut filled = 0;
+        for (timestamp, prices) in snapshots {
+            let date = trade_date(*timestamp);
+            if *timestamp < self.created_at || self.nav_history.iter().any(|p| p.date == date) {
+                continue;
+            }
+
+            // 按快照时间重建持仓, 缺少行情时按持仓成本估值
+            let mut holdings_value = 0.0;
+            for (code, transactions) in &by_code {
+                let before: Vec<Transaction> = transactions.iter()
+                    .filter(|t| t.timestamp <= *timestamp)
+                    .cloned()
+                    .collect();
+                for lot in open_lots(&before) {
+                    let price = prices.get(*code).copied().unwrap_or_else(|| to_f64(lot.cost));
+                    holdings_value += lot.amount as f64 * price;
+                }
+            }
+
+            let start = day_start(date) - chrono::Duration::nanoseconds(1);
+            self.nav_history.push(NavPoint {
+                date,
+                nav: to_f64(self.cash_ledger.balance_as_of(*timestamp)) + holdings_value,
+                net_flow: to_f64(self.cash_ledger.external_flows_between(start, *timestamp)),
+                timestamp: *timestamp,
+            });
+            filled += 1;
+        }
+
+        self.nav_history.sort_by_key(|p| p.date);
+        filled
+    }
+
+    // 计算指定区间的绩效
+    pub fn performance(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        benchmark: Option<(&str, &PriceSeries)>,
+        risk_free_rate: f64,
+    ) -> Option<PerformanceReport> {
+        let points: Vec<NavPoint> = self.nav_history.iter()
+            .filter(|p| from.is_none_or(|d| p.date >= d) && to.is_none_or(|d| p.date <= d))
+            .cloned()
+            .collect();
+        if points.is_empty() {
+            return None;
+        }
+
+        let first = &points[0];
+        let last = &points[points.len() - 1];
+        let returns = daily_returns(&points);
+        let twr = compound_return(&returns);
+
+        // 以时间加权收益构造净值指数, 用于计算回撤
+        let mut index = vec![1.0];
+        for r in &returns {
+            index.push(index[index.len() - 1] * (1.0 + r));
+        }
+
+        let benchmark_return = benchmark.and_then(|(_, series)| series_return(series, first.date, last.date));
+
+        Some(PerformanceReport {
+            name: self.name.clone(),
+            from: Some(first.date),
+            to: Some(last.date),
+            days: points.len(),
+            start_nav: first.nav,
+            end_nav: last.nav,
+            net_flows: points.iter().skip(1).map(|p| p.net_flow).sum(),
+            time_weighted_return: twr,
+            annualized_return: annualize_return(twr, returns.len()),
+            money_weighted_return: money_weighted_return(&points),
+            volatility: annualized_volatility(&returns),
+            max_drawdown: max_drawdown(&index),
+            sharpe_ratio: sharpe_ratio(&returns, risk_free_rate),
+            benchmark: benchmark.map(|(code, _)| code.to_string()),
+            benchmark_return,
+            excess_return: benchmark_return.map(|b| twr - b),
+        })
+    }
+}
+
+// 剔除外部资金流动后的每日收益率 (假设资金在当日开始时流入)
+pub fn daily_returns(points: &[NavPoint]) -> Vec<f64> {
+    points.windows(2)
+        .filter_map(|w| {
+            let base = w[0].nav + w[1].net_flow;
+            if base <= 0.0 {
+                None
+            } else {
+                Some(w[1].nav / base - 1.0)
+            }
+        })
+        .collect()
+}
+
+// 复合收益率
+pub fn compound_return(returns: &[f64]) -> f64 {
+    returns.iter().fold(1.0, |acc, r| acc * (1.0 + r)) - 1.0
+}
+
+// 按交易日年化收益率
+pub fn annualize_return(total_return: f64, periods: usize) -> f64 {
+    if periods == 0 || total_return <= -1.0 {
+        return 0.0;
+    }
+    (1.0 + total_return).powf(TRADING_DAYS_PER_YEAR / periods as f64) - 1.0
+}
+
+// 样本标准差
+pub fn std_dev(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    variance.sqrt()
+}
+
+// 年化波动率
+pub fn annualized_volatility(returns: &[f64]) -> f64 {
+    std_dev(returns) * TRADING_DAYS_PER_YEAR.sqrt()
+}
+
+// 最大回撤 (以正数表示的比例)
+pub fn max_drawdown(values: &[f64]) -> f64 {
+    let mut peak = f64::MIN;
+    let mut max_drawdown = 0.0;
+    for value in values {
+        if *value > peak {
+            peak = *value;
+        }
+        if peak > 0.0 {
+            let drawdown = (peak - value) / peak;
+            if drawdown > max_drawdown {
+                max_drawdown = drawdown;
+            }
+        }
+    }
+    max_drawdown
+}
+
+// 年化夏普比率, risk_free_rate为年化无风险利率
+pub fn sharpe_ratio(returns: &[f64], risk_free_rate: f64) -> f64 {
+    let std = std_dev(returns);
+    if std == 0.0 {
+        return 0.0;
+    }
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    (mean - risk_free_rate / TRADING_DAYS_PER_YEAR) / std * TRADING_DAYS_PER_YEAR.sqrt()
+}
+
+// 价格序列在区间内的收益率
+pub fn series_return(series: &PriceSeries, from: NaiveDate, to: NaiveDate) -> Option<f64> {
+    let start = series.range(from..=to).next()?.1;
+    let end = series.range(from..=to).next_back()?.1;
+    if *start <= 0.0 {
+        return None;
+    }
+    Some(end / start - 1.0)
+}
+
+// 资金加权收益率: 以期初净值、期间外部资金和期末净值求年化内部收益率
+pub fn money_weighted_return(points: &[NavPoint]) -> Option<f64> {
+    if points.len() < 2 {
+        return None;
+    }
+
+    let start = points[0].date;
+    let last = &points[points.len() - 1];
+    if last.date <= start {
+        return None;
+    }
+    let mut flows: Vec<(f64, f64)> = vec![(0.0, -points[0].nav)];
+    for point in points.iter().skip(1) {
+        if point.net_flow != 0.0 {
+            flows.push(((point.date - start).num_days() as f64 / 365.0, -point.net_flow));
+        }
+    }
+    flows.push(((last.date - start).num_days() as f64 / 365.0, last.nav));
+
+    let npv = |rate: f64| -> f64 {
+        flows.iter().map(|(t, cf)| cf / (1.0 + rate).powf(*t)).sum()
+    };
+
+    // 二分法求解
+    let (mut low, mut high) = (-0.99, 10.0);
+    if npv(low) * npv(high) > 0.0 {
+        return None;
+    }
+    for _ in 0..200 {
+        let mid = (low + high) / 2.0;
+        if npv(low) * npv(mid) <= 0.0 {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+
+    Some((low + high) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::models::TransactionType;
+    use crate::money::Money;
+
+    fn point(day: u32, nav: f64, net_flow: f64) -> NavPoint {
+        NavPoint {
+            date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+            nav,
+            net_flow,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_time_weighted_return_excludes_flows() {
+        // 第2天收益10%, 第3天入金1100后收益0
+        let points = vec![point(1, 1000.0, 0.0), point(2, 1100.0, 0.0), point(3, 2200.0, 1100.0)];
+        let returns = daily_returns(&points);
+        assert_eq!(returns.len(), 2);
+        assert!((returns[0] - 0.1).abs() < 1e-12);
+        assert!(returns[1].abs() < 1e-12);
+        assert!((compound_return(&returns) - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_max_drawdown() {
+        let values = vec![100.0, 120.0, 90.0, 130.0, 104.0];
+        assert!((max_drawdown(&values) - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_money_weighted_return_without_flows() {
+        let points = vec![point(1, 1000.0, 0.0), point(1, 1000.0, 0.0)];
+        assert!(money_weighted_return(&points).is_none());
+
+        let mut end = point(1, 1100.0, 0.0);
+        end.date = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+        let points = vec![point(1, 1000.0, 0.0), end];
+        let mwr = money_weighted_return(&points).unwrap();
+        // 365天收益10%
+        assert!((mwr - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_backfill_nav_includes_closed_positions() {
+        let at = |day: u32| Utc.with_ymd_and_hms(2024, 1, day, 7, 0, 0).unwrap();
+        let mut portfolio = Portfolio::new("test".to_string(), Money::from(100000));
+        portfolio.created_at = at(1);
+        portfolio.cash_ledger.entries[0].timestamp = at(1);
+        let mut buy = Transaction::new("600000".to_string(), TransactionType::Buy, 1000, Money::from(10));
+        buy.timestamp = at(2);
+        portfolio.add_transaction(buy).unwrap();
+        let mut sell = Transaction::new("600000".to_string(), TransactionType::Sell, 1000, Money::from(12));
+        sell.timestamp = at(4);
+        portfolio.add_transaction(sell).unwrap();
+        assert!(!portfolio.positions.contains_key("600000"));
+
+        let snapshots: Vec<(DateTime<Utc>, HashMap<String, f64>)> = [(3, 11.0), (5, 13.0)].iter()
+            .map(|(day, price)| (at(*day), HashMap::from([("600000".to_string(), *price)])))
+            .collect();
+        assert_eq!(portfolio.backfill_nav(&snapshots), 2);
+        // 清仓前按行情估值, 清仓后只有现金
+        assert!((portfolio.nav_history[0].nav - 101000.0).abs() < 1e-9);
+        assert!((portfolio.nav_history[1].nav - 102000.0).abs() < 1e-9);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::models::{Portfolio, Transaction, TransactionType};
+use crate::money::{decimal, Shares};
 use common::constants::BASE_URL;
 
 pub struct PositionManager<'a> {
@@ -10,7 +11,7 @@ impl<'a> PositionManager<'a> {
         Self { portfolio }
     }
 
-    pub async fn new_transaction(&mut self, code: String, transaction_type: TransactionType, amount: f64) 
+    pub async fn new_transaction(&mut self, code: String, transaction_type: TransactionType, amount: Shares) 
         -> Result<Transaction, Box<dyn std::error::Error>> {
         let url = format!("{}/stockdata/price?code={}", BASE_URL, code);
         let response = reqwest::get(url).await?;
@@ -32,7 +33,7 @@ impl<'a> PositionManager<'a> {
             code,
             transaction_type,
             amount,
-            price,
+            decimal(price),
         );
         Ok(transaction)
     }
@@ -0,0 +1,306 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeSet, HashMap};
+use crate::fees::FeeSchedule;
+use crate::market::{round_lot, trade_date, LOT_SIZE};
+use crate::models::{Portfolio, Transaction, TransactionType};
+use crate::order::{Order, OrderSide, OrderStatus, OrderType};
+use crate::money::{decimal, deserialize_shares, to_f64, Money, Shares};
+
+// 调仓设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebalanceConfig {
+    // 保留现金比例
+    pub cash_reserve: f64,
+    // 最小交易金额, 低于该金额的调整忽略
+    pub min_trade_value: Money,
+    // 交易费用
+    #[serde(default)]
+    pub fees: FeeSchedule,
+}
+
+impl Default for RebalanceConfig {
+    fn default() -> Self {
+        Self {
+            cash_reserve: 0.0,
+            min_trade_value: Money::from(1000),
+            fees: FeeSchedule::default(),
+        }
+    }
+}
+
+// 计划交易
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedTrade {
+    pub code: String,
+    pub side: OrderSide,
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub quantity: Shares,
+    pub price: Money,
+    pub value: Money,
+    pub fee: Money,
+    pub tax: Money,
+    pub current_weight: f64,
+    pub target_weight: f64,
+}
+
+// 调仓计划
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebalancePlan {
+    pub portfolio: String,
+    pub equity: Money,
+    pub cash_before: Money,
+    // 执行全部交易后的预计现金
+    pub cash_after: Money,
+    pub trades: Vec<PlannedTrade>,
+    pub warnings: Vec<String>,
+}
+
+// 调仓执行结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebalanceExecution {
+    pub code: String,
+    pub side: OrderSide,
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub quantity: Shares,
+    pub order_id: Option<String>,
+    pub error: Option<String>,
+}
+
+impl Portfolio {
+    // T+1可卖数量: 持仓减去当日买入和挂单占用
+    pub fn sellable_amount(&self, code: &str, at: DateTime<Utc>) -> Shares {
+        let position = match self.positions.get(code) {
+            Some(position) => position,
+            None => return 0,
+        };
+
+        let today = trade_date(at);
+        let bought_today: Shares = position.transactions.iter()
+            .filter(|t| matches!(t.transaction_type, TransactionType::Buy) && trade_date(t.timestamp) == today)
+            .map(|t| t.amount)
+            .sum();
+
+        (position.amount - bought_today - self.reserved_amount(code)).max(0)
+    }
+
+    // 当前持仓权重, 可作为其他组合的模型组合
+    pub fn weights(&self) -> HashMap<String, f64> {
+        let equity = self.total_market_value();
+        if equity <= 0.0 {
+            return HashMap::new();
+        }
+
+        self.positions.values()
+            .filter_map(|p| p.market_value().map(|v| (p.code.clone(), v / equity)))
+            .collect()
+    }
+
+    // 按目标权重生成调仓计划, prices中没有的股票使用持仓现价
+    pub fn plan_rebalance(
+        &self,
+        targets: &HashMap<String, f64>,
+        prices: &HashMap<String, f64>,
+        config: &RebalanceConfig,
+    ) -> Result<RebalancePlan, String> {
+        if targets.values().any(|w| *w < 0.0) {
+            return Err("目标权重不能为负".to_string());
+        }
+        let total_weight: f64 = targets.values().sum();
+        if total_weight + config.cash_reserve > 1.0 + 1e-9 {
+            return Err(format!("目标权重合计{:.4}加保留现金超过100%", total_weight));
+        }
+
+        let now = Utc::now();
+        let mut warnings = Vec::new();
+        let price_of = |code: &str| -> Option<Money> {
+            prices.get(code).copied()
+                .or_else(|| self.positions.get(code).and_then(|p| p.current_price))
+                .filter(|p| *p > 0.0)
+                .map(decimal)
+        };
+
+        // 总资产按计划价格估值
+        let mut equity = self.cash_balance();
+        for position in self.positions.values() {
+            match price_of(&position.code) {
+                Some(price) => equity += Money::from(position.amount) * price,
+                None => {
+                    warnings.push(format!("{}没有价格, 按成本估值且不参与调仓", position.code));
+                    equity += position.total_cost();
+                }
+            }
+        }
+        if equity <= Money::ZERO {
+            return Err("组合总资产为0".to_string());
+        }
+
+        let codes: BTreeSet<&String> = targets.keys().chain(self.positions.keys()).collect();
+        let mut sells = Vec::new();
+        let mut buys = Vec::new();
+
+        for code in codes {
+            let price = match price_of(code) {
+                Some(price) => price,
+                None => {
+                    if !self.positions.contains_key(code) {
+                        warnings.push(format!("{}没有价格, 跳过", code));
+                    }
+                    continue;
+                }
+            };
+
+            let held = self.positions.get(code).map(|p| p.amount).unwrap_or(0);
+            let target_weight = targets.get(code).copied().unwrap_or(0.0);
+            let target_amount = round_lot(to_f64(equity) * target_weight / to_f64(price));
+            let current_weight = to_f64(Money::from(held) * price / equity);
+
+            if held > target_amount {
+                // 清仓可以卖出零股, 其余按整手卖出
+                let sellable = self.sellable_amount(code, now);
+                let mut quantity = if target_amount == 0 { held } else { round_lot((held - target_amount) as f64) };
+                if quantity > sellable {
+                    warnings.push(format!("{}可卖数量{}不足, 受T+1限制", code, sellable));
+                    quantity = if sellable >= held { held } else { round_lot(sellable as f64) };
+                }
+                if quantity > 0 {
+                    sells.push((code.clone(), quantity, price, current_weight, target_weight));
+                }
+            } else if target_amount > held {
+                let quantity = target_amount - held;
+                if quantity > 0 {
+                    buys.push((code.clone(), quantity, price, current_weight, target_weight));
+                }
+            }
+        }
+
+        let mut trades = Vec::new();
+        let mut cash = self.available_cash();
+
+        for (code, quantity, price, current_weight, target_weight) in sells {
+            let value = Money::from(quantity) * price;
+            if value < config.min_trade_value && quantity != self.positions[&code].amount {
+                continue;
+            }
+            let (fee, tax) = config.fees.costs(OrderSide::Sell, value);
+            cash += value - fee - tax;
+            trades.push(PlannedTrade { code, side: OrderSide::Sell, quantity, price, value, fee, tax, current_weight, target_weight });
+        }
+
+        // 现金不足时优先满足缺口最大的买入
+        buys.sort_by(|a, b| (b.4 - b.3).partial_cmp(&(a.4 - a.3)).unwrap());
+        let reserve = equity * decimal(config.cash_reserve);
+        for (code, quantity, price, current_weight, target_weight) in buys {
+            let mut quantity = quantity.min(round_lot(to_f64((cash - reserve).max(Money::ZERO) / price)));
+            while quantity > 0 {
+                let (fee, _) = config.fees.costs(OrderSide::Buy, Money::from(quantity) * price);
+                if Money::from(quantity) * price + fee <= cash - reserve {
+                    break;
+                }
+                quantity -= LOT_SIZE;
+            }
+            if quantity <= 0 {
+                warnings.push(format!("{}现金不足, 未能买入", code));
+                continue;
+            }
+
+            let value = Money::from(quantity) * price;
+            if value < config.min_trade_value {
+                continue;
+            }
+            let (fee, tax) = config.fees.costs(OrderSide::Buy, value);
+            cash -= value + fee + tax;
+            trades.push(PlannedTrade { code, side: OrderSide::Buy, quantity, price, value, fee, tax, current_weight, target_weight });
+        }
+
+        Ok(RebalancePlan {
+            portfolio: self.name.clone(),
+            equity,
+            cash_before: self.cash_balance(),
+            cash_after: cash,
+            trades,
+            warnings,
+        })
+    }
+
+    // 执行调仓计划, use_orders为true时下市价单, 否则直接记录交易
+    pub fn execute_rebalance(&mut self, plan: &RebalancePlan, use_orders: bool) -> Vec<RebalanceExecution> {
+        let mut results = Vec::new();
+
+        for trade in &plan.trades {
+            let mut result = RebalanceExecution {
+                code: trade.code.clone(),
+                side: trade.side,
+                quantity: trade.quantity,
+                order_id: None,
+                error: None,
+            };
+
+            if use_orders {
+                let mut order = Order::new(trade.code.clone(), trade.side, OrderType::Market, trade.quantity);
+                order.reference_price = Some(to_f64(trade.price));
+                order.note = Some("调仓".to_string());
+                let order = self.place_order(order);
+                if order.status == OrderStatus::Rejected {
+                    result.error = order.reason.clone();
+                }
+                result.order_id = Some(order.id);
+            } else {
+                let mut transaction = Transaction::new(trade.code.clone(), trade.side.transaction_type(), trade.quantity, trade.price);
+                transaction.fee = Some(trade.fee);
+                transaction.tax = Some(trade.tax);
+                transaction.note = Some("调仓".to_string());
+                if let Err(e) = self.add_transaction(transaction) {
+                    result.error = Some(e);
+                }
+            }
+
+            results.push(result);
+        }
+
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_rebalance_respects_lots_and_t1() {
+        let mut portfolio = Portfolio::new("test".to_string(), Money::from(100000));
+        let mut buy = Transaction::new("600000".to_string(), TransactionType::Buy, 5000, Money::from(10));
+        buy.timestamp = Utc::now() - chrono::Duration::days(3);
+        portfolio.add_transaction(buy).unwrap();
+        // 当日买入的股票不可卖出
+        portfolio.add_transaction(Transaction::new("601398".to_string(), TransactionType::Buy, 1000, Money::from(5))).unwrap();
+        assert_eq!(portfolio.sellable_amount("601398", Utc::now()), 0);
+
+        let prices = HashMap::from([
+            ("600000".to_string(), 10.0),
+            ("601398".to_string(), 5.0),
+            ("000001".to_string(), 12.34),
+        ]);
+        let targets = HashMap::from([
+            ("600000".to_string(), 0.2),
+            ("000001".to_string(), 0.5),
+        ]);
+        let plan = portfolio.plan_rebalance(&targets, &prices, &RebalanceConfig::default()).unwrap();
+
+        // 总资产100000, 600000目标2000股, 需卖出3000股
+        let sell = plan.trades.iter().find(|t| t.code == "600000").unwrap();
+        assert_eq!(sell.side, OrderSide::Sell);
+        assert_eq!(sell.quantity, 3000);
+        assert!(plan.trades.iter().all(|t| t.code != "601398"));
+        assert!(plan.warnings.iter().any(|w| w.contains("601398")));
+
+        let buy = plan.trades.iter().find(|t| t.code == "000001").unwrap();
+        assert_eq!(buy.quantity % LOT_SIZE, 0);
+        assert_eq!(buy.quantity, 4000);
+        assert!(plan.cash_after >= Money::ZERO);
+
+        let results = portfolio.execute_rebalance(&plan, false);
+        assert!(results.iter().all(|r| r.error.is_none()));
+        assert_eq!(portfolio.positions["600000"].amount, 2000);
+    }
+}
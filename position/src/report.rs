@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Datelike, Utc};
+use std::collections::BTreeMap;
+use crate::cash::CashEntryType;
+use crate::lots::match_lots;
+use crate::market::trade_date;
+use crate::models::{Portfolio, Transaction, TransactionType};
+use crate::money::{round_money, Money, Shares};
+
+// 已实现盈亏 (按先进先出匹配的买入批次)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RealizedLot {
+    pub code: String,
+    pub acquired_at: DateTime<Utc>,
+    pub sold_at: DateTime<Utc>,
+    pub quantity: Shares,
+    // 买入成本 (不含费用, 费用单独列示)
+    pub cost_basis: Money,
+    // 卖出金额 (不含费用)
+    pub proceeds: Money,
+    pub gain: Money,
+    pub holding_days: i64,
+    // 卖出交易ID
+    pub transaction_id: String,
+}
+
+// 分红收入
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncomeItem {
+    pub code: String,
+    pub paid_at: DateTime<Utc>,
+    // 税前金额 (送股为0)
+    pub gross: Money,
+    // 红利税
+    pub tax: Money,
+    pub net: Money,
+    pub transaction_id: String,
+}
+
+// 年度已实现收益和收入报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnualReport {
+    pub portfolio: String,
+    pub year: i32,
+    pub realized: Vec<RealizedLot>,
+    pub dividends: Vec<IncomeItem>,
+    pub realized_gain: Money,
+    pub dividend_income: Money,
+    // 现金利息收入 (负数为利息支出)
+    pub interest: Money,
+    // 佣金和过户费
+    pub fees: Money,
+    // 印花税
+    pub stamp_tax: Money,
+    // 红利税
+    pub dividend_tax: Money,
+    // 净收益: 已实现盈亏 + 分红 + 利息 - 费用 - 税
+    pub net_income: Money,
+}
+
+fn in_year(timestamp: DateTime<Utc>, year: i32) -> bool {
+    trade_date(timestamp).year() == year
+}
+
+impl Portfolio {
+    // 生成指定年度 (按北京时间) 的已实现收益和收入报告
+    pub fn annual_report(&self, year: i32) -> Result<AnnualReport, String> {
+        let history = self.transaction_history()?;
+
+        let mut by_code: BTreeMap<&str, Vec<Transaction>> = BTreeMap::new();
+        for transaction in &history {
+            by_code.entry(transaction.code.as_str()).or_default().push(transaction.clone());
+        }
+
+        let mut realized = Vec::new();
+        for (code, transactions) in &by_code {
+            let (_, closed) = match_lots(transactions);
+            for closed in closed.into_iter().filter(|c| in_year(c.sell.timestamp, year)) {
+                let quantity = Money::from(closed.lot.amount);
+                let cost_basis = round_money(quantity * closed.lot.cost);
+                let proceeds = round_money(quantity * closed.sell.price);
+                realized.push(RealizedLot {
+                    code: code.to_string(),
+                    acquired_at: closed.lot.acquired_at,
+                    sold_at: closed.sell.timestamp,
+                    quantity: closed.lot.amount,
+                    cost_basis,
+                    proceeds,
+                    gain: proceeds - cost_basis,
+                    holding_days: (closed.sell.timestamp - closed.lot.acquired_at).num_days(),
+                    transaction_id: closed.sell.id.clone(),
+                });
+            }
+        }
+        realized.sort_by(|a, b| a.sold_at.cmp(&b.sold_at).then(a.code.cmp(&b.code)));
+
+        let mut dividends = Vec::new();
+        let mut fees = Money::ZERO;
+        let mut stamp_tax = Money::ZERO;
+        for transaction in history.iter().filter(|t| in_year(t.timestamp, year)) {
+            let tax = transaction.tax.unwrap_or_default();
+            match transaction.transaction_type {
+                TransactionType::Buy | TransactionType::Sell => {
+                    fees += transaction.fee.unwrap_or_default();
+                    stamp_tax += tax;
+                },
+                TransactionType::Dividend | TransactionType::BonusShares => {
+                    let gross = if matches!(transaction.transaction_type, TransactionType::Dividend) {
+                        transaction.total_value()
+                    } else {
+                        Money::ZERO
+                    };
+                    if gross.is_zero() && tax.is_zero() {
+                        continue;
+                    }
+                    dividends.push(IncomeItem {
+                        code: transaction.code.clone(),
+                        paid_at: transaction.timestamp,
+                        gross,
+                        tax,
+                        net: gross - tax,
+                        transaction_id: transaction.id.clone(),
+                    });
+                },
+                TransactionType::Split => {},
+            }
+        }
+
+        let interest: Money = self.cash_ledger.entries.iter()
+            .filter(|e| e.entry_type == CashEntryType::Interest && in_year(e.timestamp, year))
+            .map(|e| e.amount)
+            .sum();
+
+        let realized_gain: Money = realized.iter().map(|r| r.gain).sum();
+        let dividend_income: Money = dividends.iter().map(|d| d.gross).sum();
+        let dividend_tax: Money = dividends.iter().map(|d| d.tax).sum();
+
+        Ok(AnnualReport {
+            portfolio: self.name.clone(),
+            year,
+            realized,
+            dividends,
+            realized_gain,
+            dividend_income,
+            interest,
+            fees,
+            stamp_tax,
+            dividend_tax,
+            net_income: realized_gain + dividend_income + interest - fees - stamp_tax - dividend_tax,
+        })
+    }
+}
+
+impl AnnualReport {
+    // 导出为CSV: 每行一条已实现盈亏或分红记录, 最后一行为合计
+    pub fn to_csv(&self) -> Result<String, String> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(["类型", "证券代码", "买入日期", "卖出/发放日期", "数量", "成本", "收入", "盈亏", "税费", "持有天数"])
+            .map_err(|e| e.to_string())?;
+
+        for lot in &self.realized {
+            writer.write_record([
+                "已实现盈亏".to_string(),
+                lot.code.clone(),
+                trade_date(lot.acquired_at).to_string(),
+                trade_date(lot.sold_at).to_string(),
+                lot.quantity.to_string(),
+                lot.cost_basis.to_string(),
+                lot.proceeds.to_string(),
+                lot.gain.to_string(),
+                String::new(),
+                lot.holding_days.to_string(),
+            ]).map_err(|e| e.to_string())?;
+        }
+
+        for item in &self.dividends {
+            writer.write_record([
+                "分红".to_string(),
+                item.code.clone(),
+                String::new(),
+                trade_date(item.paid_at).to_string(),
+                String::new(),
+                String::new(),
+                item.gross.to_string(),
+                item.net.to_string(),
+                item.tax.to_string(),
+                String::new(),
+            ]).map_err(|e| e.to_string())?;
+        }
+
+        // 合计行与表头对齐: 收入含分红和利息, 税费含佣金、印花税和红利税, 收入 - 成本 - 税费 = 盈亏
+        let cost_basis: Money = self.realized.iter().map(|r| r.cost_basis).sum();
+        let proceeds: Money = self.realized.iter().map(|r| r.proceeds).sum();
+        writer.write_record([
+            "合计".to_string(),
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+            cost_basis.to_string(),
+            (proceeds + self.dividend_income + self.interest).to_string(),
+            self.net_income.to_string(),
+            (self.fees + self.stamp_tax + self.dividend_tax).to_string(),
+            String::new(),
+        ]).map_err(|e| e.to_string())?;
+
+        let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+        String::from_utf8(bytes).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::money::money;
+
+    fn trade(transaction_type: TransactionType, amount: Shares, price: Money, at: DateTime<Utc>) -> Transaction {
+        let mut transaction = Transaction::new("600000".to_string(), transaction_type, amount, price);
+        transaction.timestamp = at;
+        transaction.fee = Some(Money::from(5));
+        transaction
+    }
+
+    #[test]
+    fn test_annual_report_matches_lots_fifo() {
+        let mut portfolio = Portfolio::new("test".to_string(), Money::from(100000));
+        let day = |y, m, d| Utc.with_ymd_and_hms(y, m, d, 3, 0, 0).unwrap();
+
+        portfolio.add_transaction(trade(TransactionType::Buy, 1000, Money::from(10), day(2023, 3, 1))).unwrap();
+        portfolio.add_transaction(trade(TransactionType::Buy, 1000, Money::from(12), day(2024, 2, 1))).unwrap();
+        let mut sell = trade(TransactionType::Sell, 1500, Money::from(11), day(2024, 5, 6));
+        sell.tax = Some(money(8.25));
+        portfolio.add_transaction(sell).unwrap();
+        let mut dividend = Transaction::new("600000".to_string(), TransactionType::Dividend, 500, money(0.3));
+        dividend.timestamp = day(2024, 7, 1);
+        dividend.tax = Some(Money::from(15));
+        portfolio.add_transaction(dividend).unwrap();
+        // 清仓后的交易仍然计入报告
+        let mut sell = trade(TransactionType::Sell, 500, Money::from(13), day(2024, 9, 2));
+        sell.tax = Some(money(3.25));
+        portfolio.add_transaction(sell).unwrap();
+        assert!(portfolio.positions.is_empty());
+
+        let report = portfolio.annual_report(2024).unwrap();
+        // 先卖出2023年买入的1000股, 再卖出2024年买入的500股, 最后500股
+        assert_eq!(report.realized.len(), 3);
+        assert_eq!(report.realized[0].gain, Money::from(1000));
+        assert_eq!(report.realized[1].gain, Money::from(-500));
+        assert_eq!(report.realized[2].gain, Money::from(500));
+        assert_eq!(report.realized_gain, Money::from(1000));
+        assert_eq!(report.dividend_income, Money::from(150));
+        // 2024年三笔交易的佣金
+        assert_eq!(report.fees, Money::from(15));
+        assert_eq!(report.stamp_tax, money(11.5));
+        assert_eq!(report.net_income, money(1108.5));
+
+        let csv = report.to_csv().unwrap();
+        assert_eq!(csv.lines().count(), 6);
+        let total: Vec<&str> = csv.lines().last().unwrap().split(',').collect();
+        assert_eq!(total[0], "合计");
+        assert_eq!(total[5].parse::<Money>().unwrap(), Money::from(22000));
+        assert_eq!(total[6].parse::<Money>().unwrap(), Money::from(23150));
+        assert_eq!(total[7].parse::<Money>().unwrap(), money(1108.5));
+        assert_eq!(total[8].parse::<Money>().unwrap(), money(41.5));
+        assert!(portfolio.annual_report(2023).unwrap().realized.is_empty());
+    }
+}
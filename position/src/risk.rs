@@ -0,0 +1,344 @@
+use serde::{Deserialize, Serialize};
+use chrono::Utc;
+use std::collections::HashMap;
+use crate::cash::CashEntryType;
+use crate::market::trade_date;
+use crate::models::{Portfolio, Position};
+use crate::money::{to_f64, Money, Shares};
+use crate::order::OrderSide;
+use crate::performance::daily_returns;
+
+// 风控限制, 未设置的项不检查
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RiskLimits {
+    // 单只股票最大权重 (占总资产比例)
+    pub max_position_weight: Option<f64>,
+    // 单个行业最大权重
+    pub max_sector_weight: Option<f64>,
+    // 最大总持仓比例
+    pub max_gross_exposure: Option<f64>,
+    // 单日最大换手 (成交金额占总资产比例)
+    pub max_daily_turnover: Option<f64>,
+    // 回撤熔断, 超过后禁止买入
+    pub max_drawdown: Option<f64>,
+    // 禁止交易的股票
+    #[serde(default)]
+    pub restricted_codes: Vec<String>,
+}
+
+// 风控规则
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum RiskRule {
+    PositionWeight,
+    SectorWeight,
+    GrossExposure,
+    DailyTurnover,
+    Drawdown,
+    Restricted,
+}
+
+// 风控违规记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskBreach {
+    pub rule: RiskRule,
+    pub code: Option<String>,
+    pub value: f64,
+    pub limit: f64,
+    pub reason: String,
+}
+
+// 把违规记录合并为错误信息
+pub fn format_breaches(breaches: &[RiskBreach]) -> String {
+    let reasons: Vec<&str> = breaches.iter().map(|b| b.reason.as_str()).collect();
+    format!("风控检查未通过: {}", reasons.join("; "))
+}
+
+// 持仓估值, 未估值时按成本计算
+fn position_value(position: &Position) -> f64 {
+    position.market_value().unwrap_or_else(|| to_f64(position.total_cost()))
+}
+
+impl Portfolio {
+    // 按持仓估值 (未估值按成本) 计算的总资产
+    fn risk_equity(&self) -> f64 {
+        self.positions.values().map(position_value).sum::<f64>() + to_f64(self.cash_balance()) - self.margin_liabilities()
+    }
+
+    // 当前回撤 (按剔除资金流动的净值指数计算)
+    pub fn current_drawdown(&self) -> f64 {
+        let mut index = vec![1.0];
+        for r in daily_returns(&self.nav_history) {
+            index.push(index[index.len() - 1] * (1.0 + r));
+        }
+        let peak = index.iter().cloned().fold(f64::MIN, f64::max);
+        if peak <= 0.0 {
+            return 0.0;
+        }
+        1.0 - index[index.len() - 1] / peak
+    }
+
+    // 当日成交金额
+    pub fn daily_turnover(&self) -> f64 {
+        let today = trade_date(Utc::now());
+        let turnover: Money = self.cash_ledger.entries.iter()
+            .filter(|e| e.entry_type == CashEntryType::TradeSettlement && trade_date(e.timestamp) == today)
+            .map(|e| e.amount.abs())
+            .sum();
+        to_f64(turnover)
+    }
+
+    // 交易前风控检查, 返回所有违规项
+    // sector为新建仓股票的行业, 未提供时使用已有持仓的行业
+    pub fn check_risk(&self, code: &str, side: OrderSide, quantity: Shares, price: f64, sector: Option<&str>) -> Vec<RiskBreach> {
+        let limits = &self.risk_limits;
+        let mut breaches = Vec::new();
+        let equity = self.risk_equity();
+        let value = quantity as f64 * price;
+
+        if limits.restricted_codes.iter().any(|c| c == code) {
+            breaches.push(RiskBreach {
+                rule: RiskRule::Restricted,
+                code: Some(code.to_string()),
+                value: 0.0,
+                limit: 0.0,
+                reason: format!("{}在禁止交易名单中", code),
+            });
+        }
+
+        if let Some(limit) = limits.max_daily_turnover {
+            let turnover = self.daily_turnover() + value;
+            if equity > 0.0 && turnover / equity > limit {
+                breaches.push(RiskBreach {
+                    rule: RiskRule::DailyTurnover,
+                    code: Some(code.to_string()),
+                    value: turnover / equity,
+                    limit,
+                    reason: format!("当日换手{:.2}%超过限制{:.2}%", turnover / equity * 100.0, limit * 100.0),
+                });
+            }
+        }
+
+        // 以下检查只限制增加风险的买入
+        if side == OrderSide::Sell {
+            return breaches;
+        }
+
+        if let Some(limit) = limits.max_drawdown {
+            let drawdown = self.current_drawdown();
+            if drawdown >= limit {
+                breaches.push(RiskBreach {
+                    rule: RiskRule::Drawdown,
+                    code: None,
+                    value: drawdown,
+                    limit,
+                    reason: format!("当前回撤{:.2}%触发熔断{:.2}%, 禁止买入", drawdown * 100.0, limit * 100.0),
+                });
+            }
+        }
+
+        if equity <= 0.0 {
+            return breaches;
+        }
+
+        let position = self.positions.get(code);
+        if let Some(limit) = limits.max_position_weight {
+            let weight = (position.map(position_value).unwrap_or(0.0) + value) / equity;
+            if weight > limit {
+                breaches.push(RiskBreach {
+                    rule: RiskRule::PositionWeight,
+                    code: Some(code.to_string()),
+                    value: weight,
+                    limit,
+                    reason: format!("{}权重{:.2}%超过限制{:.2}%", code, weight * 100.0, limit * 100.0),
+                });
+            }
+        }
+
+        // 行业未知时不做行业检查
+        let sector = sector.or_else(|| position.and_then(|p| p.sector.as_deref()));
+        if let (Some(limit), Some(sector)) = (limits.max_sector_weight, sector) {
+            let sector_value: f64 = self.positions.values()
+                .filter(|p| p.sector.as_deref() == Some(sector))
+                .map(position_value)
+                .sum();
+            let weight = (sector_value + value) / equity;
+            if weight > limit {
+                breaches.push(RiskBreach {
+                    rule: RiskRule::SectorWeight,
+                    code: Some(code.to_string()),
+                    value: weight,
+                    limit,
+                    reason: format!("行业{}权重{:.2}%超过限制{:.2}%", sector, weight * 100.0, limit * 100.0),
+                });
+            }
+        }
+
+        if let Some(limit) = limits.max_gross_exposure {
+            let exposure = (self.positions.values().map(position_value).sum::<f64>() + value) / equity;
+            if exposure > limit {
+                breaches.push(RiskBreach {
+                    rule: RiskRule::GrossExposure,
+                    code: Some(code.to_string()),
+                    value: exposure,
+                    limit,
+                    reason: format!("总持仓比例{:.2}%超过限制{:.2}%", exposure * 100.0, limit * 100.0),
+                });
+            }
+        }
+
+        breaches
+    }
+
+    // 检查当前持仓的风控状态
+    pub fn risk_breaches(&self) -> Vec<RiskBreach> {
+        let limits = &self.risk_limits;
+        let mut breaches = Vec::new();
+        let equity = self.risk_equity();
+
+        for position in self.positions.values() {
+            if limits.restricted_codes.contains(&position.code) {
+                breaches.push(RiskBreach {
+                    rule: RiskRule::Restricted,
+                    code: Some(position.code.clone()),
+                    value: position.amount as f64,
+                    limit: 0.0,
+                    reason: format!("持有禁止交易的股票{}", position.code),
+                });
+            }
+        }
+
+        if let Some(limit) = limits.max_drawdown {
+            let drawdown = self.current_drawdown();
+            if drawdown >= limit {
+                breaches.push(RiskBreach {
+                    rule: RiskRule::Drawdown,
+                    code: None,
+                    value: drawdown,
+                    limit,
+                    reason: format!("当前回撤{:.2}%触发熔断{:.2}%", drawdown * 100.0, limit * 100.0),
+                });
+            }
+        }
+
+        if let Some(limit) = limits.max_daily_turnover {
+            let turnover = self.daily_turnover();
+            if equity > 0.0 && turnover / equity > limit {
+                breaches.push(RiskBreach {
+                    rule: RiskRule::DailyTurnover,
+                    code: None,
+                    value: turnover / equity,
+                    limit,
+                    reason: format!("当日换手{:.2}%超过限制{:.2}%", turnover / equity * 100.0, limit * 100.0),
+                });
+            }
+        }
+
+        if equity <= 0.0 {
+            return breaches;
+        }
+
+        if let Some(limit) = limits.max_position_weight {
+            for position in self.positions.values() {
+                let weight = position_value(position) / equity;
+                if weight > limit {
+                    breaches.push(RiskBreach {
+                        rule: RiskRule::PositionWeight,
+                        code: Some(position.code.clone()),
+                        value: weight,
+                        limit,
+                        reason: format!("{}权重{:.2}%超过限制{:.2}%", position.code, weight * 100.0, limit * 100.0),
+                    });
+                }
+            }
+        }
+
+        if let Some(limit) = limits.max_sector_weight {
+            let mut sectors: HashMap<&str, f64> = HashMap::new();
+            for position in self.positions.values() {
+                if let Some(sector) = &position.sector {
+                    *sectors.entry(sector.as_str()).or_default() += position_value(position);
+                }
+            }
+            for (sector, value) in sectors {
+                let weight = value / equity;
+                if weight > limit {
+                    breaches.push(RiskBreach {
+                        rule: RiskRule::SectorWeight,
+                        code: None,
+                        value: weight,
+                        limit,
+                        reason: format!("行业{}权重{:.2}%超过限制{:.2}%", sector, weight * 100.0, limit * 100.0),
+                    });
+                }
+            }
+        }
+
+        if let Some(limit) = limits.max_gross_exposure {
+            let exposure = self.positions.values().map(position_value).sum::<f64>() / equity;
+            if exposure > limit {
+                breaches.push(RiskBreach {
+                    rule: RiskRule::GrossExposure,
+                    code: None,
+                    value: exposure,
+                    limit,
+                    reason: format!("总持仓比例{:.2}%超过限制{:.2}%", exposure * 100.0, limit * 100.0),
+                });
+            }
+        }
+
+        breaches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Transaction, TransactionType};
+    use crate::order::{Order, OrderStatus, OrderType};
+
+    #[test]
+    fn test_pre_trade_risk_checks() {
+        let mut portfolio = Portfolio::new("test".to_string(), Money::from(100000));
+        portfolio.risk_limits = RiskLimits {
+            max_position_weight: Some(0.2),
+            max_daily_turnover: Some(0.5),
+            restricted_codes: vec!["000001".to_string()],
+            ..Default::default()
+        };
+
+        let buy = Transaction::new("600000".to_string(), TransactionType::Buy, 3000, Money::from(10));
+        let err = portfolio.add_transaction(buy).unwrap_err();
+        assert!(err.contains("600000权重30.00%超过限制20.00%"));
+
+        let buy = Transaction::new("600000".to_string(), TransactionType::Buy, 1500, Money::from(10));
+        portfolio.add_transaction(buy).unwrap();
+
+        let breaches = portfolio.check_risk("000001", OrderSide::Buy, 100, 10.0, None);
+        assert_eq!(breaches.len(), 1);
+        assert_eq!(breaches[0].rule, RiskRule::Restricted);
+
+        // 新建仓按传入的行业检查行业权重
+        portfolio.positions.get_mut("600000").unwrap().sector = Some("银行".to_string());
+        portfolio.risk_limits.max_sector_weight = Some(0.15);
+        let breaches = portfolio.check_risk("601398", OrderSide::Buy, 100, 10.0, Some("银行"));
+        assert!(breaches.iter().any(|b| b.rule == RiskRule::SectorWeight));
+        assert!(portfolio.check_risk("601398", OrderSide::Buy, 100, 10.0, Some("科技")).is_empty());
+        portfolio.risk_limits.max_sector_weight = None;
+
+        // 没有参考价格的市价单被拒绝, 提供参考价格后按金额检查权重
+        let order = portfolio.place_order(Order::new("601398".to_string(), OrderSide::Buy, OrderType::Market, 3000));
+        assert_eq!(order.status, OrderStatus::Rejected);
+        assert!(order.reason.unwrap().contains("参考价格"));
+        let mut order = Order::new("601398".to_string(), OrderSide::Buy, OrderType::Market, 3000);
+        order.reference_price = Some(10.0);
+        let order = portfolio.place_order(order);
+        assert!(order.reason.unwrap().contains("601398权重"));
+
+        // 换手超限的订单被拒绝
+        let order = portfolio.place_order(Order::new("600519".to_string(), OrderSide::Buy, OrderType::Limit { price: 100.0 }, 400));
+        assert_eq!(order.status, OrderStatus::Rejected);
+        assert!(order.reason.unwrap().contains("当日换手"));
+
+        assert!(portfolio.risk_breaches().is_empty());
+    }
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use stockdata::models::StockData;
+use log::warn;
+use crate::market::trade_date;
+use crate::models::Portfolio;
+
+// 估值用行情报价
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Quote {
+    // 股票代码
+    pub code: String,
+    // 股票名称
+    pub name: Option<String>,
+    // 最新价格
+    pub price: f64,
+    // 涨跌幅 (百分比)
+    #[serde(default)]
+    pub change_percent: Option<f64>,
+    // 当日累计成交量
+    #[serde(default)]
+    pub volume: Option<f64>,
+    // 所属行业
+    #[serde(default)]
+    pub sector: Option<String>,
+    // 总市值
+    #[serde(default)]
+    pub market_cap: Option<f64>,
+}
+
+impl Quote {
+    // 昨收价, 由涨跌幅推算
+    pub fn previous_close(&self) -> Option<f64> {
+        self.change_percent
+            .map(|c| self.price / (1.0 + c / 100.0))
+            .filter(|p| p.is_finite() && *p > 0.0)
+    }
+}
+
+// 从抓取的股票数据生成报价
+pub fn quotes_from_stock_data(stocks: &[StockData]) -> HashMap<String, Quote> {
+    stocks.iter()
+        .filter(|s| s.price > 0.0)
+        .map(|s| (s.code.clone(), Quote {
+            code: s.code.clone(),
+            name: if s.name.is_empty() { None } else { Some(s.name.clone()) },
+            price: s.price,
+            change_percent: Some(s.change_percent),
+            volume: if s.volume > 0 { Some(s.volume as f64) } else { None },
+            sector: if s.sector.is_empty() { None } else { Some(s.sector.clone()) },
+            market_cap: if s.market_cap > 0 { Some(s.market_cap as f64) } else { None },
+        }))
+        .collect()
+}
+
+// 用价格缓存覆盖报价中的价格, 保留已有的名称
+pub fn overlay_prices(quotes: &mut HashMap<String, Quote>, prices: &HashMap<String, f64>) {
+    for (code, price) in prices {
+        if *price <= 0.0 {
+            continue;
+        }
+
+        quotes.entry(code.clone())
+            .and_modify(|q| q.price = *price)
+            .or_insert_with(|| Quote {
+                code: code.clone(),
+                name: None,
+                price: *price,
+                change_percent: None,
+                volume: None,
+                sector: None,
+                market_cap: None,
+            });
+    }
+}
+
+impl Portfolio {
+    // 按最新报价更新所有持仓的现价, 返回更新的持仓数量
+    pub fn mark_to_market(&mut self, quotes: &HashMap<String, Quote>, valued_at: DateTime<Utc>) -> usize {
+        let mut updated = 0;
+
+        for position in self.positions.values_mut() {
+            if let Some(quote) = quotes.get(&position.code) {
+                position.current_price = Some(quote.price);
+                position.valued_at = Some(valued_at);
+                if let Some(name) = &quote.name {
+                    position.name = name.clone();
+                }
+                if let Some(sector) = &quote.sector {
+                    position.sector = Some(sector.clone());
+                }
+                updated += 1;
+            }
+        }
+
+        // 融券负债按现价计算
+        if let Some(margin) = self.margin.as_mut() {
+            for short in margin.shorts.values_mut() {
+                if let Some(quote) = quotes.get(&short.code) {
+                    short.current_price = Some(quote.price);
+                    updated += 1;
+                }
+            }
+        }
+
+        if updated > 0 {
+            self.valued_at = Some(valued_at);
+        }
+
+        // 信用账户计收截至估值日的利息
+        if self.margin.is_some() {
+            if let Err(e) = self.accrue_margin_interest(trade_date(valued_at)) {
+                warn!("组合{}计息失败: {}", self.name, e);
+            }
+        }
+
+        updated
+    }
+}
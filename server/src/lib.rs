@@ -1,4 +1,5 @@
 pub mod position;
+pub mod orders;
 pub mod strategy;
 pub mod stockdata;
 pub mod server;
@@ -8,13 +8,15 @@ use std::path::Path;
 
 mod server;
 mod position;
+mod orders;
 mod strategy;
 mod stockdata;
 
 // 导入相关函数
 use crate::stockdata::{init_webdriver, close_webdriver, fetch_data, get_stockdata_status, get_stockdata, get_price, StockDataState};
-use crate::position::{list_positions, get_portfolio, add_position, remove_position, add_portfolio, remove_portfolio, PositionState};
-use crate::strategy::{list_strategies, get_strategy, run_strategy, backtest_strategy, get_backtest_result, StrategyState};
+use crate::position::{list_positions, get_portfolio, add_position, remove_position, add_portfolio, remove_portfolio, list_corporate_actions, add_corporate_actions, load_corporate_actions_file, deposit_cash, withdraw_cash, get_cash_ledger, revalue_portfolios, get_performance, get_portfolio_metrics, backfill_nav, import_statement, reconcile_portfolio, set_paper_account, get_risk_status, set_risk_limits, get_exposure, rebalance_portfolio, get_events, get_annual_report, get_portfolio_as_of, amend_transaction, cancel_transaction, undo_last_event, open_margin_account, get_margin_status, margin_buy, short_sell, cover_short, repay_financing, PositionState};
+use crate::orders::{place_order, cancel_order, fill_order, list_orders};
+use crate::strategy::{list_strategies, get_strategy, run_strategy, stop_strategy, backtest_strategy, get_backtest_result, get_indicators, check_indicators, get_strategy_params, update_strategy_params, start_optimization, get_optimization, walk_forward_analysis, find_pair_candidates, StrategyState};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -64,6 +66,7 @@ async fn main() -> std::io::Result<()> {
             .service(
                 web::scope("/api/stockdata")
                     .app_data(web::Data::new(stockdata_state.clone()))
+                    .app_data(web::Data::new(position_state.clone()))
                     .service(init_webdriver)
                     .service(close_webdriver)
                     .service(fetch_data)
@@ -75,22 +78,73 @@ async fn main() -> std::io::Result<()> {
             .service(
                 web::scope("/api/position")
                     .app_data(web::Data::new(position_state.clone()))
+                    .app_data(web::Data::new(stockdata_state.clone()))
                     .service(list_positions)
                     .service(get_portfolio)
                     .service(add_position)
                     .service(remove_position)
                     .service(add_portfolio)
                     .service(remove_portfolio)
+                    .service(list_corporate_actions)
+                    .service(add_corporate_actions)
+                    .service(load_corporate_actions_file)
+                    .service(deposit_cash)
+                    .service(withdraw_cash)
+                    .service(get_cash_ledger)
+                    .service(revalue_portfolios)
+                    .service(get_performance)
+                    .service(get_portfolio_metrics)
+                    .service(backfill_nav)
+                    .service(import_statement)
+                    .service(reconcile_portfolio)
+                    .service(set_paper_account)
+                    .service(get_risk_status)
+                    .service(get_exposure)
+                    .service(set_risk_limits)
+                    .service(rebalance_portfolio)
+                    .service(get_events)
+                    .service(get_annual_report)
+                    .service(get_portfolio_as_of)
+                    .service(amend_transaction)
+                    .service(cancel_transaction)
+                    .service(undo_last_event)
+                    .service(open_margin_account)
+                    .service(get_margin_status)
+                    .service(margin_buy)
+                    .service(short_sell)
+                    .service(cover_short)
+                    .service(repay_financing)
+            )
+            // 注册订单管理API
+            .service(
+                web::scope("/api/orders")
+                    .app_data(web::Data::new(position_state.clone()))
+                    .app_data(web::Data::new(stockdata_state.clone()))
+                    .service(place_order)
+                    .service(cancel_order)
+                    .service(fill_order)
+                    .service(list_orders)
             )
             // 注册策略模块API
             .service(
                 web::scope("/api/strategy")
                     .app_data(web::Data::new(strategy_state.clone()))
+                    .app_data(web::Data::new(stockdata_state.clone()))
+                    .app_data(web::Data::new(position_state.clone()))
                     .service(list_strategies)
                     .service(get_strategy)
                     .service(run_strategy)
+                    .service(stop_strategy)
                     .service(backtest_strategy)
                     .service(get_backtest_result)
+                    .service(check_indicators)
+                    .service(get_indicators)
+                    .service(get_strategy_params)
+                    .service(update_strategy_params)
+                    .service(start_optimization)
+                    .service(get_optimization)
+                    .service(walk_forward_analysis)
+                    .service(find_pair_candidates)
             )
     })
     .bind(format!("{}:{}", common::constants::IP, common::constants::PORT))?
@@ -0,0 +1,175 @@
+use actix_web::{web, HttpResponse, Responder, get, post};
+use serde::{Deserialize, Serialize};
+use log::{info, error};
+use std::sync::{Arc, Mutex};
+use chrono::Utc;
+use position::order::{Order, OrderSide, OrderStatus, OrderType, TimeInForce};
+use position::market::normalize_code;
+use position::money::{deserialize_shares, Money, Shares};
+use crate::position::PositionState;
+use crate::stockdata::StockDataState;
+
+#[derive(Deserialize, Serialize)]
+pub struct PlaceOrderRequest {
+    pub portfolio: String,
+    pub code: String,
+    pub side: OrderSide,
+    pub order_type: OrderType,
+    pub time_in_force: Option<TimeInForce>,
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub quantity: Shares,
+    // 市价单的估算价格, 未提供时使用最新行情
+    pub reference_price: Option<f64>,
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CancelOrderRequest {
+    pub portfolio: String,
+    pub order_id: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct FillOrderRequest {
+    pub portfolio: String,
+    pub order_id: String,
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub quantity: Shares,
+    pub price: Money,
+    pub fee: Option<Money>,
+    pub tax: Option<Money>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ListOrdersQuery {
+    pub portfolio: String,
+    // 为true时只返回进行中的订单
+    pub open: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ListOrdersResponse {
+    pub portfolio: String,
+    pub orders: Vec<Order>,
+}
+
+// 下单
+#[post("/place")]
+pub async fn place_order(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    stockdata_state: web::Data<Arc<Mutex<StockDataState>>>,
+    req: web::Json<PlaceOrderRequest>,
+) -> impl Responder {
+    info!("下单: portfolio: {}, code: {}, side: {:?}, type: {:?}, quantity: {}", req.portfolio, req.code, req.side, req.order_type, req.quantity);
+
+    let code = normalize_code(&req.code);
+    let quote = stockdata_state.lock().unwrap().quotes().remove(&code);
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.portfolio) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    let mut order = Order::new(code, req.side, req.order_type, req.quantity);
+    order.time_in_force = req.time_in_force.unwrap_or_default();
+    // 参考价格和行业用于资金和风控检查
+    order.reference_price = req.reference_price.or(quote.as_ref().map(|q| q.price));
+    order.sector = quote.and_then(|q| q.sector);
+    order.note = req.note.clone();
+
+    let order = portfolio.place_order(order);
+    if order.status == OrderStatus::Rejected {
+        error!("订单被拒绝: {}: {}", order.id, order.reason.clone().unwrap_or_default());
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("订单被拒绝: {}", order.reason.clone().unwrap_or_default()),
+            "order": order
+        }));
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "order": order
+    }))
+}
+
+// 撤单
+#[post("/cancel")]
+pub async fn cancel_order(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<CancelOrderRequest>,
+) -> impl Responder {
+    info!("撤单: portfolio: {}, order: {}", req.portfolio, req.order_id);
+
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.portfolio) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    match portfolio.cancel_order(&req.order_id) {
+        Ok(order) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "order": order
+        })),
+        Err(e) => {
+            error!("撤单失败: {}", e);
+            HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))
+        }
+    }
+}
+
+// 录入成交回报
+#[post("/fill")]
+pub async fn fill_order(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<FillOrderRequest>,
+) -> impl Responder {
+    info!("订单成交: portfolio: {}, order: {}, quantity: {}, price: {}", req.portfolio, req.order_id, req.quantity, req.price);
+
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.portfolio) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    match portfolio.fill_order(&req.order_id, req.quantity, req.price, req.fee.unwrap_or_default(), req.tax.unwrap_or_default(), Utc::now()) {
+        Ok(transaction) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "transaction": transaction
+        })),
+        Err(e) => {
+            error!("订单成交失败: {}", e);
+            HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))
+        }
+    }
+}
+
+// 查询订单
+#[get("/list")]
+pub async fn list_orders(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    query: web::Query<ListOrdersQuery>,
+) -> impl Responder {
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == query.portfolio) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    portfolio.expire_orders(Utc::now());
+
+    let orders = if query.open.unwrap_or(false) {
+        portfolio.open_orders().into_iter().cloned().collect()
+    } else {
+        portfolio.orders.clone()
+    };
+
+    HttpResponse::Ok().json(ListOrdersResponse {
+        portfolio: portfolio.name.clone(),
+        orders,
+    })
+}
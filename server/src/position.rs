@@ -4,24 +4,180 @@ use serde::{Deserialize, Serialize};
 use log::{info, error};
 use std::sync::{Arc, Mutex};
 use position::models::{Portfolio, TransactionType};
+use position::corporate_action::{CorporateAction, load_corporate_actions};
+use position::cash::CashEntry;
+use position::valuation::Quote;
+use position::money::{deserialize_shares, Money, Shares};
+use position::performance::PriceSeries;
+use position::market::trade_date;
+use position::rebalance::{RebalanceConfig, RebalanceExecution, RebalancePlan};
+use position::risk::{RiskBreach, RiskLimits};
+use position::exposure::{universe_sector_weights, DEFAULT_TOP_N};
+use position::paper::{PaperConfig, PaperExchange};
+use position::margin::{MarginAlert, MarginConfig, MarginLevel};
+use strategy::metrics::portfolio_metrics;
+use common::utils::ntfy::{error_message, info_message, warning_message};
+use position::broker_import::{ColumnMapping, HoldingsMapping, parse_statement, parse_holdings};
+use chrono::NaiveDate;
+use position::models::Position;
+use chrono::{DateTime, Utc};
+use crate::stockdata::StockDataState;
+use std::collections::HashMap;
+
+// 记录价格历史的业绩基准
+const BENCHMARK_CODES: [&str; 2] = ["SH000001", "SH000300"];
+
+// 默认快照目录
+const SNAPSHOT_DIR: &str = "output";
+
+// 信用账户风险提醒的通知主题
+const MARGIN_ALERT_TOPIC: &str = "margin";
 
 // 持仓管理状态
 pub struct PositionState {
     pub portfolios: Vec<Portfolio>,
+    pub corporate_actions: Vec<CorporateAction>,
+    pub benchmarks: HashMap<String, PriceSeries>,
+    pub paper_exchange: PaperExchange,
 }
 
 impl PositionState {
     pub fn new() -> Self {
         Self {
             portfolios: vec![],
+            corporate_actions: vec![],
+            benchmarks: HashMap::new(),
+            paper_exchange: PaperExchange::new(),
+        }
+    }
+
+    // 按最新报价估值所有投资组合并记录净值, 返回更新的持仓数量
+    pub fn revalue(&mut self, quotes: &HashMap<String, Quote>) -> usize {
+        let now = Utc::now();
+        for portfolio in self.portfolios.iter_mut() {
+            portfolio.expire_orders(now);
+        }
+
+        // 撮合模拟账户的挂单
+        for report in self.paper_exchange.match_orders(&mut self.portfolios, quotes, now) {
+            info!("模拟成交: {} {} {:?} {:?} {}@{} {}", report.portfolio, report.code, report.side, report.status, report.quantity, report.price, report.reason.unwrap_or_default());
+        }
+
+        let mut updated = 0;
+        let mut alerts = Vec::new();
+        for portfolio in self.portfolios.iter_mut() {
+            updated += portfolio.mark_to_market(quotes, now);
+
+            // 估值时已计息, 检查信用账户维持担保比例
+            if portfolio.margin.is_some() {
+                alerts.extend(portfolio.check_margin());
+            }
+
+            portfolio.record_nav(now);
+        }
+        notify_margin_alerts(alerts);
+
+        for code in BENCHMARK_CODES {
+            if let Some(quote) = quotes.get(code) {
+                self.benchmarks.entry(code.to_string()).or_default().insert(trade_date(now), quote.price);
+            }
+        }
+
+        updated
+    }
+
+    // 记录公司行动并应用到所有投资组合
+    pub fn apply_corporate_actions(&mut self, actions: Vec<CorporateAction>) -> Vec<ApplyCorporateActionResult> {
+        let mut results = Vec::new();
+
+        for action in actions {
+            let key = action.key();
+            if !self.corporate_actions.iter().any(|a| a.key() == key) {
+                self.corporate_actions.push(action.clone());
+            }
+
+            for portfolio in self.portfolios.iter_mut() {
+                if portfolio.applied_actions.contains(&key) {
+                    continue;
+                }
+
+                let result = match portfolio.apply_corporate_action(&action) {
+                    Ok(transactions) => ApplyCorporateActionResult {
+                        portfolio: portfolio.name.clone(),
+                        action: key.clone(),
+                        transactions: transactions.len(),
+                        error: None,
+                    },
+                    Err(e) => {
+                        error!("应用公司行动失败: {}: {}", key, e);
+                        ApplyCorporateActionResult {
+                            portfolio: portfolio.name.clone(),
+                            action: key.clone(),
+                            transactions: 0,
+                            error: Some(e),
+                        }
+                    }
+                };
+                results.push(result);
+            }
         }
+
+        results
     }
 }
 
+// 信用账户风险等级变化时推送通知
+fn notify_margin_alerts(alerts: Vec<MarginAlert>) {
+    for alert in alerts {
+        let message = alert.message();
+        info!("信用账户风险提醒: {}", message);
+
+        let title = format!("{} 信用账户风险", alert.portfolio);
+        let notification = match alert.level {
+            MarginLevel::Liquidation | MarginLevel::Call => error_message(MARGIN_ALERT_TOPIC.to_string(), title, message),
+            MarginLevel::Warning if alert.worsened() => warning_message(MARGIN_ALERT_TOPIC.to_string(), title, message),
+            _ => info_message(MARGIN_ALERT_TOPIC.to_string(), title, message),
+        };
+        actix_web::rt::spawn(async move {
+            if let Err(e) = notification.send().await {
+                error!("发送信用账户提醒失败: {}", e);
+            }
+        });
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ApplyCorporateActionResult {
+    pub portfolio: String,
+    pub action: String,
+    pub transactions: usize,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct LoadCorporateActionsRequest {
+    pub path: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CashRequest {
+    pub portfolio: String,
+    pub amount: Money,
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CashLedgerResponse {
+    pub name: String,
+    pub cash_balance: Money,
+    pub net_contributions: Money,
+    pub entries: Vec<CashEntry>,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct AddPortfolioRequest {
     pub name: String,
-    pub cash_balance: f64,
+    pub cash_balance: Money,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -33,14 +189,16 @@ pub struct RemovePortfolioRequest {
 pub struct AddPositionRequest {
     pub portfolio: String,
     pub code: String,
-    pub amount: f64,
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub amount: Shares,
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct RemovePositionRequest {
     pub portfolio: String,
     pub code: String,
-    pub amount: f64,
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub amount: Shares,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -51,8 +209,149 @@ pub struct QueryPortfolioRequest {
 #[derive(Deserialize, Serialize)]
 pub struct QueryPortfolioResponse {
     pub name: String,
-    pub cash_balance: f64,
-    pub positions: Vec<position::models::Position>,
+    pub cash_balance: Money,
+    pub total_market_value: f64,
+    pub total_profit_loss: f64,
+    pub valued_at: Option<DateTime<Utc>>,
+    pub positions: Vec<PositionView>,
+}
+
+// 带估值信息的持仓
+#[derive(Deserialize, Serialize)]
+pub struct PositionView {
+    #[serde(flatten)]
+    pub position: Position,
+    pub market_value: Option<f64>,
+    pub profit_loss: Option<f64>,
+    pub profit_loss_percent: Option<f64>,
+}
+
+impl From<&Position> for PositionView {
+    fn from(position: &Position) -> Self {
+        Self {
+            position: position.clone(),
+            market_value: position.market_value(),
+            profit_loss: position.profit_loss(),
+            profit_loss_percent: position.profit_loss_percent(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RevalueRequest {
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct BackfillNavRequest {
+    pub name: Option<String>,
+    pub dir: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct PaperAccountRequest {
+    pub name: String,
+    pub enabled: bool,
+    pub config: Option<PaperConfig>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct MarginAccountRequest {
+    pub name: String,
+    pub config: Option<MarginConfig>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct MarginTradeRequest {
+    pub portfolio: String,
+    pub code: String,
+    #[serde(deserialize_with = "deserialize_shares")]
+    pub quantity: Shares,
+    pub price: Money,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct MarginRepayRequest {
+    pub portfolio: String,
+    pub amount: Money,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RiskLimitsRequest {
+    pub name: String,
+    pub limits: RiskLimits,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RiskStatusResponse {
+    pub name: String,
+    pub limits: RiskLimits,
+    pub drawdown: f64,
+    pub daily_turnover: f64,
+    pub breaches: Vec<RiskBreach>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RebalanceRequest {
+    pub portfolio: String,
+    // 目标权重, 与model二选一
+    pub targets: Option<HashMap<String, f64>>,
+    // 以另一个投资组合的当前权重作为目标
+    pub model: Option<String>,
+    pub config: Option<RebalanceConfig>,
+    // 为false时只预览调仓计划
+    #[serde(default)]
+    pub execute: bool,
+    // 执行时下单而不是直接记录交易
+    #[serde(default)]
+    pub use_orders: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RebalanceResponse {
+    pub plan: RebalancePlan,
+    pub executions: Vec<RebalanceExecution>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct AmendTransactionRequest {
+    pub portfolio: String,
+    pub transaction_id: String,
+    pub amount: Option<Shares>,
+    pub price: Option<Money>,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub fee: Option<Money>,
+    pub tax: Option<Money>,
+    pub actor: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CancelTransactionRequest {
+    pub portfolio: String,
+    pub transaction_id: String,
+    pub actor: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct UndoRequest {
+    pub portfolio: String,
+    pub actor: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ImportStatementRequest {
+    pub portfolio: String,
+    pub path: String,
+    pub mapping: Option<ColumnMapping>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ReconcileRequest {
+    pub portfolio: String,
+    pub path: String,
+    pub mapping: Option<HoldingsMapping>,
 }
 
 // 获取所有持仓
@@ -68,21 +367,66 @@ pub async fn list_positions(state: web::Data<Arc<Mutex<PositionState>>>) -> impl
 
 // 获取投资组合信息
 #[post("/query_portfolio")]
-pub async fn get_portfolio(state: web::Data<Arc<Mutex<PositionState>>>, req: web::Json<QueryPortfolioRequest>) -> impl Responder {
+pub async fn get_portfolio(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    stockdata_state: web::Data<Arc<Mutex<StockDataState>>>,
+    req: web::Json<QueryPortfolioRequest>,
+) -> impl Responder {
     info!("获取投资组合信息");
     
-    let state = state.lock().unwrap();
+    let quotes = stockdata_state.lock().unwrap().quotes();
+    let mut state = state.lock().unwrap();
 
-    match state.portfolios.iter().find(|p| p.name == req.name) {
-        Some(portfolio) => HttpResponse::Ok().json(QueryPortfolioResponse {
-            name: portfolio.name.clone(),
-            cash_balance: portfolio.cash_balance,
-            positions: portfolio.positions.values().cloned().collect(),
-        }),
+    match state.portfolios.iter_mut().find(|p| p.name == req.name) {
+        Some(portfolio) => {
+            // 查询前按最新报价估值
+            portfolio.mark_to_market(&quotes, Utc::now());
+            
+            HttpResponse::Ok().json(QueryPortfolioResponse {
+                name: portfolio.name.clone(),
+                cash_balance: portfolio.cash_balance(),
+                total_market_value: portfolio.total_market_value(),
+                total_profit_loss: portfolio.total_profit_loss(),
+                valued_at: portfolio.valued_at,
+                positions: portfolio.positions.values().map(PositionView::from).collect(),
+            })
+        },
         None => HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" }))
     }
 }
 
+// 按最新行情重新估值
+#[post("/revalue")]
+pub async fn revalue_portfolios(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    stockdata_state: web::Data<Arc<Mutex<StockDataState>>>,
+    req: web::Json<RevalueRequest>,
+) -> impl Responder {
+    info!("重新估值投资组合: {:?}", req.name);
+    
+    let quotes = stockdata_state.lock().unwrap().quotes();
+    if quotes.is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "暂无行情数据, 请先抓取数据或获取价格"
+        }));
+    }
+    
+    let mut state = state.lock().unwrap();
+    
+    let updated = match &req.name {
+        Some(name) => match state.portfolios.iter_mut().find(|p| &p.name == name) {
+            Some(portfolio) => portfolio.mark_to_market(&quotes, Utc::now()),
+            None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+        },
+        None => state.revalue(&quotes),
+    };
+    
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "updated": updated
+    }))
+}
+
 #[post("/add_portfolio")]
 pub async fn add_portfolio(state: web::Data<Arc<Mutex<PositionState>>>, req: web::Json<AddPortfolioRequest>) -> impl Responder {
     info!("添加投资组合: {}", req.name);
@@ -202,3 +546,831 @@ pub async fn remove_position(
         }
     }
 }
+
+// 获取已登记的公司行动
+#[get("/corporate_actions")]
+pub async fn list_corporate_actions(state: web::Data<Arc<Mutex<PositionState>>>) -> impl Responder {
+    info!("获取公司行动列表");
+
+    let state = state.lock().unwrap();
+    HttpResponse::Ok().json(&state.corporate_actions)
+}
+
+// 登记并应用公司行动
+#[post("/corporate_actions")]
+pub async fn add_corporate_actions(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<Vec<CorporateAction>>,
+) -> impl Responder {
+    info!("登记公司行动: {}条", req.len());
+
+    let mut state = state.lock().unwrap();
+    let results = state.apply_corporate_actions(req.into_inner());
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "results": results
+    }))
+}
+
+// 从文件加载并应用公司行动
+#[post("/corporate_actions/load")]
+pub async fn load_corporate_actions_file(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<LoadCorporateActionsRequest>,
+) -> impl Responder {
+    info!("从文件加载公司行动: {}", req.path);
+
+    let actions = match load_corporate_actions(&req.path) {
+        Ok(actions) => actions,
+        Err(e) => {
+            error!("加载公司行动失败: {}", e);
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("加载公司行动失败: {}", e)
+            }));
+        }
+    };
+
+    let mut state = state.lock().unwrap();
+    let results = state.apply_corporate_actions(actions);
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "results": results
+    }))
+}
+
+// 入金
+#[post("/cash/deposit")]
+pub async fn deposit_cash(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<CashRequest>,
+) -> impl Responder {
+    info!("入金: portfolio: {}, amount: {}", req.portfolio, req.amount);
+
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.portfolio) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    match portfolio.deposit(req.amount, req.note.clone()) {
+        Ok(entry) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "entry": entry,
+            "cash_balance": portfolio.cash_balance()
+        })),
+        Err(e) => {
+            error!("入金失败: {}", e);
+            HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))
+        }
+    }
+}
+
+// 出金
+#[post("/cash/withdraw")]
+pub async fn withdraw_cash(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<CashRequest>,
+) -> impl Responder {
+    info!("出金: portfolio: {}, amount: {}", req.portfolio, req.amount);
+
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.portfolio) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    match portfolio.withdraw(req.amount, req.note.clone()) {
+        Ok(entry) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "entry": entry,
+            "cash_balance": portfolio.cash_balance()
+        })),
+        Err(e) => {
+            error!("出金失败: {}", e);
+            HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))
+        }
+    }
+}
+
+// 获取现金流水
+#[get("/cash/ledger")]
+pub async fn get_cash_ledger(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    web::Query(params): web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let name = match params.get("name") {
+        Some(name) => name,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "缺少参数name" })),
+    };
+    info!("获取现金流水: {}", name);
+
+    let state = state.lock().unwrap();
+
+    match state.portfolios.iter().find(|p| &p.name == name) {
+        Some(portfolio) => HttpResponse::Ok().json(CashLedgerResponse {
+            name: portfolio.name.clone(),
+            cash_balance: portfolio.cash_balance(),
+            net_contributions: portfolio.cash_ledger.net_contributions(),
+            entries: portfolio.cash_ledger.entries.clone(),
+        }),
+        None => HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" }))
+    }
+}
+
+// 解析日期参数 (YYYY-MM-DD)
+fn parse_date_param(params: &HashMap<String, String>, key: &str) -> Result<Option<NaiveDate>, String> {
+    match params.get(key) {
+        Some(value) => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|e| format!("日期参数{}格式错误: {}", key, e)),
+        None => Ok(None),
+    }
+}
+
+// 获取投资组合绩效
+#[get("/performance")]
+pub async fn get_performance(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    web::Query(params): web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let name = match params.get("name") {
+        Some(name) => name,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "缺少参数name" })),
+    };
+    info!("获取投资组合绩效: {}", name);
+
+    let (from, to) = match (parse_date_param(&params, "from"), parse_date_param(&params, "to")) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(e), _) | (_, Err(e)) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    };
+    let risk_free_rate = params.get("risk_free_rate").and_then(|r| r.parse().ok()).unwrap_or(0.0);
+    let benchmark_code = params.get("benchmark").map(|b| b.as_str()).unwrap_or(BENCHMARK_CODES[0]);
+
+    let state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter().find(|p| &p.name == name) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    let benchmark = state.benchmarks.get(benchmark_code).map(|series| (benchmark_code, series));
+    match portfolio.performance(from, to, benchmark, risk_free_rate) {
+        Some(report) => HttpResponse::Ok().json(report),
+        None => HttpResponse::BadRequest().json(serde_json::json!({ "error": "区间内没有净值记录" })),
+    }
+}
+
+// 组合的策略绩效指标 (索提诺、卡玛、胜率、换手率等)
+#[get("/metrics")]
+pub async fn get_portfolio_metrics(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    web::Query(params): web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let name = match params.get("name") {
+        Some(name) => name,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "缺少参数name" })),
+    };
+    let risk_free_rate = params.get("risk_free_rate").and_then(|r| r.parse().ok()).unwrap_or(0.0);
+
+    let state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter().find(|p| &p.name == name) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    match portfolio_metrics(portfolio, risk_free_rate) {
+        Ok(metrics) => HttpResponse::Ok().json(metrics),
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    }
+}
+
+// 用历史快照补齐净值记录
+#[post("/nav/backfill")]
+pub async fn backfill_nav(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<BackfillNavRequest>,
+) -> impl Responder {
+    let dir = req.dir.clone().unwrap_or_else(|| SNAPSHOT_DIR.to_string());
+    info!("从快照补齐净值: {}", dir);
+
+    let history = match stockdata::io::load_history(&dir) {
+        Ok(history) => history,
+        Err(e) => {
+            error!("读取历史快照失败: {}", e);
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("读取历史快照失败: {}", e)
+            }));
+        }
+    };
+
+    let snapshots: Vec<(DateTime<Utc>, HashMap<String, f64>)> = history.into_iter()
+        .map(|(timestamp, stocks)| (timestamp, stocks.into_iter().map(|s| (s.code, s.price)).collect()))
+        .collect();
+
+    let mut state = state.lock().unwrap();
+
+    // 补齐基准价格
+    for (timestamp, prices) in &snapshots {
+        for code in BENCHMARK_CODES {
+            if let Some(price) = prices.get(code) {
+                state.benchmarks.entry(code.to_string()).or_default()
+                    .entry(trade_date(*timestamp))
+                    .or_insert(*price);
+            }
+        }
+    }
+
+    let mut filled = HashMap::new();
+    for portfolio in state.portfolios.iter_mut() {
+        if req.name.as_ref().is_none_or(|name| name == &portfolio.name) {
+            filled.insert(portfolio.name.clone(), portfolio.backfill_nav(&snapshots));
+        }
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "snapshots": snapshots.len(),
+        "filled": filled
+    }))
+}
+
+// 导入券商交割单
+#[post("/import")]
+pub async fn import_statement(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<ImportStatementRequest>,
+) -> impl Responder {
+    info!("导入交割单: portfolio: {}, path: {}", req.portfolio, req.path);
+
+    let content = match std::fs::read_to_string(&req.path) {
+        Ok(content) => content,
+        Err(e) => {
+            error!("读取交割单失败: {}", e);
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("读取交割单失败: {}", e)
+            }));
+        }
+    };
+
+    let mapping = req.mapping.clone().unwrap_or_default();
+    let statement = match parse_statement(&content, &mapping) {
+        Ok(statement) => statement,
+        Err(e) => {
+            error!("解析交割单失败: {}", e);
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("解析交割单失败: {}", e)
+            }));
+        }
+    };
+
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.portfolio) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    let report = portfolio.import_trades(&statement);
+    info!("导入完成: 新增{}笔, 银证转账{}笔, 重复{}笔, 失败{}笔", report.imported, report.transfers, report.duplicates, report.errors.len());
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": report.errors.is_empty(),
+        "report": report
+    }))
+}
+
+// 与券商持仓对账
+#[post("/reconcile")]
+pub async fn reconcile_portfolio(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<ReconcileRequest>,
+) -> impl Responder {
+    info!("持仓对账: portfolio: {}, path: {}", req.portfolio, req.path);
+
+    let content = match std::fs::read_to_string(&req.path) {
+        Ok(content) => content,
+        Err(e) => {
+            error!("读取持仓文件失败: {}", e);
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("读取持仓文件失败: {}", e)
+            }));
+        }
+    };
+
+    let mapping = req.mapping.clone().unwrap_or_default();
+    let holdings = match parse_holdings(&content, &mapping) {
+        Ok(holdings) => holdings,
+        Err(e) => {
+            error!("解析持仓文件失败: {}", e);
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("解析持仓文件失败: {}", e)
+            }));
+        }
+    };
+
+    let state = state.lock().unwrap();
+
+    match state.portfolios.iter().find(|p| p.name == req.portfolio) {
+        Some(portfolio) => HttpResponse::Ok().json(portfolio.reconcile(&holdings)),
+        None => HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    }
+}
+
+// 设置模拟交易账户
+#[post("/paper")]
+pub async fn set_paper_account(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<PaperAccountRequest>,
+) -> impl Responder {
+    info!("设置模拟交易: portfolio: {}, enabled: {}", req.name, req.enabled);
+
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.name) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    portfolio.paper = if req.enabled {
+        Some(req.config.clone().unwrap_or_default())
+    } else {
+        None
+    };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "paper": portfolio.paper
+    }))
+}
+
+// 查询风控状态
+#[get("/risk")]
+pub async fn get_risk_status(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    web::Query(params): web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let name = match params.get("name") {
+        Some(name) => name,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "缺少参数name" })),
+    };
+
+    let state = state.lock().unwrap();
+
+    match state.portfolios.iter().find(|p| &p.name == name) {
+        Some(portfolio) => HttpResponse::Ok().json(RiskStatusResponse {
+            name: portfolio.name.clone(),
+            limits: portfolio.risk_limits.clone(),
+            drawdown: portfolio.current_drawdown(),
+            daily_turnover: portfolio.daily_turnover(),
+            breaches: portfolio.risk_breaches(),
+        }),
+        None => HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" }))
+    }
+}
+
+// 设置风控限制
+#[post("/risk/limits")]
+pub async fn set_risk_limits(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<RiskLimitsRequest>,
+) -> impl Responder {
+    info!("设置风控限制: portfolio: {}, limits: {:?}", req.name, req.limits);
+
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.name) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    portfolio.risk_limits = req.limits.clone();
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "breaches": portfolio.risk_breaches()
+    }))
+}
+
+// 查询行业、市值和板块暴露
+#[get("/exposure")]
+pub async fn get_exposure(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    stockdata_state: web::Data<Arc<Mutex<StockDataState>>>,
+    web::Query(params): web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let name = match params.get("name") {
+        Some(name) => name,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "缺少参数name" })),
+    };
+    let top_n = match params.get("top").map(|t| t.parse::<usize>()) {
+        Some(Ok(top_n)) => top_n,
+        Some(Err(_)) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "参数top格式错误" })),
+        None => DEFAULT_TOP_N,
+    };
+
+    let quotes = stockdata_state.lock().unwrap().quotes();
+    // 与已加载行情的股票池的行业市值分布比较
+    let universe = universe_sector_weights(&quotes);
+    let mut state = state.lock().unwrap();
+
+    match state.portfolios.iter_mut().find(|p| &p.name == name) {
+        Some(portfolio) => {
+            portfolio.mark_to_market(&quotes, Utc::now());
+            let universe = if universe.is_empty() { None } else { Some(&universe) };
+            HttpResponse::Ok().json(portfolio.exposure(&quotes, universe, top_n))
+        },
+        None => HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" }))
+    }
+}
+
+// 按目标权重调仓
+#[post("/rebalance")]
+pub async fn rebalance_portfolio(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    stockdata_state: web::Data<Arc<Mutex<StockDataState>>>,
+    req: web::Json<RebalanceRequest>,
+) -> impl Responder {
+    info!("调仓: portfolio: {}, execute: {}", req.portfolio, req.execute);
+
+    let quotes = stockdata_state.lock().unwrap().quotes();
+    let prices: HashMap<String, f64> = quotes.values().map(|q| (q.code.clone(), q.price)).collect();
+    let mut state = state.lock().unwrap();
+
+    let targets = match (&req.targets, &req.model) {
+        (Some(targets), _) => targets.clone(),
+        (None, Some(model)) => match state.portfolios.iter().find(|p| &p.name == model) {
+            Some(model) => model.weights(),
+            None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "模型组合不存在" })),
+        },
+        (None, None) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "缺少目标权重或模型组合" })),
+    };
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.portfolio) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    let config = req.config.clone().unwrap_or_default();
+    let plan = match portfolio.plan_rebalance(&targets, &prices, &config) {
+        Ok(plan) => plan,
+        Err(e) => {
+            error!("生成调仓计划失败: {}", e);
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": e }));
+        }
+    };
+
+    let executions = if req.execute {
+        portfolio.execute_rebalance(&plan, req.use_orders)
+    } else {
+        Vec::new()
+    };
+
+    HttpResponse::Ok().json(RebalanceResponse { plan, executions })
+}
+
+// 年度已实现收益和收入报告, format=csv时返回CSV
+#[get("/report/annual")]
+pub async fn get_annual_report(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    web::Query(params): web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let name = match params.get("name") {
+        Some(name) => name,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "缺少参数name" })),
+    };
+    let year = match params.get("year").map(|y| y.parse::<i32>()) {
+        Some(Ok(year)) => year,
+        _ => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "缺少参数year或格式错误" })),
+    };
+    info!("生成年度报告: portfolio: {}, year: {}", name, year);
+
+    let state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter().find(|p| &p.name == name) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    let report = match portfolio.annual_report(year) {
+        Ok(report) => report,
+        Err(e) => {
+            error!("生成年度报告失败: {}", e);
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": e }));
+        }
+    };
+
+    if params.get("format").map(|f| f.as_str()) == Some("csv") {
+        match report.to_csv() {
+            Ok(csv) => HttpResponse::Ok().content_type("text/csv; charset=utf-8").body(csv),
+            Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "error": e })),
+        }
+    } else {
+        HttpResponse::Ok().json(report)
+    }
+}
+
+// 获取组合事件记录
+#[get("/events")]
+pub async fn get_events(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    web::Query(params): web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let name = match params.get("name") {
+        Some(name) => name,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "缺少参数name" })),
+    };
+
+    let state = state.lock().unwrap();
+
+    match state.portfolios.iter().find(|p| &p.name == name) {
+        Some(portfolio) => HttpResponse::Ok().json(&portfolio.events),
+        None => HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" }))
+    }
+}
+
+// 查看组合在过去某一时间的状态
+#[get("/as_of")]
+pub async fn get_portfolio_as_of(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    web::Query(params): web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let (name, at) = match (params.get("name"), params.get("at")) {
+        (Some(name), Some(at)) => (name, at),
+        _ => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "缺少参数name或at" })),
+    };
+    let at = match DateTime::parse_from_rfc3339(at) {
+        Ok(at) => at.with_timezone(&Utc),
+        Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": format!("时间格式错误: {}", e) })),
+    };
+
+    let state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter().find(|p| &p.name == name) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    match portfolio.as_of(at) {
+        Ok(past) => HttpResponse::Ok().json(QueryPortfolioResponse {
+            name: past.name.clone(),
+            cash_balance: past.cash_balance(),
+            total_market_value: past.total_market_value(),
+            total_profit_loss: past.total_profit_loss(),
+            valued_at: past.valued_at,
+            positions: past.positions.values().map(PositionView::from).collect(),
+        }),
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    }
+}
+
+// 修改交易
+#[post("/transaction/amend")]
+pub async fn amend_transaction(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<AmendTransactionRequest>,
+) -> impl Responder {
+    info!("修改交易: portfolio: {}, transaction: {}, actor: {:?}", req.portfolio, req.transaction_id, req.actor);
+
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.portfolio) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    let mut transaction = match portfolio.find_transaction(&req.transaction_id) {
+        Some(transaction) => transaction,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "交易不存在或已撤销" })),
+    };
+    transaction.amount = req.amount.unwrap_or(transaction.amount);
+    transaction.price = req.price.unwrap_or(transaction.price);
+    transaction.timestamp = req.timestamp.unwrap_or(transaction.timestamp);
+    transaction.fee = req.fee.or(transaction.fee);
+    transaction.tax = req.tax.or(transaction.tax);
+
+    match portfolio.amend_transaction(&req.transaction_id, transaction, req.actor.clone(), req.reason.clone()) {
+        Ok(event) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "event": event
+        })),
+        Err(e) => {
+            error!("修改交易失败: {}", e);
+            HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))
+        }
+    }
+}
+
+// 撤销交易
+#[post("/transaction/cancel")]
+pub async fn cancel_transaction(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<CancelTransactionRequest>,
+) -> impl Responder {
+    info!("撤销交易: portfolio: {}, transaction: {}, actor: {:?}", req.portfolio, req.transaction_id, req.actor);
+
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.portfolio) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    match portfolio.cancel_transaction(&req.transaction_id, req.actor.clone(), req.reason.clone()) {
+        Ok(event) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "event": event
+        })),
+        Err(e) => {
+            error!("撤销交易失败: {}", e);
+            HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))
+        }
+    }
+}
+
+// 撤回最近一次操作
+#[post("/undo")]
+pub async fn undo_last_event(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<UndoRequest>,
+) -> impl Responder {
+    info!("撤回操作: portfolio: {}, actor: {:?}", req.portfolio, req.actor);
+
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.portfolio) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    match portfolio.undo_last(req.actor.clone()) {
+        Ok(event) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "event": event
+        })),
+        Err(e) => {
+            error!("撤回操作失败: {}", e);
+            HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))
+        }
+    }
+}
+
+// 开通融资融券
+#[post("/margin/open")]
+pub async fn open_margin_account(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<MarginAccountRequest>,
+) -> impl Responder {
+    info!("开通融资融券: portfolio: {}", req.name);
+
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.name) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    match portfolio.open_margin(req.config.clone().unwrap_or_default()) {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "margin": portfolio.margin
+        })),
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    }
+}
+
+// 查询信用账户状态
+#[get("/margin")]
+pub async fn get_margin_status(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    stockdata_state: web::Data<Arc<Mutex<StockDataState>>>,
+    web::Query(params): web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let name = match params.get("name") {
+        Some(name) => name,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "缺少参数name" })),
+    };
+
+    let quotes = stockdata_state.lock().unwrap().quotes();
+    let mut state = state.lock().unwrap();
+
+    match state.portfolios.iter_mut().find(|p| &p.name == name) {
+        Some(portfolio) => {
+            portfolio.mark_to_market(&quotes, Utc::now());
+            match portfolio.margin_status() {
+                Ok(status) => HttpResponse::Ok().json(status),
+                Err(e) => HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+            }
+        },
+        None => HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" }))
+    }
+}
+
+// 融资买入
+#[post("/margin/buy")]
+pub async fn margin_buy(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<MarginTradeRequest>,
+) -> impl Responder {
+    info!("融资买入: portfolio: {}, code: {}, quantity: {}, price: {}", req.portfolio, req.code, req.quantity, req.price);
+
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.portfolio) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    match portfolio.margin_buy(&req.code, req.quantity, req.price) {
+        Ok(transaction) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "transaction": transaction
+        })),
+        Err(e) => {
+            error!("融资买入失败: {}", e);
+            HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))
+        }
+    }
+}
+
+// 融券卖出
+#[post("/margin/short")]
+pub async fn short_sell(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<MarginTradeRequest>,
+) -> impl Responder {
+    info!("融券卖出: portfolio: {}, code: {}, quantity: {}, price: {}", req.portfolio, req.code, req.quantity, req.price);
+
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.portfolio) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    match portfolio.short_sell(&req.code, req.quantity, req.price) {
+        Ok(short) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "short": short
+        })),
+        Err(e) => {
+            error!("融券卖出失败: {}", e);
+            HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))
+        }
+    }
+}
+
+// 买券还券
+#[post("/margin/cover")]
+pub async fn cover_short(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<MarginTradeRequest>,
+) -> impl Responder {
+    info!("买券还券: portfolio: {}, code: {}, quantity: {}, price: {}", req.portfolio, req.code, req.quantity, req.price);
+
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.portfolio) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    match portfolio.cover_short(&req.code, req.quantity, req.price) {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({ "success": true })),
+        Err(e) => {
+            error!("买券还券失败: {}", e);
+            HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))
+        }
+    }
+}
+
+// 直接还款
+#[post("/margin/repay")]
+pub async fn repay_financing(
+    state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<MarginRepayRequest>,
+) -> impl Responder {
+    info!("融资还款: portfolio: {}, amount: {}", req.portfolio, req.amount);
+
+    let mut state = state.lock().unwrap();
+
+    let portfolio = match state.portfolios.iter_mut().find(|p| p.name == req.portfolio) {
+        Some(portfolio) => portfolio,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "投资组合不存在" })),
+    };
+
+    match portfolio.repay_financing(req.amount) {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "financing_debt": portfolio.margin.as_ref().map(|m| m.financing_debt)
+        })),
+        Err(e) => {
+            error!("融资还款失败: {}", e);
+            HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))
+        }
+    }
+}
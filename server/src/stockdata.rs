@@ -5,6 +5,8 @@ use log::{info, error};
 use std::sync::{Arc, Mutex};
 use std::process::Child;
 use std::collections::HashMap;
+use position::valuation::{Quote, quotes_from_stock_data, overlay_prices};
+use crate::position::PositionState;
 
 // 定义模块状态
 pub struct StockDataState {
@@ -29,6 +31,22 @@ impl StockDataState {
             fetched_price_last_fetch: None,
         }
     }
+
+    // 生成估值用的最新报价, 价格缓存比抓取数据新时以缓存价格为准
+    pub fn quotes(&self) -> HashMap<String, Quote> {
+        let mut quotes = quotes_from_stock_data(&self.fetched_data);
+
+        let price_is_newer = match (self.fetched_price_last_fetch, self.fetch_data_last_fetch) {
+            (Some(price_time), Some(data_time)) => price_time > data_time,
+            (Some(_), None) => true,
+            _ => false,
+        };
+        if price_is_newer || quotes.is_empty() {
+            overlay_prices(&mut quotes, &self.fetched_price);
+        }
+
+        quotes
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -131,7 +149,11 @@ pub async fn close_webdriver(state: web::Data<Arc<Mutex<StockDataState>>>) -> im
 
 // 抓取股票数据
 #[post("/fetch")]
-pub async fn fetch_data(state: web::Data<Arc<Mutex<StockDataState>>>, req: web::Json<FetchRequest>) -> impl Responder {
+pub async fn fetch_data(
+    state: web::Data<Arc<Mutex<StockDataState>>>,
+    position_state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<FetchRequest>,
+) -> impl Responder {
     // 检查是否可以开始抓取
     let client_option = {
         let mut state = state.lock().unwrap();
@@ -156,6 +178,7 @@ pub async fn fetch_data(state: web::Data<Arc<Mutex<StockDataState>>>, req: web::
     
     let save_to_file = req.save_to_file.unwrap_or(false);
     let state_clone = Arc::clone(&state);
+    let position_state = Arc::clone(&position_state);
     
     // 启动异步任务执行数据抓取
     actix_web::rt::spawn(async move {
@@ -169,9 +192,16 @@ pub async fn fetch_data(state: web::Data<Arc<Mutex<StockDataState>>>, req: web::
                 info!("成功获取{}支股票的数据", stocks.len());
                 
                 // 更新状态
-                let mut state = state_clone.lock().unwrap();
-                state.fetched_data = stocks;
-                state.is_fetching = false;
+                let quotes = {
+                    let mut state = state_clone.lock().unwrap();
+                    state.fetched_data = stocks;
+                    state.is_fetching = false;
+                    state.quotes()
+                };
+                
+                // 按最新数据重新估值持仓
+                let updated = position_state.lock().unwrap().revalue(&quotes);
+                info!("已更新{}个持仓的估值", updated);
             },
             Err(e) => {
                 error!("{}", e);
@@ -206,6 +236,7 @@ pub async fn get_stockdata_status(state: web::Data<Arc<Mutex<StockDataState>>>)
 
 #[get("/price")]
 pub async fn get_price(state: web::Data<Arc<Mutex<StockDataState>>>, 
+    position_state: web::Data<Arc<Mutex<PositionState>>>,
     web::Query(params): web::Query<HashMap<String, String>>) -> impl Responder {
     info!("获取股票价格");
     
@@ -245,6 +276,16 @@ pub async fn get_price(state: web::Data<Arc<Mutex<StockDataState>>>,
         };
 
         state.fetched_price_last_fetch = Some(chrono::Utc::now());
+        
+        // 按最新价格重新估值持仓
+        // 持仓模块下单时会持有锁并请求本接口, 这里不能阻塞等待
+        match position_state.try_lock() {
+            Ok(mut position_state) => {
+                let updated = position_state.revalue(&state.quotes());
+                info!("已更新{}个持仓的估值", updated);
+            },
+            Err(_) => info!("持仓模块繁忙, 跳过本次估值"),
+        }
     } 
 
     
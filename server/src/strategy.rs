@@ -1,16 +1,55 @@
-use actix_web::{web, HttpResponse, Responder, get, post};
+use actix_web::{web, HttpResponse, Responder, get, post, put};
 use serde::{Deserialize, Serialize};
-use log::info;
+use log::{info, error};
 use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
-use strategy::models::{StrategyParams, StrategyType, BacktestResult};
+use chrono::{DateTime, NaiveDate, Utc};
+use strategy::models::{StrategyParams, StrategyType, BacktestResult, MarketData};
+use strategy::backtest::{run_backtest, BacktestConfig};
+use strategy::strategy::create_strategy;
+use strategy::schema::param_schema;
+use strategy::walkforward::{walk_forward, WalkForwardConfig};
+use strategy::pairs::find_pairs;
+use strategy::optimizer::{candidates, run_optimization, Objective, OptimizationConfig, OptimizationRun, ParamRange, SearchMethod};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use strategy::context::StrategyRunner;
+use strategy::indicators::{cross_check, series, Atr, Bollinger, Cci, Ema, Macd, Obv, Rsi, Sma, Stochastic};
+use position::models::Portfolio;
+use position::money::Money;
+use crate::position::PositionState;
+use crate::stockdata::StockDataState;
+
+// 默认历史快照目录
+const SNAPSHOT_DIR: &str = "output";
 
 // 策略管理状态
 pub struct StrategyState {
     pub strategies: HashMap<String, StrategyParams>,
     pub backtest_results: HashMap<String, BacktestResult>,
+    // 正在实盘运行的策略
+    pub runners: HashMap<String, StrategyRunner>,
+    // 参数优化任务
+    pub optimizations: HashMap<String, OptimizationJob>,
+}
+
+// 后台运行的参数优化任务
+pub struct OptimizationJob {
+    pub id: String,
+    pub strategy: String,
+    pub objective: Objective,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub total: usize,
+    pub progress: Arc<AtomicUsize>,
+    pub results: Vec<OptimizationRun>,
+    // 任务失败原因 (如读取历史快照失败)
+    pub error: Option<String>,
 }
 
+// 保留的已完成优化任务数量, 超出时移除最早完成的任务
+const MAX_FINISHED_OPTIMIZATIONS: usize = 20;
+
 impl StrategyState {
     pub fn new() -> Self {
         let mut strategies = HashMap::new();
@@ -22,9 +61,29 @@ impl StrategyState {
         let mean_reversion = StrategyParams::new("均值回归策略".to_string(), StrategyType::MeanReversion);
         strategies.insert(mean_reversion.name.clone(), mean_reversion);
         
+        let factor_model = StrategyParams::new("多因子策略".to_string(), StrategyType::FactorModel);
+        strategies.insert(factor_model.name.clone(), factor_model);
+        
         Self {
             strategies,
             backtest_results: HashMap::new(),
+            runners: HashMap::new(),
+            optimizations: HashMap::new(),
+        }
+    }
+
+    // 移除较早完成的优化任务, 避免任务记录无限增长
+    fn evict_finished_optimizations(&mut self) {
+        let mut finished: Vec<(DateTime<Utc>, String)> = self.optimizations.values()
+            .filter_map(|job| job.finished_at.map(|at| (at, job.id.clone())))
+            .collect();
+        if finished.len() <= MAX_FINISHED_OPTIMIZATIONS {
+            return;
+        }
+        finished.sort();
+        let excess = finished.len() - MAX_FINISHED_OPTIMIZATIONS;
+        for (_, id) in finished.into_iter().take(excess) {
+            self.optimizations.remove(&id);
         }
     }
 }
@@ -33,6 +92,8 @@ impl StrategyState {
 #[derive(Deserialize, Serialize)]
 pub struct StrategyRequest {
     pub name: String,
+    // 信号按该组合的持仓和资金确定数量
+    pub portfolio: Option<String>,
 }
 
 // 回测请求
@@ -42,6 +103,40 @@ pub struct BacktestRequest {
     pub start_date: Option<String>,
     pub end_date: Option<String>,
     pub initial_capital: Option<f64>,
+    // 历史快照目录
+    pub dir: Option<String>,
+}
+
+// 参数优化请求
+#[derive(Deserialize, Serialize)]
+pub struct OptimizeRequest {
+    pub name: String,
+    pub ranges: BTreeMap<String, ParamRange>,
+    pub method: Option<SearchMethod>,
+    pub objective: Option<Objective>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub initial_capital: Option<f64>,
+    pub threads: Option<usize>,
+    // 历史快照目录
+    pub dir: Option<String>,
+}
+
+// 滚动前向分析请求
+#[derive(Deserialize, Serialize)]
+pub struct WalkForwardRequest {
+    #[serde(flatten)]
+    pub optimize: OptimizeRequest,
+    pub in_sample_bars: usize,
+    pub out_of_sample_bars: usize,
+    #[serde(default)]
+    pub anchored: bool,
+}
+
+// 修改策略参数请求
+#[derive(Deserialize, Serialize)]
+pub struct UpdateParamsRequest {
+    pub params: HashMap<String, serde_json::Value>,
 }
 
 // 获取所有策略
@@ -75,27 +170,196 @@ pub async fn get_strategy(
     }
 }
 
-// 运行策略
+// 获取策略参数及参数定义
+#[get("/{name}/params")]
+pub async fn get_strategy_params(
+    state: web::Data<Arc<Mutex<StrategyState>>>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let name = path.into_inner();
+    info!("获取策略参数: {}", name);
+
+    match state.lock().unwrap().strategies.get(&name) {
+        Some(params) => HttpResponse::Ok().json(serde_json::json!({
+            "name": name,
+            "strategy_type": params.strategy_type,
+            "params": params.params,
+            "schema": param_schema(params.strategy_type),
+        })),
+        None => HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("未找到策略: {}", name)
+        })),
+    }
+}
+
+// 修改策略参数: 校验通过并能创建新实例后才生效, 运行中的策略用新实例替换并保留历史行情
+#[put("/{name}/params")]
+pub async fn update_strategy_params(
+    state: web::Data<Arc<Mutex<StrategyState>>>,
+    path: web::Path<String>,
+    req: web::Json<UpdateParamsRequest>,
+) -> impl Responder {
+    let name = path.into_inner();
+    info!("修改策略参数: {}", name);
+
+    let mut state = state.lock().unwrap();
+    let mut params = match state.strategies.get(&name) {
+        Some(params) => params.clone(),
+        None => return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("未找到策略: {}", name)
+        })),
+    };
+    if let Err(e) = params.update_params(&req.params) {
+        return HttpResponse::BadRequest().json(serde_json::json!({ "error": e }));
+    }
+    let strategy = match create_strategy(&params) {
+        Ok(strategy) => strategy,
+        Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    };
+
+    let running = match state.runners.get_mut(&name) {
+        Some(runner) => {
+            runner.replace_strategy(strategy, params.sizing.clone());
+            true
+        },
+        None => false,
+    };
+    state.strategies.insert(name.clone(), params.clone());
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": if running {
+            format!("策略 {} 参数已更新, 运行中的策略已重新创建", name)
+        } else {
+            format!("策略 {} 参数已更新", name)
+        },
+        "strategy": params,
+    }))
+}
+
+// 运行策略: 首次运行时用历史快照预热, 之后每次用最新抓取的行情驱动策略并返回信号
 #[post("/run")]
 pub async fn run_strategy(
     state: web::Data<Arc<Mutex<StrategyState>>>,
+    stockdata_state: web::Data<Arc<Mutex<StockDataState>>>,
+    position_state: web::Data<Arc<Mutex<PositionState>>>,
     req: web::Json<StrategyRequest>,
 ) -> impl Responder {
     info!("运行策略: {}", req.name);
-    
-    let state = state.lock().unwrap();
-    
-    if let Some(strategy) = state.strategies.get(&req.name) {
-        // 这里应该实际运行策略, 但目前只返回成功
-        HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "message": format!("策略 {} 已启动", req.name),
-            "strategy": strategy
-        }))
-    } else {
-        HttpResponse::BadRequest().json(serde_json::json!({
+
+    let params = match state.lock().unwrap().strategies.get(&req.name) {
+        Some(params) => params.clone(),
+        None => return HttpResponse::BadRequest().json(serde_json::json!({
             "error": format!("未找到策略: {}", req.name)
-        }))
+        })),
+    };
+    if !params.enabled {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("策略 {} 未启用", req.name)
+        }));
+    }
+
+    let data = {
+        let stockdata = stockdata_state.lock().unwrap();
+        match stockdata.fetch_data_last_fetch {
+            Some(timestamp) if !stockdata.fetched_data.is_empty() => MarketData::from_stock_data(timestamp, &stockdata.fetched_data),
+            _ => return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "暂无行情数据, 请先抓取数据"
+            })),
+        }
+    };
+
+    // 未指定组合时信号不确定数量
+    let portfolio = match &req.portfolio {
+        Some(name) => match position_state.lock().unwrap().portfolios.iter().find(|p| &p.name == name) {
+            Some(portfolio) => Some(portfolio.clone()),
+            None => return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("未找到投资组合: {}", name)
+            })),
+        },
+        None => None,
+    };
+
+    // 首次运行: 创建策略实例并载入历史快照 (在锁外进行)
+    let is_running = state.lock().unwrap().runners.contains_key(&req.name);
+    if !is_running {
+        let strategy = match create_strategy(&params) {
+            Ok(strategy) => strategy,
+            Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+        };
+        let mut runner = StrategyRunner::new(strategy, params.sizing.clone());
+        match stockdata::io::load_history(SNAPSHOT_DIR) {
+            Ok(history) => {
+                let history: Vec<MarketData> = history.iter()
+                    .filter(|(timestamp, _)| *timestamp < data.timestamp)
+                    .map(|(timestamp, stocks)| MarketData::from_stock_data(*timestamp, stocks))
+                    .collect();
+                runner.warm_up(&history);
+            },
+            Err(e) => error!("读取历史快照失败, 策略将从空历史开始: {}", e),
+        }
+        state.lock().unwrap().runners.entry(req.name.clone()).or_insert(runner);
+    }
+
+    let mut state = state.lock().unwrap();
+    let runner = match state.runners.get_mut(&req.name) {
+        Some(runner) => runner,
+        None => return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("策略 {} 启动失败", req.name)
+        })),
+    };
+    let updated = runner.history().last_timestamp().is_none_or(|last| data.timestamp > last);
+    let signals = match &portfolio {
+        Some(portfolio) => runner.on_bar(&data, portfolio),
+        None => runner.on_bar_unsized(&data),
+    };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": if updated {
+            format!("策略 {} 已运行, 生成{}个信号", req.name, signals.len())
+        } else {
+            "行情未更新, 没有新的信号".to_string()
+        },
+        "timestamp": data.timestamp,
+        "signals": signals,
+    }))
+}
+
+// 停止策略
+#[post("/stop")]
+pub async fn stop_strategy(
+    state: web::Data<Arc<Mutex<StrategyState>>>,
+    position_state: web::Data<Arc<Mutex<PositionState>>>,
+    req: web::Json<StrategyRequest>,
+) -> impl Responder {
+    info!("停止策略: {}", req.name);
+
+    let portfolio = req.portfolio.as_ref()
+        .and_then(|name| position_state.lock().unwrap().portfolios.iter().find(|p| &p.name == name).cloned())
+        .unwrap_or_else(|| Portfolio::new(req.name.clone(), Money::ZERO));
+
+    match state.lock().unwrap().runners.remove(&req.name) {
+        Some(mut runner) => {
+            runner.finish(&portfolio);
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "message": format!("策略 {} 已停止", req.name)
+            }))
+        },
+        None => HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("策略 {} 未在运行", req.name)
+        })),
+    }
+}
+
+// 解析日期参数 (YYYY-MM-DD)
+fn parse_date(value: &Option<String>, field: &str) -> Result<Option<NaiveDate>, String> {
+    match value {
+        Some(value) => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| format!("{}格式错误, 应为YYYY-MM-DD", field)),
+        None => Ok(None),
     }
 }
 
@@ -106,45 +370,46 @@ pub async fn backtest_strategy(
     req: web::Json<BacktestRequest>,
 ) -> impl Responder {
     info!("回测策略: {}", req.name);
-    
-    let mut state = state.lock().unwrap();
-    
-    if let Some(strategy) = state.strategies.get(&req.name) {
-        // 克隆策略名称, 避免同时借用
-        let strategy_name = strategy.name.clone();
-        let strategy_type = strategy.strategy_type;
-        
-        // 这里应该实际执行回测, 但目前只返回模拟结果
-        let result = BacktestResult {
-            strategy_name: strategy_name.clone(),
-            strategy_type,
-            start_date: chrono::Utc::now() - chrono::Duration::days(30),
-            end_date: chrono::Utc::now(),
-            initial_capital: req.initial_capital.unwrap_or(100000.0),
-            final_capital: 110000.0,
-            total_return: 0.1,
-            annualized_return: 0.12,
-            sharpe_ratio: 1.5,
-            max_drawdown: 0.05,
-            win_rate: 0.6,
-            profit_factor: 1.8,
-            total_trades: 20,
-            winning_trades: 12,
-            losing_trades: 8,
-            avg_profit: 1000.0,
-            avg_loss: -500.0,
-            equity_curve: vec![],
-            trades: vec![],
-        };
-        
-        // 保存回测结果
-        state.backtest_results.insert(strategy_name, result.clone());
-        
-        HttpResponse::Ok().json(result)
-    } else {
-        HttpResponse::BadRequest().json(serde_json::json!({
+
+    let params = match state.lock().unwrap().strategies.get(&req.name) {
+        Some(params) => params.clone(),
+        None => return HttpResponse::BadRequest().json(serde_json::json!({
             "error": format!("未找到策略: {}", req.name)
-        }))
+        })),
+    };
+
+    let (start_date, end_date) = match (parse_date(&req.start_date, "start_date"), parse_date(&req.end_date, "end_date")) {
+        (Ok(start), Ok(end)) => (start, end),
+        (Err(e), _) | (_, Err(e)) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    };
+    let config = BacktestConfig {
+        initial_capital: req.initial_capital.unwrap_or(100000.0),
+        start_date,
+        end_date,
+        ..BacktestConfig::default()
+    };
+
+    // 读取历史快照和回测耗时较长, 在阻塞线程池中运行
+    let dir = req.dir.clone();
+    let result = web::block(move || {
+        let strategy = create_strategy(&params)?;
+        let history: Vec<MarketData> = load_snapshots(dir.as_ref())?.iter()
+            .map(|(timestamp, stocks)| MarketData::from_stock_data(*timestamp, stocks))
+            .collect();
+        run_backtest(strategy, &params, &history, &config)
+    }).await;
+
+    match result {
+        Ok(Ok(result)) => {
+            // 保存回测结果
+            state.lock().unwrap().backtest_results.insert(result.strategy_name.clone(), result.clone());
+            HttpResponse::Ok().json(result)
+        },
+        Ok(Err(e)) => {
+            error!("回测失败: {}", e);
+            HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))
+        },
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "error": e.to_string() })),
     }
 }
 
@@ -167,3 +432,319 @@ pub async fn get_backtest_result(
         }))
     }
 }
+
+// 按时间保存的历史快照
+type Snapshots = Vec<(DateTime<Utc>, Vec<stockdata::models::StockData>)>;
+
+// 读取历史快照
+fn load_snapshots(dir: Option<&String>) -> Result<Snapshots, String> {
+    let dir = dir.map(|d| d.as_str()).unwrap_or(SNAPSHOT_DIR);
+    stockdata::io::load_history(dir).map_err(|e| format!("读取历史快照失败: {}", e))
+}
+
+// 在历史快照上计算单只股票的指标序列
+#[get("/indicators")]
+pub async fn get_indicators(query: web::Query<HashMap<String, String>>) -> impl Responder {
+    let code = match query.get("code") {
+        Some(code) => code.clone(),
+        None => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "缺少股票代码" })),
+    };
+    info!("计算技术指标: {}", code);
+
+    // 读取历史快照和计算指标耗时较长, 在阻塞线程池中运行
+    let dir = query.get("dir").cloned();
+    let series_code = code.clone();
+    let result = web::block(move || {
+        let mut history = load_snapshots(dir.as_ref())?;
+        history.sort_by_key(|(timestamp, _)| *timestamp);
+        let (timestamps, bars): (Vec<DateTime<Utc>>, Vec<_>) = history.iter()
+            .filter_map(|(timestamp, stocks)| {
+                stocks.iter().find(|s| s.code == series_code && s.price > 0.0)
+                    .map(|s| (*timestamp, strategy::models::StockSnapshot::from_stock_data(s)))
+            })
+            .unzip();
+        if bars.is_empty() {
+            return Err(format!("历史快照中没有股票: {}", series_code));
+        }
+
+        let sma = series(&Sma::new(20), &bars);
+        let ema = series(&Ema::new(20), &bars);
+        let rsi = series(&Rsi::new(14), &bars);
+        let macd = series(&Macd::default(), &bars);
+        let bollinger = series(&Bollinger::new(20, 2.0), &bars);
+        let atr = series(&Atr::new(14), &bars);
+        let stochastic = series(&Stochastic::default(), &bars);
+        let cci = series(&Cci::new(20), &bars);
+        let obv = series(&Obv::default(), &bars);
+
+        Ok((0..bars.len()).map(|i| serde_json::json!({
+            "timestamp": timestamps[i],
+            "close": bars[i].price,
+            "sma_20": sma[i],
+            "ema_20": ema[i],
+            "rsi_14": rsi[i],
+            "macd": macd[i],
+            "bollinger": bollinger[i],
+            "atr_14": atr[i],
+            "stochastic": stochastic[i],
+            "cci_20": cci[i],
+            "obv": obv[i],
+        })).collect::<Vec<serde_json::Value>>())
+    }).await;
+
+    match result {
+        Ok(Ok(rows)) => HttpResponse::Ok().json(serde_json::json!({ "code": code, "series": rows })),
+        Ok(Err(e)) => HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+// 用历史快照计算的指标与最新抓取的指标对比
+#[get("/indicators/check")]
+pub async fn check_indicators(query: web::Query<HashMap<String, String>>) -> impl Responder {
+    info!("核对技术指标");
+
+    let tolerance = match query.get("tolerance").map(|t| t.parse::<f64>()) {
+        Some(Ok(tolerance)) => tolerance,
+        Some(Err(_)) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "tolerance格式错误" })),
+        None => 5.0,
+    };
+
+    // 读取历史快照和重算指标耗时较长, 在阻塞线程池中运行
+    let dir = query.get("dir").cloned();
+    let code = query.get("code").cloned();
+    let result = web::block(move || {
+        let history = load_snapshots(dir.as_ref())?;
+        let mut checks = cross_check(&history);
+        if let Some(code) = code {
+            checks.retain(|c| c.code == code);
+        }
+        Ok::<_, String>(checks)
+    }).await;
+
+    match result {
+        Ok(Ok(checks)) => {
+            let mismatches: Vec<_> = checks.iter().filter(|c| c.difference.abs() > tolerance).collect();
+            HttpResponse::Ok().json(serde_json::json!({
+                "tolerance": tolerance,
+                "checked": checks.len(),
+                "mismatched": mismatches.len(),
+                "mismatches": mismatches,
+                "checks": checks,
+            }))
+        },
+        Ok(Err(e)) => HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+// 由请求生成优化设置
+fn optimization_config(req: &OptimizeRequest) -> Result<OptimizationConfig, String> {
+    Ok(OptimizationConfig {
+        ranges: req.ranges.clone(),
+        method: req.method.unwrap_or(SearchMethod::Grid),
+        objective: req.objective.unwrap_or_default(),
+        backtest: BacktestConfig {
+            initial_capital: req.initial_capital.unwrap_or(100000.0),
+            start_date: parse_date(&req.start_date, "start_date")?,
+            end_date: parse_date(&req.end_date, "end_date")?,
+            ..BacktestConfig::default()
+        },
+        threads: req.threads,
+    })
+}
+
+// 启动参数优化: 在后台线程并行回测全部参数组合, 立即返回任务ID
+#[post("/optimize")]
+pub async fn start_optimization(
+    state: web::Data<Arc<Mutex<StrategyState>>>,
+    req: web::Json<OptimizeRequest>,
+) -> impl Responder {
+    info!("参数优化: {}", req.name);
+
+    let params = match state.lock().unwrap().strategies.get(&req.name) {
+        Some(params) => params.clone(),
+        None => return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("未找到策略: {}", req.name)
+        })),
+    };
+    let config = match optimization_config(&req) {
+        Ok(config) => config,
+        Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    };
+
+    let candidates = match candidates(&params, &config) {
+        Ok(candidates) => candidates,
+        Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    };
+    let total = candidates.len();
+    let started_at = Utc::now();
+    let id = format!("{}-{}", params.name, started_at.timestamp_millis());
+    let progress = Arc::new(AtomicUsize::new(0));
+    let mut guard = state.lock().unwrap();
+    guard.evict_finished_optimizations();
+    guard.optimizations.insert(id.clone(), OptimizationJob {
+        id: id.clone(),
+        strategy: params.name.clone(),
+        objective: config.objective,
+        started_at,
+        finished_at: None,
+        total,
+        progress: progress.clone(),
+        results: Vec::new(),
+        error: None,
+    });
+    drop(guard);
+
+    // 回测占用CPU, 在独立线程中运行, 不阻塞服务器的工作线程
+    let state: Arc<Mutex<StrategyState>> = state.get_ref().clone();
+    let job_id = id.clone();
+    let dir = req.dir.clone();
+    std::thread::spawn(move || {
+        // 读取历史快照也在后台线程中进行
+        let result = load_snapshots(dir.as_ref()).map(|history| {
+            let history: Vec<MarketData> = history.iter()
+                .map(|(timestamp, stocks)| MarketData::from_stock_data(*timestamp, stocks))
+                .collect();
+            run_optimization(&params, &candidates, &history, &config, &progress)
+        });
+        if let Some(job) = state.lock().unwrap().optimizations.get_mut(&job_id) {
+            match result {
+                Ok(results) => job.results = results,
+                Err(e) => {
+                    error!("参数优化失败: {}: {}", job_id, e);
+                    job.error = Some(e);
+                },
+            }
+            job.finished_at = Some(Utc::now());
+        }
+        info!("参数优化结束: {}", job_id);
+    });
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "job_id": id,
+        "total": total,
+    }))
+}
+
+// 查询参数优化进度和排名结果
+#[get("/optimize/{id}")]
+pub async fn get_optimization(
+    state: web::Data<Arc<Mutex<StrategyState>>>,
+    path: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let top = query.get("top").and_then(|t| t.parse::<usize>().ok()).unwrap_or(20);
+
+    let state = state.lock().unwrap();
+    match state.optimizations.get(&id) {
+        Some(job) => HttpResponse::Ok().json(serde_json::json!({
+            "job_id": job.id,
+            "strategy": job.strategy,
+            "objective": job.objective,
+            "status": match (&job.error, job.finished_at) {
+                (Some(_), _) => "failed",
+                (None, Some(_)) => "finished",
+                (None, None) => "running",
+            },
+            "error": job.error,
+            "started_at": job.started_at,
+            "finished_at": job.finished_at,
+            "completed": job.progress.load(Ordering::Relaxed),
+            "total": job.total,
+            "results": job.results.iter().take(top).collect::<Vec<_>>(),
+        })),
+        None => HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("未找到优化任务: {}", id)
+        })),
+    }
+}
+
+// 滚动前向分析: 逐窗口在样本内优化参数并在样本外验证
+#[post("/walkforward")]
+pub async fn walk_forward_analysis(
+    state: web::Data<Arc<Mutex<StrategyState>>>,
+    req: web::Json<WalkForwardRequest>,
+) -> impl Responder {
+    let req = req.into_inner();
+    info!("滚动前向分析: {}", req.optimize.name);
+
+    let params = match state.lock().unwrap().strategies.get(&req.optimize.name) {
+        Some(params) => params.clone(),
+        None => return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("未找到策略: {}", req.optimize.name)
+        })),
+    };
+    let optimization = match optimization_config(&req.optimize) {
+        Ok(config) => config,
+        Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    };
+    let (start_date, end_date) = (optimization.backtest.start_date, optimization.backtest.end_date);
+    let config = WalkForwardConfig {
+        in_sample_bars: req.in_sample_bars,
+        out_of_sample_bars: req.out_of_sample_bars,
+        anchored: req.anchored,
+        optimization,
+    };
+    let dir = req.optimize.dir.clone();
+
+    // 多次优化耗时较长, 在阻塞线程池中运行
+    let result = web::block(move || {
+        let history: Vec<MarketData> = load_snapshots(dir.as_ref())?.iter()
+            .filter(|(timestamp, _)| {
+                let date = position::market::trade_date(*timestamp);
+                start_date.is_none_or(|start| date >= start) && end_date.is_none_or(|end| date <= end)
+            })
+            .map(|(timestamp, stocks)| MarketData::from_stock_data(*timestamp, stocks))
+            .collect();
+        walk_forward(&params, &history, &config, &AtomicUsize::new(0))
+    }).await;
+
+    match result {
+        Ok(Ok(result)) => HttpResponse::Ok().json(result),
+        Ok(Err(e)) => {
+            error!("滚动前向分析失败: {}", e);
+            HttpResponse::BadRequest().json(serde_json::json!({ "error": e }))
+        },
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+// 在历史快照上筛选同行业的配对: 收益率相关系数和Engle-Granger协整检验
+#[get("/pairs")]
+pub async fn find_pair_candidates(query: web::Query<HashMap<String, String>>) -> impl Responder {
+    let min_correlation = match query.get("min_correlation").map(|v| v.parse::<f64>()) {
+        Some(Ok(value)) => value,
+        Some(Err(_)) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": "min_correlation应为数字" })),
+        None => 0.7,
+    };
+    let limit = query.get("limit").and_then(|v| v.parse::<usize>().ok()).unwrap_or(50);
+    let sector = query.get("sector").cloned();
+    let cointegrated_only = query.get("cointegrated").is_some_and(|v| v == "true");
+    let dir = query.get("dir").cloned();
+    info!("筛选配对: 最小相关系数{}", min_correlation);
+
+    // 两两检验耗时较长, 在阻塞线程池中运行
+    let result = web::block(move || {
+        let history: Vec<MarketData> = load_snapshots(dir.as_ref())?.iter()
+            .map(|(timestamp, stocks)| MarketData::from_stock_data(*timestamp, stocks))
+            .collect();
+        Ok::<_, String>(find_pairs(&history, min_correlation))
+    }).await;
+
+    match result {
+        Ok(Ok(pairs)) => {
+            let total = pairs.len();
+            let pairs: Vec<_> = pairs.into_iter()
+                .filter(|p| sector.as_ref().is_none_or(|sector| &p.sector == sector))
+                .filter(|p| !cointegrated_only || p.cointegration.is_cointegrated())
+                .take(limit)
+                .collect();
+            HttpResponse::Ok().json(serde_json::json!({ "total": total, "pairs": pairs }))
+        },
+        Ok(Err(e)) => HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
@@ -1,7 +1,7 @@
 use std::fs::{self, File};
 use std::io::{self, Write, BufWriter};
 use std::path::Path;
-use chrono::Local;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
 use crate::models::StockData;
 use std::collections::HashMap;
 use crate::models::merge_stock_data;
@@ -74,6 +74,41 @@ pub fn save_stock_data(stocks: &[StockData]) -> io::Result<String> {
     }
 }
 
+// 从文件名解析快照时间, 文件名格式为 {prefix}_%Y%m%d_%H%M%S.json
+fn parse_snapshot_time(filename: &str, prefix: &str) -> Option<DateTime<Utc>> {
+    let timestamp = filename
+        .strip_prefix(&format!("{}_", prefix))?
+        .strip_suffix(".json")?;
+    let naive = NaiveDateTime::parse_from_str(timestamp, "%Y%m%d_%H%M%S").ok()?;
+    Local.from_local_datetime(&naive).single().map(|t| t.with_timezone(&Utc))
+}
+
+// 读取目录下保存的全部股票数据快照, 按时间升序排列
+pub fn load_history(dir: &str) -> io::Result<Vec<(DateTime<Utc>, Vec<StockData>)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let filename = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        if let Some(timestamp) = parse_snapshot_time(&filename, "stock_data") {
+            files.push((timestamp, path));
+        }
+    }
+    files.sort_by_key(|(timestamp, _)| *timestamp);
+
+    let mut history = Vec::new();
+    for (timestamp, path) in files {
+        match load_from_json(&path.to_string_lossy()) {
+            Ok(stocks) => history.push((timestamp, stocks)),
+            Err(e) => error!("读取快照文件失败: {}: {}", path.display(), e),
+        }
+    }
+
+    Ok(history)
+}
+
 // 合并多个股票数据来源
 pub fn merge_stock_data_sources(data_sources: &[Vec<StockData>]) -> Vec<StockData> {
     // 创建一个映射, 用股票代码做键
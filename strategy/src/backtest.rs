@@ -1,61 +1,402 @@
-use crate::models::{BacktestResult, BacktestTrade, StrategyType};
-use log::info;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashMap;
+use log::{info, warn};
+use position::fees::FeeSchedule;
+use position::market::{trade_date, LOT_SIZE};
+use position::models::{Portfolio, Transaction, TransactionType};
+use position::money::{decimal, money, round_money, to_f64, Money, Shares};
+use position::order::OrderSide;
+use position::risk::RiskRule;
+use crate::models::{BacktestResult, BacktestTrade, MarketData, Signal, SignalAction, StrategyParams};
+use crate::metrics::compute_metrics;
+use crate::context::StrategyRunner;
+use crate::strategy::Strategy;
 
-// 执行回测
+// 回测设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestConfig {
+    pub initial_capital: f64,
+    // 回测区间 (按北京时间的交易日, 含首尾)
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    // 交易费用
+    #[serde(default)]
+    pub fees: FeeSchedule,
+    // 滑点, 成交价相对开盘价的不利偏移比例
+    pub slippage: f64,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            initial_capital: 100000.0,
+            start_date: None,
+            end_date: None,
+            fees: FeeSchedule::default(),
+            slippage: 0.001,
+        }
+    }
+}
+
+// 回测撮合: 维护组合、未平仓交易和已完成交易
+struct Broker<'a> {
+    portfolio: Portfolio,
+    config: &'a BacktestConfig,
+    open_trades: HashMap<String, BacktestTrade>,
+    trades: Vec<BacktestTrade>,
+}
+
+impl<'a> Broker<'a> {
+    fn new(params: &StrategyParams, config: &'a BacktestConfig) -> Self {
+        let mut portfolio = Portfolio::new(params.name.clone(), money(config.initial_capital));
+        portfolio.risk_limits = params.risk_limits();
+        Self {
+            portfolio,
+            config,
+            open_trades: HashMap::new(),
+            trades: Vec::new(),
+        }
+    }
+
+    // 按开盘价加滑点成交信号
+    fn fill(&mut self, signal: &Signal, open: f64, at: DateTime<Utc>) {
+        let quantity = signal.shares().unwrap_or(0);
+        if quantity <= 0 || open <= 0.0 {
+            return;
+        }
+
+        match signal.action {
+            SignalAction::Buy => self.buy(signal, quantity, open * (1.0 + self.config.slippage), at),
+            SignalAction::Sell => self.sell(signal, quantity, open * (1.0 - self.config.slippage), at),
+            // 回测组合没有信用账户
+            SignalAction::Short | SignalAction::Cover => warn!("回测不支持融券, 忽略信号: {} {:?}", signal.code, signal.action),
+            SignalAction::Hold => {},
+        }
+    }
+
+    fn buy(&mut self, signal: &Signal, quantity: Shares, price: f64, at: DateTime<Utc>) {
+        let price = round_money(decimal(price));
+        let cash = self.portfolio.available_cash();
+
+        // 现金不足或超过单个持仓比例限制时按整手减少数量
+        // (信号按收盘价定量, 按开盘价加滑点成交后可能略超限制)
+        let mut quantity = quantity;
+        let mut fee = Money::ZERO;
+        while quantity > 0 {
+            (fee, _) = self.config.fees.costs(OrderSide::Buy, Money::from(quantity) * price);
+            let within_limit = !self.portfolio.check_risk(&signal.code, OrderSide::Buy, quantity, to_f64(price), None)
+                .iter()
+                .any(|b| b.rule == RiskRule::PositionWeight);
+            if Money::from(quantity) * price + fee <= cash && within_limit {
+                break;
+            }
+            quantity -= LOT_SIZE;
+        }
+        if quantity <= 0 {
+            return;
+        }
+
+        let mut transaction = Transaction::new(signal.code.clone(), TransactionType::Buy, quantity, price);
+        transaction.timestamp = at;
+        transaction.fee = Some(fee);
+        transaction.note = Some(signal.reason.clone());
+        if let Err(e) = self.portfolio.add_transaction(transaction) {
+            warn!("回测买入{}失败: {}", signal.code, e);
+            return;
+        }
+
+        // 费用计入每股成本
+        let cost = to_f64(Money::from(quantity) * price + fee) / quantity as f64;
+        let trade = self.open_trades.entry(signal.code.clone()).or_insert_with(|| BacktestTrade {
+            code: signal.code.clone(),
+            entry_date: at,
+            entry_price: 0.0,
+            entry_amount: 0.0,
+            exit_date: None,
+            exit_price: None,
+            exit_amount: None,
+            profit_loss: None,
+            profit_loss_percent: None,
+            exit_reason: None,
+        });
+        let amount = trade.entry_amount + quantity as f64;
+        trade.entry_price = (trade.entry_price * trade.entry_amount + cost * quantity as f64) / amount;
+        trade.entry_amount = amount;
+    }
+
+    fn sell(&mut self, signal: &Signal, quantity: Shares, price: f64, at: DateTime<Utc>) {
+        // 受T+1限制
+        let quantity = quantity.min(self.portfolio.sellable_amount(&signal.code, at));
+        if quantity <= 0 {
+            return;
+        }
+
+        let price = round_money(decimal(price));
+        let (fee, tax) = self.config.fees.costs(OrderSide::Sell, Money::from(quantity) * price);
+        let mut transaction = Transaction::new(signal.code.clone(), TransactionType::Sell, quantity, price);
+        transaction.timestamp = at;
+        transaction.fee = Some(fee);
+        transaction.tax = Some(tax);
+        transaction.note = Some(signal.reason.clone());
+        if let Err(e) = self.portfolio.add_transaction(transaction) {
+            warn!("回测卖出{}失败: {}", signal.code, e);
+            return;
+        }
+
+        let open = match self.open_trades.get_mut(&signal.code) {
+            Some(open) => open,
+            None => return,
+        };
+
+        // 部分卖出时拆分出已平仓部分
+        let exit_price = to_f64(price);
+        let profit_loss = (exit_price - open.entry_price) * quantity as f64 - to_f64(fee + tax);
+        let mut closed = open.clone();
+        closed.entry_amount = quantity as f64;
+        closed.exit_date = Some(at);
+        closed.exit_price = Some(exit_price);
+        closed.exit_amount = Some(quantity as f64);
+        closed.profit_loss = Some(profit_loss);
+        closed.profit_loss_percent = Some(profit_loss / (open.entry_price * quantity as f64) * 100.0);
+        closed.exit_reason = Some(signal.reason.clone());
+        self.trades.push(closed);
+
+        open.entry_amount -= quantity as f64;
+        if !self.portfolio.positions.contains_key(&signal.code) {
+            self.open_trades.remove(&signal.code);
+        }
+    }
+
+    // 按收盘价检查止损和止盈, 生成下一根行情的全部卖出信号
+    fn exit_signals(&self, data: &MarketData, params: &StrategyParams) -> Vec<Signal> {
+        let mut signals = Vec::new();
+        for (code, trade) in &self.open_trades {
+            let price = match data.stocks.get(code) {
+                Some(snapshot) => snapshot.price,
+                None => continue,
+            };
+            let change = price / trade.entry_price - 1.0;
+            let reason = if params.stop_loss > 0.0 && change <= -params.stop_loss {
+                "止损"
+            } else if params.take_profit > 0.0 && change >= params.take_profit {
+                "止盈"
+            } else {
+                continue;
+            };
+
+            let held = self.portfolio.positions.get(code).map(|p| p.amount).unwrap_or(0);
+            signals.push(Signal {
+                code: code.clone(),
+                timestamp: data.timestamp,
+                action: SignalAction::Sell,
+                price: Some(price),
+                amount: None,
+                reason: reason.to_string(),
+                strength: 1.0,
+            }.with_shares(held));
+        }
+        signals
+    }
+}
+
+// 在历史行情上回放策略: 每根行情收盘后生成信号, 在下一根行情按开盘价成交
 pub fn run_backtest(
-    strategy_name: &str,
-    strategy_type: StrategyType,
-    initial_capital: f64,
-) -> BacktestResult {
-    info!("执行回测: {}", strategy_name);
-    
-    // 这里只是创建一个模拟的回测结果
-    // 实际应用中需要实现真正的回测逻辑
-    
-    BacktestResult {
-        strategy_name: strategy_name.to_string(),
-        strategy_type,
-        start_date: chrono::Utc::now() - chrono::Duration::days(30),
-        end_date: chrono::Utc::now(),
-        initial_capital,
-        final_capital: initial_capital * 1.1, // 假设收益10%
-        total_return: 0.1,
-        annualized_return: 0.12,
-        sharpe_ratio: 1.5,
-        max_drawdown: 0.05,
-        win_rate: 0.6,
-        profit_factor: 1.8,
-        total_trades: 20,
-        winning_trades: 12,
-        losing_trades: 8,
-        avg_profit: 1000.0,
-        avg_loss: -500.0,
-        equity_curve: vec![],
-        trades: vec![
-            BacktestTrade {
-                code: "SH000001".to_string(),
-                entry_date: chrono::Utc::now() - chrono::Duration::days(25),
-                entry_price: 3000.0,
-                entry_amount: 1.0,
-                exit_date: Some(chrono::Utc::now() - chrono::Duration::days(20)),
-                exit_price: Some(3100.0),
-                exit_amount: Some(1.0),
-                profit_loss: Some(100.0),
-                profit_loss_percent: Some(3.33),
-                exit_reason: Some("止盈".to_string()),
-            },
-            BacktestTrade {
-                code: "SZ399001".to_string(),
-                entry_date: chrono::Utc::now() - chrono::Duration::days(15),
-                entry_price: 10000.0,
-                entry_amount: 1.0,
-                exit_date: Some(chrono::Utc::now() - chrono::Duration::days(10)),
-                exit_price: Some(9800.0),
-                exit_amount: Some(1.0),
-                profit_loss: Some(-200.0),
-                profit_loss_percent: Some(-2.0),
-                exit_reason: Some("止损".to_string()),
-            },
-        ],
-    }
-} 
\ No newline at end of file
+    strategy: Box<dyn Strategy>,
+    params: &StrategyParams,
+    history: &[MarketData],
+    config: &BacktestConfig,
+) -> Result<BacktestResult, String> {
+    run_backtest_with_warmup(strategy, params, &[], history, config)
+}
+
+// 回测前先用warmup行情填充策略的历史窗口, 这些行情不产生交易
+pub fn run_backtest_with_warmup(
+    strategy: Box<dyn Strategy>,
+    params: &StrategyParams,
+    warmup: &[MarketData],
+    history: &[MarketData],
+    config: &BacktestConfig,
+) -> Result<BacktestResult, String> {
+    info!("执行回测: {}", strategy.name());
+
+    if config.initial_capital <= 0.0 {
+        return Err("初始资金必须大于0".to_string());
+    }
+
+    let mut bars: Vec<&MarketData> = history.iter()
+        .filter(|d| {
+            let date = trade_date(d.timestamp);
+            config.start_date.is_none_or(|start| date >= start) && config.end_date.is_none_or(|end| date <= end)
+        })
+        .collect();
+    bars.sort_by_key(|d| d.timestamp);
+    let (first, last) = match (bars.first(), bars.last()) {
+        (Some(first), Some(last)) => (first.timestamp, last.timestamp),
+        _ => return Err("回测区间内没有行情数据".to_string()),
+    };
+
+    let mut broker = Broker::new(params, config);
+    let mut runner = StrategyRunner::new(strategy, params.sizing.clone());
+    let mut warmup: Vec<&MarketData> = warmup.iter().filter(|d| d.timestamp < first).collect();
+    warmup.sort_by_key(|d| d.timestamp);
+    for data in warmup {
+        runner.warm_up(std::slice::from_ref(data));
+    }
+    let mut pending: Vec<Signal> = Vec::new();
+    let mut equity_curve = Vec::new();
+
+    for data in bars {
+        // 先卖后买, 释放现金
+        pending.sort_by_key(|s| s.action != SignalAction::Sell);
+        for signal in pending.drain(..) {
+            if let Some(snapshot) = data.stocks.get(&signal.code) {
+                broker.fill(&signal, snapshot.open, data.timestamp);
+            }
+        }
+
+        broker.portfolio.mark_to_market(&data.quotes(), data.timestamp);
+
+        let mut signals = runner.on_bar(data, &broker.portfolio);
+        let exits: Vec<Signal> = broker.exit_signals(data, params).into_iter()
+            .filter(|exit| !signals.iter().any(|s| s.code == exit.code && s.action == SignalAction::Sell))
+            .collect();
+        signals.extend(exits);
+        pending = signals;
+
+        broker.portfolio.record_nav(data.timestamp);
+        equity_curve.push((data.timestamp, broker.portfolio.total_market_value()));
+    }
+
+    runner.finish(&broker.portfolio);
+
+    let final_capital = equity_curve.last().map(|(_, v)| *v).unwrap_or(config.initial_capital);
+
+    // 未平仓交易附在最后
+    let mut trades = broker.trades;
+    let mut open_trades: Vec<BacktestTrade> = broker.open_trades.into_values().collect();
+    open_trades.sort_by(|a, b| a.entry_date.cmp(&b.entry_date).then(a.code.cmp(&b.code)));
+    trades.extend(open_trades);
+
+    let metrics = compute_metrics(&equity_curve, &trades, 0.0);
+
+    Ok(BacktestResult {
+        strategy_name: params.name.clone(),
+        strategy_type: params.strategy_type,
+        start_date: first,
+        end_date: last,
+        initial_capital: config.initial_capital,
+        final_capital,
+        total_return: final_capital / config.initial_capital - 1.0,
+        annualized_return: metrics.annualized_return,
+        sharpe_ratio: metrics.sharpe_ratio,
+        max_drawdown: metrics.max_drawdown,
+        win_rate: metrics.win_rate,
+        profit_factor: metrics.profit_factor,
+        sortino_ratio: metrics.sortino_ratio,
+        calmar_ratio: metrics.calmar_ratio,
+        max_drawdown_duration: metrics.max_drawdown_duration,
+        exposure: metrics.exposure,
+        turnover: metrics.turnover,
+        total_trades: metrics.total_trades,
+        winning_trades: metrics.winning_trades,
+        losing_trades: metrics.losing_trades,
+        avg_profit: metrics.avg_profit,
+        avg_loss: metrics.avg_loss,
+        equity_curve,
+        trades,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StrategyType;
+    use crate::context::StrategyContext;
+    use crate::sizing::SizingModel;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use crate::testing::bar_with_open as bar;
+
+    // 第一天买入, 第三天卖出
+    #[derive(Default)]
+    struct ScriptedStrategy {
+        bars: usize,
+        ended: Option<Arc<AtomicBool>>,
+    }
+
+    impl Strategy for ScriptedStrategy {
+        fn name(&self) -> &str { "scripted" }
+        fn strategy_type(&self) -> StrategyType { StrategyType::Custom }
+        fn is_enabled(&self) -> bool { true }
+        fn set_enabled(&mut self, _enabled: bool) {}
+        fn on_bar(&mut self, ctx: &StrategyContext) -> Vec<Signal> {
+            self.bars += 1;
+            // 历史窗口包含当前行情
+            assert_eq!(ctx.history.len("600000"), self.bars.min(self.history_window()));
+            let action = match ctx.now.format("%d").to_string().as_str() {
+                "01" => SignalAction::Buy,
+                "03" if ctx.position("600000") > 0 => SignalAction::Sell,
+                _ => return Vec::new(),
+            };
+            vec![Signal {
+                code: "600000".to_string(),
+                timestamp: ctx.now,
+                action,
+                price: None,
+                amount: None,
+                reason: "test".to_string(),
+                strength: 1.0,
+            }]
+        }
+        fn on_end(&mut self, _ctx: &StrategyContext) {
+            if let Some(ended) = &self.ended {
+                ended.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+
+    #[test]
+    fn test_backtest_fills_next_open_with_costs() {
+        let mut params = StrategyParams::new("scripted".to_string(), StrategyType::Custom);
+        params.sizing = SizingModel::FixedCash { cash: 10000.0 };
+        params.stop_loss = 0.0;
+        params.take_profit = 0.0;
+        let config = BacktestConfig {
+            fees: FeeSchedule { stamp_tax_rate: 0.001, ..FeeSchedule::zero() },
+            slippage: 0.0,
+            ..BacktestConfig::default()
+        };
+        let history = vec![bar(0, 10.0, 10.0), bar(1, 10.0, 11.0), bar(2, 11.0, 12.0), bar(3, 12.0, 12.5), bar(4, 12.5, 13.0)];
+
+        let ended = Arc::new(AtomicBool::new(false));
+        let strategy = ScriptedStrategy { ended: Some(ended.clone()), ..Default::default() };
+        let result = run_backtest(Box::new(strategy), &params, &history, &config).unwrap();
+        assert!(ended.load(Ordering::SeqCst));
+
+        // 第二天开盘买入1000股@10, 第四天开盘按仓位模型卖出800股@12, 印花税9.6
+        assert_eq!(result.total_trades, 1);
+        let trade = &result.trades[0];
+        assert_eq!(trade.entry_price, 10.0);
+        assert_eq!(trade.exit_price, Some(12.0));
+        assert!((trade.profit_loss.unwrap() - 1590.4).abs() < 1e-6);
+        // 剩余200股未平仓, 按最后收盘价13估值
+        assert_eq!(result.trades[1].entry_amount, 200.0);
+        assert!(result.trades[1].exit_date.is_none());
+        assert!((result.final_capital - 102190.4).abs() < 1e-6);
+        assert_eq!(result.equity_curve.len(), 5);
+        assert_eq!(result.win_rate, 1.0);
+
+        // 买入数量超过单个持仓10%的限制时减到限制以内
+        let mut oversized = params.clone();
+        oversized.sizing = SizingModel::FixedCash { cash: 50000.0 };
+        let result = run_backtest(Box::<ScriptedStrategy>::default(), &oversized, &history, &config).unwrap();
+        assert_eq!(result.trades[0].entry_amount, 1000.0);
+
+        // 回测区间过滤
+        let config = BacktestConfig { start_date: Some(NaiveDate::from_ymd_opt(2024, 1, 4).unwrap()), ..config };
+        let result = run_backtest(Box::<ScriptedStrategy>::default(), &params, &history, &config).unwrap();
+        assert_eq!(result.equity_curve.len(), 2);
+        assert_eq!(result.total_trades, 0);
+    }
+}
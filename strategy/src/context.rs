@@ -0,0 +1,253 @@
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use position::models::Portfolio;
+use position::money::Money;
+use crate::models::{MarketData, Signal, SignalAction, StockSnapshot};
+use crate::sizing::{apply_sizing, SizingModel};
+use crate::strategy::Strategy;
+
+// 默认保留的历史行情数量
+pub const DEFAULT_HISTORY_WINDOW: usize = 120;
+
+// 按股票保存的滚动历史行情窗口
+#[derive(Debug, Clone)]
+pub struct History {
+    window: usize,
+    bars: HashMap<String, VecDeque<StockSnapshot>>,
+    last_timestamp: Option<DateTime<Utc>>,
+}
+
+impl History {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            bars: HashMap::new(),
+            last_timestamp: None,
+        }
+    }
+
+    // 追加一次行情, 超出窗口的旧数据丢弃
+    pub fn push(&mut self, data: &MarketData) {
+        for (code, snapshot) in &data.stocks {
+            let bars = self.bars.entry(code.clone()).or_default();
+            bars.push_back(snapshot.clone());
+            while bars.len() > self.window {
+                bars.pop_front();
+            }
+        }
+        self.last_timestamp = Some(data.timestamp);
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    // 调整窗口长度, 缩短时丢弃较早的数据
+    pub fn resize(&mut self, window: usize) {
+        self.window = window.max(1);
+        for bars in self.bars.values_mut() {
+            while bars.len() > self.window {
+                bars.pop_front();
+            }
+        }
+    }
+
+    pub fn last_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.last_timestamp
+    }
+
+    // 股票的历史行情, 按时间升序, 最后一个为当前行情
+    pub fn bars(&self, code: &str) -> Option<&VecDeque<StockSnapshot>> {
+        self.bars.get(code)
+    }
+
+    // 最近n个收盘价, 按时间升序, 数据不足时返回全部
+    pub fn closes(&self, code: &str, n: usize) -> Vec<f64> {
+        match self.bars.get(code) {
+            Some(bars) => bars.iter().skip(bars.len().saturating_sub(n)).map(|b| b.price).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // n根行情之前的收盘价 (0为当前)
+    pub fn close_ago(&self, code: &str, n: usize) -> Option<f64> {
+        let bars = self.bars.get(code)?;
+        bars.len().checked_sub(n + 1).map(|i| bars[i].price)
+    }
+
+    pub fn len(&self, code: &str) -> usize {
+        self.bars.get(code).map(|b| b.len()).unwrap_or(0)
+    }
+
+    pub fn codes(&self) -> impl Iterator<Item = &String> {
+        self.bars.keys()
+    }
+}
+
+// 策略运行上下文
+pub struct StrategyContext<'a> {
+    // 当前时间 (回测中为行情时间)
+    pub now: DateTime<Utc>,
+    // 当前行情
+    pub data: &'a MarketData,
+    // 历史行情, 已包含当前行情
+    pub history: &'a History,
+    // 当前组合
+    pub portfolio: &'a Portfolio,
+}
+
+impl StrategyContext<'_> {
+    // 当前持仓数量
+    pub fn position(&self, code: &str) -> i64 {
+        self.portfolio.positions.get(code).map(|p| p.amount).unwrap_or(0)
+    }
+}
+
+// 驱动策略运行: 维护历史窗口并按生命周期调用策略, 回测和实盘共用
+pub struct StrategyRunner {
+    strategy: Box<dyn Strategy>,
+    history: History,
+    sizing: SizingModel,
+    last: Option<MarketData>,
+    started: bool,
+}
+
+impl StrategyRunner {
+    pub fn new(strategy: Box<dyn Strategy>, sizing: SizingModel) -> Self {
+        let history = History::new(strategy.history_window());
+        Self {
+            strategy,
+            history,
+            sizing,
+            last: None,
+            started: false,
+        }
+    }
+
+    pub fn strategy(&self) -> &dyn Strategy {
+        self.strategy.as_ref()
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    // 替换策略实例 (如参数修改后), 保留已有的历史行情, 新实例在下一根行情时重新调用on_start
+    pub fn replace_strategy(&mut self, strategy: Box<dyn Strategy>, sizing: SizingModel) {
+        self.history.resize(strategy.history_window());
+        self.strategy = strategy;
+        self.sizing = sizing;
+        self.started = false;
+    }
+
+    // 预先载入历史行情, 不产生信号
+    pub fn warm_up(&mut self, history: &[MarketData]) {
+        for data in history {
+            if self.history.last_timestamp().is_none_or(|last| data.timestamp > last) {
+                self.history.push(data);
+            }
+        }
+    }
+
+    // 处理一次新行情, 返回按仓位模型确定数量的信号; 行情没有更新时返回空
+    pub fn on_bar(&mut self, data: &MarketData, portfolio: &Portfolio) -> Vec<Signal> {
+        let mut signals = self.step(data, portfolio);
+        apply_sizing(&mut signals, data, portfolio, &self.sizing);
+        signals
+    }
+
+    // 没有关联组合时只生成信号, 不确定数量 (amount为None)
+    pub fn on_bar_unsized(&mut self, data: &MarketData) -> Vec<Signal> {
+        let portfolio = Portfolio::new(String::new(), Money::ZERO);
+        self.step(data, &portfolio)
+    }
+
+    fn step(&mut self, data: &MarketData, portfolio: &Portfolio) -> Vec<Signal> {
+        if self.history.last_timestamp().is_some_and(|last| data.timestamp <= last) {
+            return Vec::new();
+        }
+        self.history.push(data);
+
+        let ctx = StrategyContext {
+            now: data.timestamp,
+            data,
+            history: &self.history,
+            portfolio,
+        };
+        if !self.started {
+            self.strategy.on_start(&ctx);
+            self.started = true;
+        }
+
+        let signals: Vec<Signal> = self.strategy.on_bar(&ctx).into_iter()
+            .filter(|s| s.action != SignalAction::Hold)
+            .collect();
+        self.last = Some(data.clone());
+        signals
+    }
+
+    // 结束运行
+    pub fn finish(&mut self, portfolio: &Portfolio) {
+        if !self.started {
+            return;
+        }
+        if let Some(data) = &self.last {
+            let ctx = StrategyContext {
+                now: data.timestamp,
+                data,
+                history: &self.history,
+                portfolio,
+            };
+            self.strategy.on_end(&ctx);
+        }
+        self.started = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bar;
+    use crate::strategy::MomentumStrategy;
+    use crate::models::{StrategyParams, StrategyType};
+
+    #[test]
+    fn test_runner_keeps_window_and_drives_momentum() {
+        let mut params = StrategyParams::new("momentum".to_string(), StrategyType::Momentum);
+        params.set_param("lookback_period", 3).unwrap();
+        params.set_param("threshold", 0.05).unwrap();
+        let mut runner = StrategyRunner::new(Box::new(MomentumStrategy::from_params(&params).unwrap()), SizingModel::FixedCash { cash: 10000.0 });
+        assert_eq!(runner.history().window(), 4);
+        let portfolio = Portfolio::new("test".to_string(), Money::from(100000));
+
+        // 预热数据不产生信号
+        runner.warm_up(&[bar(0, 10.0), bar(1, 10.0)]);
+        assert_eq!(runner.history().len("600000"), 2);
+
+        // 回看期数据不足
+        assert!(runner.on_bar(&bar(2, 10.2), &portfolio).is_empty());
+        // 3日涨幅10%超过阈值
+        let signals = runner.on_bar(&bar(3, 11.0), &portfolio);
+        assert_eq!(signals.len(), 1);
+        assert_eq!(signals[0].action, SignalAction::Buy);
+        assert_eq!(signals[0].shares(), Some(900));
+        assert_eq!(runner.history().closes("600000", 10), vec![10.0, 10.0, 10.2, 11.0]);
+
+        // 重复行情不再驱动策略, 窗口保持固定长度
+        assert!(runner.on_bar(&bar(3, 11.0), &portfolio).is_empty());
+        runner.on_bar(&bar(4, 11.0), &portfolio);
+        assert_eq!(runner.history().len("600000"), 4);
+        assert_eq!(runner.history().close_ago("600000", 3), Some(10.0));
+
+        // 未持仓时下跌不产生卖出信号
+        assert!(runner.on_bar(&bar(5, 9.0), &portfolio).is_empty());
+        runner.finish(&portfolio);
+
+        // 没有组合时信号不确定数量
+        let mut runner = StrategyRunner::new(Box::new(MomentumStrategy::from_params(&params).unwrap()), SizingModel::FixedCash { cash: 10000.0 });
+        runner.warm_up(&[bar(0, 10.0), bar(1, 10.0), bar(2, 10.2)]);
+        let signals = runner.on_bar_unsized(&bar(3, 11.0));
+        assert_eq!(signals.len(), 1);
+        assert_eq!(signals[0].shares(), None);
+    }
+}
@@ -0,0 +1,373 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use crate::context::StrategyContext;
+use crate::models::{Fundamentals, Signal, SignalAction, StockSnapshot, StrategyParams, StrategyType};
+use crate::strategy::Strategy;
+
+// 选股因子, 数值越大越好 (估值类因子取倒数, 规模和波动率取负)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Factor {
+    EarningsYield,    // 盈利收益率 (1/PE)
+    BookToPrice,      // 账面市值比 (1/PB)
+    Roe,              // 净资产收益率
+    Roic,             // 投资资本回报率
+    GrossMargin,      // 毛利率
+    OperatingMargin,  // 经营利润率
+    NetMargin,        // 净利率
+    RevenueGrowth,    // 收入增长
+    EarningsGrowth,   // 盈利增长
+    DividendYield,    // 股息率
+    Momentum3m,       // 3个月表现
+    Momentum6m,       // 6个月表现
+    Momentum1y,       // 1年表现
+    LowVolatility,    // 低波动 (近一月波动率取负)
+    SmallSize,        // 小市值 (市值对数取负)
+}
+
+impl Factor {
+    pub const ALL: [Factor; 15] = [
+        Factor::EarningsYield, Factor::BookToPrice, Factor::Roe, Factor::Roic,
+        Factor::GrossMargin, Factor::OperatingMargin, Factor::NetMargin,
+        Factor::RevenueGrowth, Factor::EarningsGrowth, Factor::DividendYield,
+        Factor::Momentum3m, Factor::Momentum6m, Factor::Momentum1y,
+        Factor::LowVolatility, Factor::SmallSize,
+    ];
+
+    // 因子权重对应的策略参数名
+    pub fn weight_param(&self) -> &'static str {
+        match self {
+            Factor::EarningsYield => "weight_earnings_yield",
+            Factor::BookToPrice => "weight_book_to_price",
+            Factor::Roe => "weight_roe",
+            Factor::Roic => "weight_roic",
+            Factor::GrossMargin => "weight_gross_margin",
+            Factor::OperatingMargin => "weight_operating_margin",
+            Factor::NetMargin => "weight_net_margin",
+            Factor::RevenueGrowth => "weight_revenue_growth",
+            Factor::EarningsGrowth => "weight_earnings_growth",
+            Factor::DividendYield => "weight_dividend_yield",
+            Factor::Momentum3m => "weight_momentum_3m",
+            Factor::Momentum6m => "weight_momentum_6m",
+            Factor::Momentum1y => "weight_momentum_1y",
+            Factor::LowVolatility => "weight_low_volatility",
+            Factor::SmallSize => "weight_small_size",
+        }
+    }
+
+    // 默认权重: 价值, 质量和动量
+    pub fn default_weight(&self) -> f64 {
+        match self {
+            Factor::EarningsYield => 0.3,
+            Factor::BookToPrice => 0.2,
+            Factor::Roe => 0.3,
+            Factor::Momentum6m => 0.2,
+            _ => 0.0,
+        }
+    }
+
+    // 因子原始值, 亏损公司的PE和PB不参与估值因子
+    pub fn value(&self, fundamentals: &Fundamentals) -> Option<f64> {
+        match self {
+            Factor::EarningsYield => fundamentals.pe_ratio.filter(|pe| *pe > 0.0).map(|pe| 1.0 / pe),
+            Factor::BookToPrice => fundamentals.price_to_book.filter(|pb| *pb > 0.0).map(|pb| 1.0 / pb),
+            Factor::Roe => fundamentals.roe,
+            Factor::Roic => fundamentals.roic,
+            Factor::GrossMargin => fundamentals.gross_margin,
+            Factor::OperatingMargin => fundamentals.operating_margin,
+            Factor::NetMargin => fundamentals.net_margin,
+            Factor::RevenueGrowth => fundamentals.revenue_growth,
+            Factor::EarningsGrowth => fundamentals.earnings_growth,
+            Factor::DividendYield => fundamentals.dividend_yield,
+            Factor::Momentum3m => fundamentals.performance_3m,
+            Factor::Momentum6m => fundamentals.performance_6m,
+            Factor::Momentum1y => fundamentals.performance_1y,
+            Factor::LowVolatility => fundamentals.volatility_1m.map(|v| -v),
+            Factor::SmallSize => fundamentals.market_cap.filter(|c| *c > 0.0).map(|c| -c.ln()),
+        }
+    }
+}
+
+// 按分位数缩尾, 两端各截断quantile比例的极端值
+pub fn winsorize(values: &mut HashMap<String, f64>, quantile: f64) {
+    if values.len() < 3 || quantile <= 0.0 {
+        return;
+    }
+    let mut sorted: Vec<f64> = values.values().cloned().collect();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let last = sorted.len() - 1;
+    let index = ((last as f64) * quantile.min(0.5)).round() as usize;
+    let (low, high) = (sorted[index], sorted[last - index]);
+    for value in values.values_mut() {
+        *value = value.clamp(low, high);
+    }
+}
+
+// 截面标准化, 标准差为0时全部为0
+pub fn z_scores(values: &HashMap<String, f64>) -> HashMap<String, f64> {
+    let n = values.len() as f64;
+    if n == 0.0 {
+        return HashMap::new();
+    }
+    let mean = values.values().sum::<f64>() / n;
+    let std_dev = (values.values().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+    values.iter()
+        .map(|(code, v)| (code.clone(), if std_dev > 0.0 { (v - mean) / std_dev } else { 0.0 }))
+        .collect()
+}
+
+// 行业中性化: 在每个行业内分别标准化, 没有行业的股票归为一组
+pub fn sector_neutral_z_scores(values: &HashMap<String, f64>, sectors: &HashMap<String, String>) -> HashMap<String, f64> {
+    let mut groups: HashMap<&str, HashMap<String, f64>> = HashMap::new();
+    for (code, value) in values {
+        let sector = sectors.get(code).map(|s| s.as_str()).unwrap_or("");
+        groups.entry(sector).or_default().insert(code.clone(), *value);
+    }
+    groups.values().flat_map(z_scores).collect()
+}
+
+// 因子模型设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FactorModelConfig {
+    pub weights: BTreeMap<Factor, f64>,
+    pub top_n: usize,
+    // 调仓间隔 (行情数)
+    pub rebalance_bars: usize,
+    // 缩尾分位数
+    pub winsorize: f64,
+    pub sector_neutral: bool,
+}
+
+impl FactorModelConfig {
+    pub fn from_params(params: &StrategyParams) -> Self {
+        Self {
+            weights: Factor::ALL.iter()
+                .map(|f| (*f, params.get_param(f.weight_param()).unwrap_or(f.default_weight())))
+                .filter(|(_, w)| *w != 0.0)
+                .collect(),
+            top_n: params.get_param::<usize>("top_n").unwrap_or(10).max(1),
+            rebalance_bars: params.get_param::<usize>("rebalance_bars").unwrap_or(20).max(1),
+            winsorize: params.get_param("winsorize").unwrap_or(0.05),
+            sector_neutral: params.get_param("sector_neutral").unwrap_or(false),
+        }
+    }
+}
+
+// 计算综合因子得分: 每个因子缩尾后截面标准化 (可按行业), 缺失记为0, 按权重加权平均
+pub fn composite_scores(stocks: &[&StockSnapshot], config: &FactorModelConfig) -> HashMap<String, f64> {
+    let total_weight: f64 = config.weights.values().map(|w| w.abs()).sum();
+    let mut scores: HashMap<String, f64> = HashMap::new();
+    if total_weight == 0.0 {
+        return scores;
+    }
+    let sectors: HashMap<String, String> = stocks.iter()
+        .filter_map(|s| s.fundamentals.sector.clone().map(|sector| (s.code.clone(), sector)))
+        .collect();
+
+    for (factor, weight) in &config.weights {
+        let mut values: HashMap<String, f64> = stocks.iter()
+            .filter_map(|s| factor.value(&s.fundamentals).map(|v| (s.code.clone(), v)))
+            .collect();
+        winsorize(&mut values, config.winsorize);
+        let z = if config.sector_neutral {
+            sector_neutral_z_scores(&values, &sectors)
+        } else {
+            z_scores(&values)
+        };
+        for (code, z) in z {
+            *scores.entry(code).or_default() += weight * z / total_weight;
+        }
+    }
+    scores
+}
+
+// 多因子选股策略: 每隔rebalance_bars根行情按综合得分选出前top_n只股票, 卖出落选的持仓
+pub struct FactorModelStrategy {
+    params: StrategyParams,
+    config: FactorModelConfig,
+    bars: usize,
+}
+
+impl FactorModelStrategy {
+    pub fn new(name: &str) -> Self {
+        Self::with_params(StrategyParams::new(name.to_string(), StrategyType::FactorModel))
+    }
+
+    // 按策略参数创建, 参数按参数定义校验
+    pub fn from_params(params: &StrategyParams) -> Result<Self, String> {
+        Ok(Self::with_params(params.validated()?))
+    }
+
+    fn with_params(params: StrategyParams) -> Self {
+        Self {
+            config: FactorModelConfig::from_params(&params),
+            params,
+            bars: 0,
+        }
+    }
+
+    pub fn config(&self) -> &FactorModelConfig {
+        &self.config
+    }
+}
+
+impl Strategy for FactorModelStrategy {
+    fn name(&self) -> &str {
+        &self.params.name
+    }
+
+    fn strategy_type(&self) -> StrategyType {
+        self.params.strategy_type
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.params.enabled
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.params.enabled = enabled;
+    }
+
+    // 因子只使用当前截面
+    fn history_window(&self) -> usize {
+        1
+    }
+
+    fn on_start(&mut self, _ctx: &StrategyContext) {
+        self.bars = 0;
+    }
+
+    fn on_bar(&mut self, ctx: &StrategyContext) -> Vec<Signal> {
+        let rebalance = self.bars.is_multiple_of(self.config.rebalance_bars);
+        self.bars += 1;
+        if !rebalance {
+            return Vec::new();
+        }
+
+        let stocks: Vec<&StockSnapshot> = ctx.data.stocks.values().filter(|s| s.price > 0.0).collect();
+        let scores = composite_scores(&stocks, &self.config);
+        let mut ranked: Vec<(&String, f64)> = scores.iter().map(|(code, score)| (code, *score)).collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+        let selected: HashSet<&String> = ranked.iter().take(self.config.top_n).map(|(code, _)| *code).collect();
+
+        let mut signals = Vec::new();
+        // 卖出落选的持仓
+        let mut held: Vec<(&String, i64)> = ctx.portfolio.positions.iter()
+            .filter(|(code, p)| p.amount > 0 && !selected.contains(code))
+            .map(|(code, p)| (code, p.amount))
+            .collect();
+        held.sort();
+        for (code, amount) in held {
+            let price = ctx.data.stocks.get(code).map(|s| s.price);
+            signals.push(Signal {
+                code: code.clone(),
+                timestamp: ctx.now,
+                action: SignalAction::Sell,
+                price,
+                amount: None,
+                reason: format!("调仓: 综合因子得分未进入前{}", self.config.top_n),
+                strength: 1.0,
+            }.with_shares(amount)); // 全部卖出
+        }
+
+        // 买入新入选的股票, 数量由仓位模型确定
+        for (rank, (code, score)) in ranked.iter().take(self.config.top_n).enumerate() {
+            if ctx.position(code) > 0 {
+                continue;
+            }
+            signals.push(Signal {
+                code: (*code).clone(),
+                timestamp: ctx.now,
+                action: SignalAction::Buy,
+                price: ctx.data.stocks.get(*code).map(|s| s.price),
+                amount: None,
+                reason: format!("调仓: 综合因子得分{:.2}, 排名第{}", score, rank + 1),
+                strength: 1.0,
+            });
+        }
+        signals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+    use position::money::Money;
+    use position::models::{Portfolio, Position};
+    use crate::context::StrategyRunner;
+    use crate::models::MarketData;
+    use crate::sizing::SizingModel;
+    use crate::strategy::create_strategy;
+    use crate::testing::snapshot;
+
+    fn stock(code: &str, sector: &str, pe: f64, roe: f64) -> StockSnapshot {
+        snapshot(code, 10.0, 0.0, Fundamentals {
+            sector: Some(sector.to_string()),
+            pe_ratio: Some(pe),
+            roe: Some(roe),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_factor_ranking_and_rebalance() {
+        // 缩尾: 5个值截断两端各1个
+        let mut values: HashMap<String, f64> = [("a", 1.0), ("b", 2.0), ("c", 3.0), ("d", 4.0), ("e", 100.0)].iter()
+            .map(|(k, v)| (k.to_string(), *v)).collect();
+        winsorize(&mut values, 0.25);
+        assert_eq!(values["e"], 4.0);
+        assert_eq!(values["a"], 2.0);
+        let z = z_scores(&values);
+        assert!(z.values().sum::<f64>().abs() < 1e-9);
+
+        let stocks = [
+            stock("A", "银行", 5.0, 10.0),
+            stock("B", "银行", 6.0, 12.0),
+            stock("C", "科技", 40.0, 20.0),
+            stock("D", "科技", 80.0, 8.0),
+            // 亏损公司不参与盈利收益率因子
+            stock("E", "科技", -10.0, -5.0),
+        ];
+        let mut params = StrategyParams::new("factor".to_string(), StrategyType::FactorModel);
+        params.update_params(&HashMap::from([
+            ("weight_book_to_price".to_string(), serde_json::json!(0.0)),
+            ("weight_momentum_6m".to_string(), serde_json::json!(0.0)),
+            ("top_n".to_string(), serde_json::json!(2)),
+            ("rebalance_bars".to_string(), serde_json::json!(2)),
+            ("winsorize".to_string(), serde_json::json!(0.0)),
+        ])).unwrap();
+        let config = FactorModelConfig::from_params(&params);
+        assert_eq!(config.weights.len(), 2);
+
+        let refs: Vec<&StockSnapshot> = stocks.iter().collect();
+        let scores = composite_scores(&refs, &config);
+        let mut ranked: Vec<(&String, &f64)> = scores.iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(a.1));
+        // 银行股估值低, 排名靠前
+        let top: HashSet<&str> = ranked[..2].iter().map(|(code, _)| code.as_str()).collect();
+        assert_eq!(top, HashSet::from(["A", "B"]));
+
+        // 行业中性化后每个行业内的得分和为0, 科技股C在行业内最好
+        let neutral = FactorModelConfig { sector_neutral: true, ..config.clone() };
+        let scores = composite_scores(&refs, &neutral);
+        assert!((scores["A"] + scores["B"]).abs() < 1e-9);
+        assert!(scores["C"] > scores["D"] && scores["C"] > scores["E"]);
+
+        // 每两根行情调仓一次, 卖出落选持仓
+        let data = |day: i64| MarketData {
+            timestamp: Utc.with_ymd_and_hms(2024, 1, 1, 7, 0, 0).unwrap() + Duration::days(day),
+            stocks: stocks.iter().map(|s| (s.code.clone(), s.clone())).collect(),
+        };
+        let mut portfolio = Portfolio::new("test".to_string(), Money::from(100000));
+        portfolio.positions.insert("D".to_string(), Position::new("D".to_string(), "D".to_string(), 1000, Money::from(10)));
+        let mut runner = StrategyRunner::new(create_strategy(&params).unwrap(), SizingModel::FixedCash { cash: 10000.0 });
+
+        let signals = runner.on_bar(&data(0), &portfolio);
+        assert_eq!(signals.len(), 3);
+        assert_eq!((signals[0].code.as_str(), signals[0].action, signals[0].shares()), ("D", SignalAction::Sell, Some(1000)));
+        assert!(signals[1..].iter().all(|s| s.action == SignalAction::Buy && s.shares() == Some(1000)));
+        assert!(runner.on_bar(&data(1), &portfolio).is_empty());
+        assert_eq!(runner.on_bar(&data(2), &portfolio).len(), 3);
+    }
+}
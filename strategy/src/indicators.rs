@@ -0,0 +1,850 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use stockdata::models::StockData;
+use position::market::trade_date;
+use crate::models::{MarketData, StockSnapshot};
+
+// 流式技术指标: 每根行情增量更新, 数据不足时返回None
+pub trait Indicator {
+    type Output: Copy;
+
+    fn update(&mut self, bar: &StockSnapshot) -> Option<Self::Output>;
+    fn value(&self) -> Option<Self::Output>;
+}
+
+// 在一组行情上计算完整的指标序列
+pub fn series<'a, I, B>(indicator: &I, bars: B) -> Vec<Option<I::Output>>
+where
+    I: Indicator + Clone,
+    B: IntoIterator<Item = &'a StockSnapshot>,
+{
+    let mut indicator = indicator.clone();
+    bars.into_iter().map(|bar| indicator.update(bar)).collect()
+}
+
+// 按股票代码分别维护的指标
+#[derive(Debug, Clone)]
+pub struct PerSymbol<I> {
+    template: I,
+    indicators: HashMap<String, I>,
+}
+
+impl<I: Indicator + Clone> PerSymbol<I> {
+    pub fn new(template: I) -> Self {
+        Self {
+            template,
+            indicators: HashMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, bar: &StockSnapshot) -> Option<I::Output> {
+        let template = &self.template;
+        self.indicators.entry(bar.code.clone())
+            .or_insert_with(|| template.clone())
+            .update(bar)
+    }
+
+    // 用一次行情更新所有股票
+    pub fn update_all(&mut self, data: &MarketData) {
+        for bar in data.stocks.values() {
+            self.update(bar);
+        }
+    }
+
+    pub fn value(&self, code: &str) -> Option<I::Output> {
+        self.indicators.get(code).and_then(|i| i.value())
+    }
+
+    pub fn get(&self, code: &str) -> Option<&I> {
+        self.indicators.get(code)
+    }
+}
+
+// 固定长度窗口, 维护和、均值与离差平方和
+// 离差平方和按Welford方法增量更新, 避免平方和相减的精度损失;
+// 每推入period个值按窗口重新计算一次, 消除累计误差 (均摊仍为O(1))
+#[derive(Debug, Clone)]
+struct Window {
+    period: usize,
+    values: VecDeque<f64>,
+    sum: f64,
+    mean: f64,
+    m2: f64,
+    // 距上次重新计算推入的值的个数, 为0表示刚重新计算过
+    since_resync: usize,
+}
+
+impl Window {
+    fn new(period: usize) -> Self {
+        Self {
+            period: period.max(1),
+            values: VecDeque::with_capacity(period.max(1) + 1),
+            sum: 0.0,
+            mean: 0.0,
+            m2: 0.0,
+            since_resync: 0,
+        }
+    }
+
+    // 加入新值, 返回被移出窗口的值
+    fn push(&mut self, value: f64) -> Option<f64> {
+        self.values.push_back(value);
+        self.sum += value;
+        let evicted = if self.values.len() > self.period {
+            let old = self.values.pop_front()?;
+            self.sum -= old;
+            // 窗口长度不变, 用新值替换旧值
+            let prev_mean = self.mean;
+            self.mean += (value - old) / self.period as f64;
+            self.m2 += (value - old) * (value - self.mean + old - prev_mean);
+            Some(old)
+        } else {
+            let delta = value - self.mean;
+            self.mean += delta / self.values.len() as f64;
+            self.m2 += delta * (value - self.mean);
+            None
+        };
+
+        self.since_resync += 1;
+        if self.since_resync >= self.period {
+            self.resync();
+        }
+        evicted
+    }
+
+    // 按窗口内的值重新计算
+    fn resync(&mut self) {
+        let n = self.values.len().max(1) as f64;
+        self.sum = self.values.iter().sum();
+        self.mean = self.sum / n;
+        self.m2 = self.values.iter().map(|v| (v - self.mean).powi(2)).sum();
+        self.since_resync = 0;
+    }
+
+    fn is_full(&self) -> bool {
+        self.values.len() == self.period
+    }
+
+    fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    // 总体标准差
+    fn std_dev(&self) -> f64 {
+        let n = self.values.len().max(1) as f64;
+        (self.m2 / n).max(0.0).sqrt()
+    }
+}
+
+// 简单移动平均
+#[derive(Debug, Clone)]
+pub struct Sma {
+    window: Window,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Self {
+        Self { window: Window::new(period) }
+    }
+
+    pub fn next(&mut self, value: f64) -> Option<f64> {
+        self.window.push(value);
+        self.current()
+    }
+
+    pub fn current(&self) -> Option<f64> {
+        self.window.is_full().then(|| self.window.mean())
+    }
+}
+
+impl Indicator for Sma {
+    type Output = f64;
+
+    fn update(&mut self, bar: &StockSnapshot) -> Option<f64> {
+        self.next(bar.price)
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.current()
+    }
+}
+
+// 指数移动平均, 以前period个值的简单平均作为初值
+#[derive(Debug, Clone)]
+pub struct Ema {
+    period: usize,
+    alpha: f64,
+    count: usize,
+    seed: f64,
+    value: Option<f64>,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Self {
+        Self::with_alpha(period, 2.0 / (period.max(1) as f64 + 1.0))
+    }
+
+    // Wilder平滑 (RSI和ATR使用), alpha = 1/period
+    pub fn wilder(period: usize) -> Self {
+        Self::with_alpha(period, 1.0 / period.max(1) as f64)
+    }
+
+    fn with_alpha(period: usize, alpha: f64) -> Self {
+        Self {
+            period: period.max(1),
+            alpha,
+            count: 0,
+            seed: 0.0,
+            value: None,
+        }
+    }
+
+    pub fn next(&mut self, value: f64) -> Option<f64> {
+        match self.value {
+            Some(prev) => self.value = Some(prev + self.alpha * (value - prev)),
+            None => {
+                self.count += 1;
+                self.seed += value;
+                if self.count == self.period {
+                    self.value = Some(self.seed / self.period as f64);
+                }
+            },
+        }
+        self.value
+    }
+
+    pub fn current(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+impl Indicator for Ema {
+    type Output = f64;
+
+    fn update(&mut self, bar: &StockSnapshot) -> Option<f64> {
+        self.next(bar.price)
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.current()
+    }
+}
+
+// 加权移动平均, 最新值权重为period
+#[derive(Debug, Clone)]
+pub struct Wma {
+    window: Window,
+    weighted_sum: f64,
+}
+
+impl Wma {
+    pub fn new(period: usize) -> Self {
+        Self {
+            window: Window::new(period),
+            weighted_sum: 0.0,
+        }
+    }
+
+    pub fn next(&mut self, value: f64) -> Option<f64> {
+        // 窗口已满时所有旧值权重减1, 新值权重为period
+        let prev_sum = self.window.sum;
+        let evicted = self.window.push(value);
+        if evicted.is_some() {
+            self.weighted_sum += self.window.period as f64 * value - prev_sum;
+        } else {
+            self.weighted_sum += self.window.values.len() as f64 * value;
+        }
+        // 窗口重新计算时同步重算加权和
+        if self.window.since_resync == 0 {
+            self.weighted_sum = self.window.values.iter().enumerate().map(|(i, v)| (i + 1) as f64 * v).sum();
+        }
+        self.current()
+    }
+
+    pub fn current(&self) -> Option<f64> {
+        let n = self.window.period as f64;
+        self.window.is_full().then(|| self.weighted_sum / (n * (n + 1.0) / 2.0))
+    }
+}
+
+impl Indicator for Wma {
+    type Output = f64;
+
+    fn update(&mut self, bar: &StockSnapshot) -> Option<f64> {
+        self.next(bar.price)
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.current()
+    }
+}
+
+// 相对强弱指数 (Wilder平滑)
+#[derive(Debug, Clone)]
+pub struct Rsi {
+    prev: Option<f64>,
+    gain: Ema,
+    loss: Ema,
+}
+
+impl Rsi {
+    pub fn new(period: usize) -> Self {
+        Self {
+            prev: None,
+            gain: Ema::wilder(period),
+            loss: Ema::wilder(period),
+        }
+    }
+
+    pub fn next(&mut self, value: f64) -> Option<f64> {
+        if let Some(prev) = self.prev.replace(value) {
+            let change = value - prev;
+            self.gain.next(change.max(0.0));
+            self.loss.next((-change).max(0.0));
+        }
+        self.current()
+    }
+
+    pub fn current(&self) -> Option<f64> {
+        let (gain, loss) = (self.gain.current()?, self.loss.current()?);
+        Some(if loss == 0.0 {
+            if gain == 0.0 { 50.0 } else { 100.0 }
+        } else {
+            100.0 - 100.0 / (1.0 + gain / loss)
+        })
+    }
+}
+
+impl Indicator for Rsi {
+    type Output = f64;
+
+    fn update(&mut self, bar: &StockSnapshot) -> Option<f64> {
+        self.next(bar.price)
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.current()
+    }
+}
+
+// MACD指标值
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MacdValue {
+    pub macd: f64,
+    pub signal: f64,
+    pub histogram: f64,
+}
+
+// 指数平滑异同移动平均
+#[derive(Debug, Clone)]
+pub struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+    value: Option<MacdValue>,
+}
+
+impl Macd {
+    pub fn new(fast: usize, slow: usize, signal: usize) -> Self {
+        Self {
+            fast: Ema::new(fast),
+            slow: Ema::new(slow),
+            signal: Ema::new(signal),
+            value: None,
+        }
+    }
+
+    pub fn next(&mut self, value: f64) -> Option<MacdValue> {
+        let fast = self.fast.next(value);
+        let slow = self.slow.next(value);
+        if let (Some(fast), Some(slow)) = (fast, slow) {
+            let macd = fast - slow;
+            if let Some(signal) = self.signal.next(macd) {
+                self.value = Some(MacdValue { macd, signal, histogram: macd - signal });
+            }
+        }
+        self.value
+    }
+}
+
+impl Default for Macd {
+    fn default() -> Self {
+        Self::new(12, 26, 9)
+    }
+}
+
+impl Indicator for Macd {
+    type Output = MacdValue;
+
+    fn update(&mut self, bar: &StockSnapshot) -> Option<MacdValue> {
+        self.next(bar.price)
+    }
+
+    fn value(&self) -> Option<MacdValue> {
+        self.value
+    }
+}
+
+// 布林带指标值
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BollingerValue {
+    pub middle: f64,
+    pub upper: f64,
+    pub lower: f64,
+    pub std_dev: f64,
+}
+
+// 布林带: 均线加减k倍总体标准差
+#[derive(Debug, Clone)]
+pub struct Bollinger {
+    window: Window,
+    multiplier: f64,
+}
+
+impl Bollinger {
+    pub fn new(period: usize, multiplier: f64) -> Self {
+        Self {
+            window: Window::new(period),
+            multiplier,
+        }
+    }
+
+    pub fn next(&mut self, value: f64) -> Option<BollingerValue> {
+        self.window.push(value);
+        self.current()
+    }
+
+    pub fn current(&self) -> Option<BollingerValue> {
+        if !self.window.is_full() {
+            return None;
+        }
+        let middle = self.window.mean();
+        let std_dev = self.window.std_dev();
+        Some(BollingerValue {
+            middle,
+            upper: middle + self.multiplier * std_dev,
+            lower: middle - self.multiplier * std_dev,
+            std_dev,
+        })
+    }
+}
+
+impl Indicator for Bollinger {
+    type Output = BollingerValue;
+
+    fn update(&mut self, bar: &StockSnapshot) -> Option<BollingerValue> {
+        self.next(bar.price)
+    }
+
+    fn value(&self) -> Option<BollingerValue> {
+        self.current()
+    }
+}
+
+// 平均真实波幅 (Wilder平滑)
+#[derive(Debug, Clone)]
+pub struct Atr {
+    prev_close: Option<f64>,
+    average: Ema,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Self {
+        Self {
+            prev_close: None,
+            average: Ema::wilder(period),
+        }
+    }
+}
+
+impl Indicator for Atr {
+    type Output = f64;
+
+    fn update(&mut self, bar: &StockSnapshot) -> Option<f64> {
+        let range = bar.high - bar.low;
+        let true_range = match self.prev_close.replace(bar.price) {
+            Some(prev) => range.max((bar.high - prev).abs()).max((bar.low - prev).abs()),
+            None => range,
+        };
+        self.average.next(true_range)
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.average.current()
+    }
+}
+
+// 滑动窗口最大/最小值 (单调队列, 均摊O(1))
+#[derive(Debug, Clone)]
+struct Extremum {
+    period: usize,
+    count: usize,
+    // (序号, 值)
+    values: VecDeque<(usize, f64)>,
+    is_max: bool,
+}
+
+impl Extremum {
+    fn new(period: usize, is_max: bool) -> Self {
+        Self {
+            period: period.max(1),
+            count: 0,
+            values: VecDeque::new(),
+            is_max,
+        }
+    }
+
+    fn push(&mut self, value: f64) -> f64 {
+        while let Some(&(_, last)) = self.values.back() {
+            if (self.is_max && last <= value) || (!self.is_max && last >= value) {
+                self.values.pop_back();
+            } else {
+                break;
+            }
+        }
+        self.values.push_back((self.count, value));
+        self.count += 1;
+        while self.values.front().is_some_and(|&(i, _)| i + self.period < self.count) {
+            self.values.pop_front();
+        }
+        self.values.front().map(|&(_, v)| v).unwrap_or(value)
+    }
+}
+
+// 随机指标值
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StochasticValue {
+    pub k: f64,
+    pub d: f64,
+}
+
+// 随机指标: 原始K经平滑得到K, K的移动平均为D
+#[derive(Debug, Clone)]
+pub struct Stochastic {
+    period: usize,
+    count: usize,
+    highest: Extremum,
+    lowest: Extremum,
+    k: Sma,
+    d: Sma,
+    value: Option<StochasticValue>,
+}
+
+impl Stochastic {
+    pub fn new(period: usize, k_smoothing: usize, d_period: usize) -> Self {
+        Self {
+            period: period.max(1),
+            count: 0,
+            highest: Extremum::new(period, true),
+            lowest: Extremum::new(period, false),
+            k: Sma::new(k_smoothing),
+            d: Sma::new(d_period),
+            value: None,
+        }
+    }
+}
+
+impl Default for Stochastic {
+    // 与TradingView的Stoch(14, 3, 3)一致
+    fn default() -> Self {
+        Self::new(14, 3, 3)
+    }
+}
+
+impl Indicator for Stochastic {
+    type Output = StochasticValue;
+
+    fn update(&mut self, bar: &StockSnapshot) -> Option<StochasticValue> {
+        let highest = self.highest.push(bar.high);
+        let lowest = self.lowest.push(bar.low);
+        self.count += 1;
+        if self.count < self.period {
+            return None;
+        }
+        let raw = if highest > lowest { 100.0 * (bar.price - lowest) / (highest - lowest) } else { 50.0 };
+        if let Some(k) = self.k.next(raw) {
+            if let Some(d) = self.d.next(k) {
+                self.value = Some(StochasticValue { k, d });
+            }
+        }
+        self.value
+    }
+
+    fn value(&self) -> Option<StochasticValue> {
+        self.value
+    }
+}
+
+// 顺势指标: 典型价格偏离均值的程度
+// 与其他指标不同, 每根行情为O(period)而不是O(1): 平均绝对偏差|x - mean|在均值变化时每一项都会变,
+// 没有可增量维护的形式; 常用周期为14~20, 遍历窗口的开销可以忽略
+#[derive(Debug, Clone)]
+pub struct Cci {
+    window: Window,
+    value: Option<f64>,
+}
+
+impl Cci {
+    pub fn new(period: usize) -> Self {
+        Self {
+            window: Window::new(period),
+            value: None,
+        }
+    }
+}
+
+impl Indicator for Cci {
+    type Output = f64;
+
+    fn update(&mut self, bar: &StockSnapshot) -> Option<f64> {
+        let typical = (bar.high + bar.low + bar.price) / 3.0;
+        self.window.push(typical);
+        if !self.window.is_full() {
+            return None;
+        }
+        let mean = self.window.mean();
+        let deviation = self.window.values.iter().map(|v| (v - mean).abs()).sum::<f64>() / self.window.period as f64;
+        self.value = Some(if deviation > 0.0 { (typical - mean) / (0.015 * deviation) } else { 0.0 });
+        self.value
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+// 能量潮: 上涨日累加成交量, 下跌日减去
+#[derive(Debug, Clone, Default)]
+pub struct Obv {
+    prev_close: Option<f64>,
+    value: f64,
+}
+
+impl Indicator for Obv {
+    type Output = f64;
+
+    fn update(&mut self, bar: &StockSnapshot) -> Option<f64> {
+        if let Some(prev) = self.prev_close.replace(bar.price) {
+            if bar.price > prev {
+                self.value += bar.volume;
+            } else if bar.price < prev {
+                self.value -= bar.volume;
+            }
+        }
+        Some(self.value)
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.prev_close.map(|_| self.value)
+    }
+}
+
+// 滚动Z分数: 当前值相对窗口均值的标准差倍数
+#[derive(Debug, Clone)]
+pub struct ZScore {
+    window: Window,
+    value: Option<f64>,
+}
+
+impl ZScore {
+    pub fn new(period: usize) -> Self {
+        Self {
+            window: Window::new(period),
+            value: None,
+        }
+    }
+
+    pub fn next(&mut self, value: f64) -> Option<f64> {
+        self.window.push(value);
+        if self.window.is_full() {
+            let std_dev = self.window.std_dev();
+            self.value = Some(if std_dev > 0.0 { (value - self.window.mean()) / std_dev } else { 0.0 });
+        }
+        self.value
+    }
+
+    pub fn current(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+impl Indicator for ZScore {
+    type Output = f64;
+
+    fn update(&mut self, bar: &StockSnapshot) -> Option<f64> {
+        self.next(bar.price)
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.current()
+    }
+}
+
+// 自算指标与抓取值的对比
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndicatorCheck {
+    pub code: String,
+    pub indicator: String,
+    pub date: NaiveDate,
+    pub computed: f64,
+    pub scraped: f64,
+    pub difference: f64,
+    // 样本天数
+    pub bars: usize,
+}
+
+// 在历史快照上计算RSI(14), CCI(20)和随机指标K, 与最后一天抓取的值对比
+// 每个交易日取最后一次快照作为日线; 快照中的开盘和最高最低价为估算值, CCI和随机指标会有偏差
+pub fn cross_check(history: &[(DateTime<Utc>, Vec<StockData>)]) -> Vec<IndicatorCheck> {
+    let mut daily: BTreeMap<NaiveDate, &Vec<StockData>> = BTreeMap::new();
+    let mut sorted: Vec<&(DateTime<Utc>, Vec<StockData>)> = history.iter().collect();
+    sorted.sort_by_key(|(timestamp, _)| *timestamp);
+    for (timestamp, stocks) in sorted {
+        daily.insert(trade_date(*timestamp), stocks);
+    }
+
+    let mut bars: BTreeMap<&str, Vec<StockSnapshot>> = BTreeMap::new();
+    let mut latest: HashMap<&str, (NaiveDate, &StockData)> = HashMap::new();
+    for (date, stocks) in &daily {
+        for stock in stocks.iter().filter(|s| s.price > 0.0) {
+            bars.entry(stock.code.as_str()).or_default().push(StockSnapshot::from_stock_data(stock));
+            latest.insert(stock.code.as_str(), (*date, stock));
+        }
+    }
+
+    let mut checks = Vec::new();
+    for (code, bars) in &bars {
+        let (date, stock) = latest[code];
+        let mut rsi = Rsi::new(14);
+        let mut cci = Cci::new(20);
+        let mut stochastic = Stochastic::default();
+        for bar in bars {
+            rsi.update(bar);
+            cci.update(bar);
+            stochastic.update(bar);
+        }
+
+        let computed = [
+            ("rsi_14", rsi.value(), stock.rsi_14),
+            ("cci_20", cci.value(), stock.cci_20),
+            ("stochastic_k", stochastic.value().map(|v| v.k), stock.stochastic_k),
+        ];
+        for (indicator, value, scraped) in computed {
+            if let Some(value) = value {
+                checks.push(IndicatorCheck {
+                    code: code.to_string(),
+                    indicator: indicator.to_string(),
+                    date,
+                    computed: value,
+                    scraped,
+                    difference: value - scraped,
+                    bars: bars.len(),
+                });
+            }
+        }
+    }
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::snapshot;
+
+    fn bar(price: f64, high: f64, low: f64, volume: f64) -> StockSnapshot {
+        StockSnapshot { high, low, ..snapshot("600000", price, volume, Default::default()) }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_streaming_matches_full_recomputation() {
+        let prices: Vec<f64> = (0..60).map(|i| 10.0 + (i as f64 * 0.7).sin() * 2.0 + i as f64 * 0.05).collect();
+        let bars: Vec<StockSnapshot> = prices.iter().enumerate()
+            .map(|(i, &p)| bar(p, p + 0.3 + (i % 3) as f64 * 0.1, p - 0.2, 1000.0 + i as f64))
+            .collect();
+        let period = 5;
+
+        let sma = series(&Sma::new(period), &bars);
+        let wma = series(&Wma::new(period), &bars);
+        let bollinger = series(&Bollinger::new(period, 2.0), &bars);
+        let zscore = series(&ZScore::new(period), &bars);
+        let stochastic = series(&Stochastic::new(period, 1, 1), &bars);
+        let cci = series(&Cci::new(period), &bars);
+        assert!(sma[period - 2].is_none());
+
+        for i in period - 1..bars.len() {
+            let window = &prices[i + 1 - period..=i];
+            let mean = window.iter().sum::<f64>() / period as f64;
+            let std = (window.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / period as f64).sqrt();
+            let weighted = window.iter().enumerate().map(|(j, p)| (j + 1) as f64 * p).sum::<f64>() / 15.0;
+            assert!(close(sma[i].unwrap(), mean));
+            assert!(close(wma[i].unwrap(), weighted));
+            assert!(close(bollinger[i].unwrap().upper, mean + 2.0 * std));
+            assert!(close(zscore[i].unwrap(), (prices[i] - mean) / std));
+
+            let highest = bars[i + 1 - period..=i].iter().map(|b| b.high).fold(f64::MIN, f64::max);
+            let lowest = bars[i + 1 - period..=i].iter().map(|b| b.low).fold(f64::MAX, f64::min);
+            assert!(close(stochastic[i].unwrap().k, 100.0 * (prices[i] - lowest) / (highest - lowest)));
+
+            let typical: Vec<f64> = bars[i + 1 - period..=i].iter().map(|b| (b.high + b.low + b.price) / 3.0).collect();
+            let tp_mean = typical.iter().sum::<f64>() / period as f64;
+            let deviation = typical.iter().map(|t| (t - tp_mean).abs()).sum::<f64>() / period as f64;
+            assert!(close(cci[i].unwrap(), (typical[period - 1] - tp_mean) / (0.015 * deviation)));
+        }
+
+        // 长序列上滚动和与标准差没有累计误差
+        let drifting: Vec<f64> = (0..20000).map(|i| 1e6 + (i as f64 * 0.37).sin() * 3.0).collect();
+        let mut bollinger = Bollinger::new(period, 2.0);
+        let mut wma = Wma::new(period);
+        let mut last = None;
+        for &p in &drifting {
+            last = bollinger.next(p);
+            wma.next(p);
+        }
+        let window = &drifting[drifting.len() - period..];
+        let mean = window.iter().sum::<f64>() / period as f64;
+        let std = (window.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / period as f64).sqrt();
+        let weighted = window.iter().enumerate().map(|(j, p)| (j + 1) as f64 * p).sum::<f64>() / 15.0;
+        assert!((last.unwrap().upper - (mean + 2.0 * std)).abs() < 1e-6);
+        assert!((wma.current().unwrap() - weighted).abs() < 1e-6);
+
+        // EMA以简单平均为初值
+        let mut ema = Ema::new(3);
+        assert_eq!(ema.next(1.0), None);
+        assert_eq!(ema.next(2.0), None);
+        assert_eq!(ema.next(3.0), Some(2.0));
+        assert_eq!(ema.next(6.0), Some(4.0));
+
+        // RSI: 连续上涨为100, 涨跌相同为50
+        let mut rsi = Rsi::new(2);
+        rsi.next(10.0);
+        rsi.next(11.0);
+        assert_eq!(rsi.next(12.0), Some(100.0));
+        assert!(close(rsi.next(11.0).unwrap(), 100.0 - 100.0 / (1.0 + 0.5 / 0.5)));
+
+        // ATR第一根用最高减最低, 之后考虑跳空
+        let mut atr = Atr::new(2);
+        assert_eq!(atr.update(&bar(10.0, 11.0, 9.0, 0.0)), None);
+        assert_eq!(atr.update(&bar(13.0, 14.0, 12.0, 0.0)), Some(3.0));
+
+        let obv = series(&Obv::default(), &[bar(10.0, 10.0, 10.0, 100.0), bar(11.0, 11.0, 11.0, 50.0), bar(10.5, 10.5, 10.5, 30.0)]);
+        assert_eq!(obv, vec![Some(0.0), Some(50.0), Some(20.0)]);
+
+        let macd = series(&Macd::default(), &bars);
+        assert!(macd[32].is_none() && macd[33].is_some());
+
+        // 按股票分别计算
+        let mut per_symbol = PerSymbol::new(Sma::new(2));
+        let mut other = bar(100.0, 100.0, 100.0, 0.0);
+        other.code = "000001".to_string();
+        per_symbol.update(&bars[0]);
+        per_symbol.update(&other);
+        per_symbol.update(&bars[1]);
+        assert!(close(per_symbol.value("600000").unwrap(), (prices[0] + prices[1]) / 2.0));
+        assert_eq!(per_symbol.value("000001"), None);
+    }
+}
@@ -1,3 +1,14 @@
 pub mod models;
+pub mod schema;
 pub mod strategy;
-pub mod backtest; 
\ No newline at end of file
+pub mod context;
+pub mod backtest;
+pub mod sizing;
+pub mod metrics;
+pub mod indicators;
+pub mod optimizer;
+pub mod walkforward;
+pub mod factor;
+pub mod pairs;
+#[cfg(test)]
+pub(crate) mod testing;
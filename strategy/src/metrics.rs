@@ -0,0 +1,309 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::BTreeMap;
+use position::lots::match_lots;
+use position::market::trade_date;
+use position::models::{Portfolio, Transaction, TransactionType};
+use position::money::{to_f64, Money};
+use position::performance::{annualize_return, annualized_volatility, daily_returns, max_drawdown, sharpe_ratio, TRADING_DAYS_PER_YEAR};
+use crate::models::BacktestTrade;
+
+// 净值曲线和交易记录的绩效指标
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PerformanceMetrics {
+    pub total_return: f64,
+    // 按交易日年化的收益率
+    pub annualized_return: f64,
+    // 年化波动率
+    pub volatility: f64,
+    pub sharpe_ratio: f64,
+    pub sortino_ratio: f64,
+    // 年化收益率 / 最大回撤
+    pub calmar_ratio: f64,
+    pub max_drawdown: f64,
+    // 最长回撤持续交易日数 (从前高到恢复, 未恢复时算到区间结束)
+    pub max_drawdown_duration: usize,
+    // 有持仓的交易日占比
+    pub exposure: f64,
+    // 年化换手率 (单边成交金额 / 平均净值)
+    pub turnover: f64,
+    pub win_rate: f64,
+    // 总盈利 / 总亏损, 没有亏损交易时为0
+    pub profit_factor: f64,
+    pub total_trades: usize,
+    pub winning_trades: usize,
+    pub losing_trades: usize,
+    pub avg_profit: f64,
+    pub avg_loss: f64,
+}
+
+// 每个交易日取最后一个净值
+pub fn daily_equity(curve: &[(DateTime<Utc>, f64)]) -> Vec<(NaiveDate, f64)> {
+    let mut days: BTreeMap<NaiveDate, (DateTime<Utc>, f64)> = BTreeMap::new();
+    for (timestamp, value) in curve {
+        let entry = days.entry(trade_date(*timestamp)).or_insert((*timestamp, *value));
+        if *timestamp >= entry.0 {
+            *entry = (*timestamp, *value);
+        }
+    }
+    days.into_iter().map(|(date, (_, value))| (date, value)).collect()
+}
+
+// 逐期收益率
+pub fn period_returns(values: &[f64]) -> Vec<f64> {
+    values.windows(2)
+        .filter(|w| w[0] > 0.0)
+        .map(|w| w[1] / w[0] - 1.0)
+        .collect()
+}
+
+// 年化索提诺比率, 只用低于无风险收益的部分计算下行波动
+pub fn sortino_ratio(returns: &[f64], risk_free_rate: f64) -> f64 {
+    if returns.is_empty() {
+        return 0.0;
+    }
+    let target = risk_free_rate / TRADING_DAYS_PER_YEAR;
+    let downside = (returns.iter().map(|r| (r - target).min(0.0).powi(2)).sum::<f64>() / returns.len() as f64).sqrt();
+    if downside == 0.0 {
+        return 0.0;
+    }
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    (mean - target) / downside * TRADING_DAYS_PER_YEAR.sqrt()
+}
+
+// 卡玛比率
+pub fn calmar_ratio(annualized_return: f64, max_drawdown: f64) -> f64 {
+    if max_drawdown <= 0.0 {
+        return 0.0;
+    }
+    annualized_return / max_drawdown
+}
+
+// 最长回撤持续期数
+pub fn max_drawdown_duration(values: &[f64]) -> usize {
+    let mut peak = f64::MIN;
+    let mut peak_index = 0;
+    let mut longest = 0;
+    for (i, value) in values.iter().enumerate() {
+        if *value >= peak {
+            peak = *value;
+            peak_index = i;
+        } else {
+            longest = longest.max(i - peak_index);
+        }
+    }
+    longest
+}
+
+// 已平仓交易的统计
+fn trade_stats(trades: &[BacktestTrade], metrics: &mut PerformanceMetrics) {
+    let closed: Vec<f64> = trades.iter().filter(|t| t.exit_date.is_some()).filter_map(|t| t.profit_loss).collect();
+    let profits: Vec<f64> = closed.iter().copied().filter(|p| *p > 0.0).collect();
+    let losses: Vec<f64> = closed.iter().copied().filter(|p| *p < 0.0).collect();
+    let mean = |values: &[f64]| if values.is_empty() { 0.0 } else { values.iter().sum::<f64>() / values.len() as f64 };
+    let gross_loss = -losses.iter().sum::<f64>();
+
+    metrics.total_trades = closed.len();
+    metrics.winning_trades = profits.len();
+    metrics.losing_trades = losses.len();
+    metrics.win_rate = if closed.is_empty() { 0.0 } else { profits.len() as f64 / closed.len() as f64 };
+    metrics.profit_factor = if gross_loss > 0.0 { profits.iter().sum::<f64>() / gross_loss } else { 0.0 };
+    metrics.avg_profit = mean(&profits);
+    metrics.avg_loss = mean(&losses);
+}
+
+// 按净值曲线和交易记录计算绩效指标, 净值曲线不应包含外部资金流动
+pub fn compute_metrics(curve: &[(DateTime<Utc>, f64)], trades: &[BacktestTrade], risk_free_rate: f64) -> PerformanceMetrics {
+    let mut metrics = PerformanceMetrics::default();
+    trade_stats(trades, &mut metrics);
+
+    let days = daily_equity(curve);
+    let values: Vec<f64> = days.iter().map(|(_, v)| *v).collect();
+    let (first, last) = match (values.first(), values.last()) {
+        (Some(first), Some(last)) if *first > 0.0 => (*first, *last),
+        _ => return metrics,
+    };
+    let returns = period_returns(&values);
+
+    metrics.total_return = last / first - 1.0;
+    metrics.annualized_return = annualize_return(metrics.total_return, returns.len());
+    metrics.volatility = annualized_volatility(&returns);
+    metrics.sharpe_ratio = sharpe_ratio(&returns, risk_free_rate);
+    metrics.sortino_ratio = sortino_ratio(&returns, risk_free_rate);
+    metrics.max_drawdown = max_drawdown(&values);
+    metrics.calmar_ratio = calmar_ratio(metrics.annualized_return, metrics.max_drawdown);
+    metrics.max_drawdown_duration = max_drawdown_duration(&values);
+
+    // 收盘时有未平仓交易的交易日
+    let invested = days.iter()
+        .filter(|(date, _)| trades.iter().any(|t| {
+            trade_date(t.entry_date) <= *date && t.exit_date.is_none_or(|exit| trade_date(exit) > *date)
+        }))
+        .count();
+    metrics.exposure = invested as f64 / days.len() as f64;
+
+    let traded: f64 = trades.iter()
+        .map(|t| t.entry_price * t.entry_amount + t.exit_price.unwrap_or(0.0) * t.exit_amount.unwrap_or(0.0))
+        .sum::<f64>() / 2.0;
+    let average = values.iter().sum::<f64>() / values.len() as f64;
+    if average > 0.0 && !returns.is_empty() {
+        metrics.turnover = traded / average * TRADING_DAYS_PER_YEAR / returns.len() as f64;
+    }
+
+    metrics
+}
+
+// 把组合的交易按先进先出匹配为交易记录, 未卖出的批次作为未平仓交易
+pub fn portfolio_trades(transactions: &[Transaction]) -> Vec<BacktestTrade> {
+    let mut by_code: BTreeMap<&str, Vec<Transaction>> = BTreeMap::new();
+    for transaction in transactions {
+        by_code.entry(transaction.code.as_str()).or_default().push(transaction.clone());
+    }
+
+    let mut trades = Vec::new();
+    for (code, transactions) in by_code {
+        let (open, closed) = match_lots(&transactions);
+        for closed in closed {
+            let quantity = closed.lot.amount as f64;
+            // 卖出费用按数量分摊
+            let costs = to_f64(closed.sell.fee.unwrap_or_default() + closed.sell.tax.unwrap_or_default())
+                * closed.lot.amount as f64 / closed.sell.amount.max(1) as f64;
+            let entry_price = to_f64(closed.lot.cost);
+            let exit_price = to_f64(closed.sell.price);
+            let profit_loss = (exit_price - entry_price) * quantity - costs;
+            trades.push(BacktestTrade {
+                code: code.to_string(),
+                entry_date: closed.lot.acquired_at,
+                entry_price,
+                entry_amount: quantity,
+                exit_date: Some(closed.sell.timestamp),
+                exit_price: Some(exit_price),
+                exit_amount: Some(quantity),
+                profit_loss: Some(profit_loss),
+                profit_loss_percent: if entry_price > 0.0 { Some(profit_loss / (entry_price * quantity) * 100.0) } else { None },
+                exit_reason: closed.sell.note.clone(),
+            });
+        }
+        for lot in open {
+            trades.push(BacktestTrade {
+                code: code.to_string(),
+                entry_date: lot.acquired_at,
+                entry_price: to_f64(lot.cost),
+                entry_amount: lot.amount as f64,
+                exit_date: None,
+                exit_price: None,
+                exit_amount: None,
+                profit_loss: None,
+                profit_loss_percent: None,
+                exit_reason: None,
+            });
+        }
+    }
+    trades.sort_by_key(|t| t.entry_date);
+    trades
+}
+
+// 实盘组合的绩效指标: 用剔除资金流动的净值指数作为净值曲线
+pub fn portfolio_metrics(portfolio: &Portfolio, risk_free_rate: f64) -> Result<PerformanceMetrics, String> {
+    let first = portfolio.nav_history.first().ok_or_else(|| "没有净值记录".to_string())?;
+
+    let mut curve = vec![(first.timestamp, first.nav)];
+    let mut value = first.nav;
+    for (point, r) in portfolio.nav_history.iter().skip(1).zip(daily_returns(&portfolio.nav_history)) {
+        value *= 1.0 + r;
+        curve.push((point.timestamp, value));
+    }
+
+    let transactions = portfolio.transaction_history()?;
+    let trades = portfolio_trades(&transactions);
+    let mut metrics = compute_metrics(&curve, &trades, risk_free_rate);
+
+    // 换手率按实际净值计算
+    let average = portfolio.nav_history.iter().map(|p| p.nav).sum::<f64>() / portfolio.nav_history.len() as f64;
+    let periods = portfolio.nav_history.len().saturating_sub(1);
+    let traded: Money = transactions.iter()
+        .filter(|t| matches!(t.transaction_type, TransactionType::Buy | TransactionType::Sell))
+        .map(|t| t.total_value())
+        .sum();
+    metrics.turnover = if average > 0.0 && periods > 0 {
+        to_f64(traded) / 2.0 / average * TRADING_DAYS_PER_YEAR / periods as f64
+    } else {
+        0.0
+    };
+    Ok(metrics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn curve(values: &[f64]) -> Vec<(DateTime<Utc>, f64)> {
+        values.iter().enumerate()
+            .map(|(i, v)| (Utc.with_ymd_and_hms(2024, 1, 1, 7, 0, 0).unwrap() + chrono::Duration::days(i as i64), *v))
+            .collect()
+    }
+
+    fn trade(day: i64, entry: f64, exit: Option<(i64, f64)>, amount: f64) -> BacktestTrade {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 7, 0, 0).unwrap();
+        BacktestTrade {
+            code: "600000".to_string(),
+            entry_date: start + chrono::Duration::days(day),
+            entry_price: entry,
+            entry_amount: amount,
+            exit_date: exit.map(|(d, _)| start + chrono::Duration::days(d)),
+            exit_price: exit.map(|(_, p)| p),
+            exit_amount: exit.map(|_| amount),
+            profit_loss: exit.map(|(_, p)| (p - entry) * amount),
+            profit_loss_percent: None,
+            exit_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_drawdown_and_ratios() {
+        let values = [100.0, 110.0, 99.0, 104.5, 121.0, 115.0];
+        assert!((max_drawdown(&values) - 0.1).abs() < 1e-12);
+        // 110之后经过2期恢复, 121之后1期未恢复
+        assert_eq!(max_drawdown_duration(&values), 2);
+        assert_eq!(max_drawdown_duration(&[1.0, 2.0, 3.0]), 0);
+
+        let returns = [0.01, -0.02, 0.03, -0.01];
+        // 下行偏差 sqrt((0.0004 + 0.0001) / 4)
+        let expected = 0.0025 / (0.0005f64 / 4.0).sqrt() * 252f64.sqrt();
+        assert!((sortino_ratio(&returns, 0.0) - expected).abs() < 1e-9);
+        assert_eq!(sortino_ratio(&[0.01, 0.02], 0.0), 0.0);
+        assert!((calmar_ratio(0.3, 0.15) - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_compute_metrics() {
+        // 同一交易日的多个净值只取最后一个
+        let mut points = curve(&[100.0, 110.0, 99.0, 121.0]);
+        points.insert(0, (points[0].0 - chrono::Duration::hours(1), 150.0));
+        let trades = vec![
+            trade(0, 10.0, Some((1, 11.0)), 1000.0),
+            trade(1, 10.0, Some((2, 9.0)), 500.0),
+            trade(3, 12.0, None, 100.0),
+        ];
+
+        let metrics = compute_metrics(&points, &trades, 0.0);
+        assert!((metrics.total_return - 0.21).abs() < 1e-12);
+        assert!((metrics.annualized_return - (1.21f64.powf(252.0 / 3.0) - 1.0)).abs() < 1e-6);
+        assert!((metrics.max_drawdown - 0.1).abs() < 1e-12);
+        assert_eq!(metrics.max_drawdown_duration, 1);
+        assert_eq!(metrics.total_trades, 2);
+        assert_eq!(metrics.winning_trades, 1);
+        assert_eq!(metrics.win_rate, 0.5);
+        assert!((metrics.profit_factor - 2.0).abs() < 1e-12);
+        assert_eq!(metrics.avg_loss, -500.0);
+        // 第三个交易日收盘时空仓
+        assert_eq!(metrics.exposure, 0.75);
+        // 单边成交 (10000 + 11000 + 5000 + 4500 + 1200) / 2, 平均净值107.5
+        let expected_turnover = 15850.0 / 107.5 * 252.0 / 3.0;
+        assert!((metrics.turnover - expected_turnover).abs() < 1e-6);
+
+        let empty = compute_metrics(&[], &[], 0.0);
+        assert_eq!(empty.total_return, 0.0);
+    }
+}
@@ -1,6 +1,12 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
+use position::money::Shares;
+use position::risk::RiskLimits;
+use position::valuation::Quote;
+use stockdata::models::StockData;
+use crate::schema::default_params;
+use crate::sizing::SizingModel;
 
 // 策略类型
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -28,6 +34,8 @@ pub struct StrategyParams {
     pub max_drawdown: f64,          // 最大回撤限制
     pub stop_loss: f64,             // 止损比例
     pub take_profit: f64,           // 止盈比例
+    #[serde(default)]
+    pub sizing: SizingModel,        // 仓位模型
     
     // 策略特定参数
     pub params: HashMap<String, serde_json::Value>,
@@ -46,7 +54,17 @@ impl StrategyParams {
             max_drawdown: 0.2,
             stop_loss: 0.05,
             take_profit: 0.1,
-            params: HashMap::new(),
+            sizing: SizingModel::default(),
+            params: default_params(strategy_type),
+        }
+    }
+    
+    // 策略风控参数对应的组合风控限制
+    pub fn risk_limits(&self) -> RiskLimits {
+        RiskLimits {
+            max_position_weight: Some(self.max_position_size),
+            max_drawdown: Some(self.max_drawdown),
+            ..Default::default()
         }
     }
     
@@ -90,6 +108,19 @@ pub struct BacktestResult {
     pub max_drawdown: f64,
     pub win_rate: f64,
     pub profit_factor: f64,
+    #[serde(default)]
+    pub sortino_ratio: f64,
+    #[serde(default)]
+    pub calmar_ratio: f64,
+    // 最长回撤持续交易日数
+    #[serde(default)]
+    pub max_drawdown_duration: usize,
+    // 有持仓的交易日占比
+    #[serde(default)]
+    pub exposure: f64,
+    // 年化换手率
+    #[serde(default)]
+    pub turnover: f64,
     
     // 交易统计
     pub total_trades: usize,
@@ -130,12 +161,32 @@ pub struct Signal {
     pub strength: f64, // 信号强度, 0-1
 }
 
+impl Signal {
+    // 交易股数, amount以浮点数序列化, 只在这里与股数互相转换
+    pub fn shares(&self) -> Option<Shares> {
+        self.amount.map(|amount| amount.max(0.0).round() as Shares)
+    }
+
+    pub fn set_shares(&mut self, shares: Shares) {
+        self.amount = Some(shares as f64);
+    }
+
+    pub fn with_shares(mut self, shares: Shares) -> Self {
+        self.set_shares(shares);
+        self
+    }
+}
+
 // 信号动作
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum SignalAction {
     Buy,
     Sell,
     Hold,
+    // 融券卖出 (需要信用账户)
+    Short,
+    // 买券还券
+    Cover,
 }
 
 // 市场数据
@@ -157,4 +208,116 @@ pub struct StockSnapshot {
     pub volume: f64,
     pub turnover: f64,
     pub change_percent: f64,
-} 
\ No newline at end of file
+    // 近一月日波动率 (百分比)
+    #[serde(default)]
+    pub volatility_1m: f64,
+    // 平均真实波幅
+    #[serde(default)]
+    pub atr: Option<f64>,
+    // 基本面数据
+    #[serde(default)]
+    pub fundamentals: Fundamentals,
+}
+
+// 因子模型使用的基本面数据, 缺失的数据为None
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Fundamentals {
+    pub sector: Option<String>,
+    pub market_cap: Option<f64>,
+    pub pe_ratio: Option<f64>,
+    pub price_to_book: Option<f64>,
+    pub roe: Option<f64>,
+    pub roic: Option<f64>,
+    pub gross_margin: Option<f64>,
+    pub operating_margin: Option<f64>,
+    pub net_margin: Option<f64>,
+    pub revenue_growth: Option<f64>,
+    pub earnings_growth: Option<f64>,
+    pub dividend_yield: Option<f64>,
+    pub performance_3m: Option<f64>,
+    pub performance_6m: Option<f64>,
+    pub performance_1y: Option<f64>,
+    pub volatility_1m: Option<f64>,
+}
+
+// 抓取数据中0表示缺失
+fn present(value: f64) -> Option<f64> {
+    (value != 0.0 && value.is_finite()).then_some(value)
+}
+
+impl Fundamentals {
+    pub fn from_stock_data(stock: &StockData) -> Self {
+        Self {
+            sector: Some(stock.sector.trim().to_string()).filter(|s| !s.is_empty()),
+            market_cap: present(stock.market_cap as f64),
+            pe_ratio: present(stock.pe_ratio),
+            price_to_book: present(stock.price_to_book),
+            roe: present(stock.roe),
+            roic: present(stock.roic),
+            gross_margin: present(stock.gross_margin),
+            operating_margin: present(stock.operating_margin),
+            net_margin: present(stock.pure_margin),
+            revenue_growth: present(stock.revenue_growth),
+            earnings_growth: present(stock.earnings_growth),
+            dividend_yield: present(stock.dividend_yield),
+            performance_3m: present(stock.performance_3m),
+            performance_6m: present(stock.performance_6m),
+            performance_1y: present(stock.performance_1y),
+            volatility_1m: present(stock.volatility_1m),
+        }
+    }
+}
+
+impl StockSnapshot {
+    // 由抓取的股票数据生成快照, 数据中没有开盘价和最高最低价, 用昨收和跳空估算
+    pub fn from_stock_data(stock: &StockData) -> Self {
+        let prev_close = if stock.change_percent > -100.0 {
+            stock.price / (1.0 + stock.change_percent / 100.0)
+        } else {
+            stock.price
+        };
+        let open = if stock.gap != 0.0 { prev_close * (1.0 + stock.gap / 100.0) } else { prev_close };
+        Self {
+            code: stock.code.clone(),
+            name: stock.name.clone(),
+            price: stock.price,
+            open,
+            high: open.max(stock.price),
+            low: open.min(stock.price),
+            volume: stock.volume as f64,
+            turnover: stock.volume as f64 * stock.price,
+            change_percent: stock.change_percent,
+            volatility_1m: stock.volatility_1m,
+            atr: None,
+            fundamentals: Fundamentals::from_stock_data(stock),
+        }
+    }
+}
+
+impl MarketData {
+    // 由一次抓取的快照生成市场数据, 忽略没有价格的股票
+    pub fn from_stock_data(timestamp: DateTime<Utc>, stocks: &[StockData]) -> Self {
+        Self {
+            timestamp,
+            stocks: stocks.iter()
+                .filter(|s| s.price > 0.0)
+                .map(|s| (s.code.clone(), StockSnapshot::from_stock_data(s)))
+                .collect(),
+        }
+    }
+
+    // 转换为持仓估值使用的报价
+    pub fn quotes(&self) -> HashMap<String, Quote> {
+        self.stocks.values()
+            .map(|s| (s.code.clone(), Quote {
+                code: s.code.clone(),
+                name: Some(s.name.clone()),
+                price: s.price,
+                change_percent: Some(s.change_percent),
+                volume: Some(s.volume),
+                sector: None,
+                market_cap: None,
+            }))
+            .collect()
+    }
+}
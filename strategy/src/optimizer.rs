@@ -0,0 +1,341 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use log::info;
+use crate::backtest::{run_backtest, BacktestConfig};
+use crate::models::{BacktestResult, MarketData, StrategyParams};
+use crate::schema::{param_schema, validate_params, ParamSpec, ParamType};
+use crate::strategy::create_strategy;
+
+// 单次优化允许的最大参数组合数
+pub const MAX_CANDIDATES: usize = 5000;
+
+// 参数取值范围
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ParamRange {
+    // 枚举取值
+    Values { values: Vec<Value> },
+    // 区间, 网格搜索按步长取值, 随机搜索在区间内均匀抽样
+    Range { min: f64, max: f64, step: Option<f64> },
+}
+
+// 搜索方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SearchMethod {
+    Grid,
+    Random { samples: usize, seed: Option<u64> },
+}
+
+// 优化目标
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Objective {
+    #[default]
+    SharpeRatio,
+    SortinoRatio,
+    CalmarRatio,
+    TotalReturn,
+    AnnualizedReturn,
+    // 总收益/最大回撤
+    ReturnOverDrawdown,
+    ProfitFactor,
+    WinRate,
+}
+
+impl Objective {
+    pub fn score(&self, result: &BacktestResult) -> f64 {
+        match self {
+            Objective::SharpeRatio => result.sharpe_ratio,
+            Objective::SortinoRatio => result.sortino_ratio,
+            Objective::CalmarRatio => result.calmar_ratio,
+            Objective::TotalReturn => result.total_return,
+            Objective::AnnualizedReturn => result.annualized_return,
+            Objective::ReturnOverDrawdown => {
+                if result.max_drawdown <= 0.0 {
+                    0.0
+                } else {
+                    result.total_return / result.max_drawdown
+                }
+            },
+            Objective::ProfitFactor => result.profit_factor,
+            Objective::WinRate => result.win_rate,
+        }
+    }
+}
+
+// 优化设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizationConfig {
+    // 参与搜索的参数, 其余参数沿用策略当前值
+    pub ranges: BTreeMap<String, ParamRange>,
+    pub method: SearchMethod,
+    #[serde(default)]
+    pub objective: Objective,
+    #[serde(default)]
+    pub backtest: BacktestConfig,
+    // 并行线程数, 默认为CPU核数
+    pub threads: Option<usize>,
+}
+
+// 一组参数的回测结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizationRun {
+    pub rank: usize,
+    pub params: HashMap<String, Value>,
+    pub score: f64,
+    pub total_return: f64,
+    pub annualized_return: f64,
+    pub sharpe_ratio: f64,
+    pub max_drawdown: f64,
+    pub win_rate: f64,
+    pub total_trades: usize,
+    // 回测失败的原因
+    pub error: Option<String>,
+}
+
+// SplitMix64伪随机数, 固定种子时结果可复现
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // [0, 1)区间的均匀分布
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// 按参数类型转换数值
+fn to_value(spec: &ParamSpec, number: f64) -> Value {
+    match spec.param_type {
+        ParamType::Integer => Value::from(number.round() as i64),
+        _ => Value::from(number),
+    }
+}
+
+// 网格搜索时参数的全部取值
+fn grid_values(name: &str, spec: &ParamSpec, range: &ParamRange) -> Result<Vec<Value>, String> {
+    let values = match range {
+        ParamRange::Values { values } => values.clone(),
+        ParamRange::Range { min, max, step } => {
+            let step = step.filter(|s| *s > 0.0).ok_or_else(|| format!("参数{}的网格搜索需要大于0的步长", name))?;
+            if max < min {
+                return Err(format!("参数{}的范围无效", name));
+            }
+            let count = ((max - min) / step + 1e-9).floor() as usize + 1;
+            if count > MAX_CANDIDATES {
+                return Err(format!("参数{}的取值过多", name));
+            }
+            (0..count).map(|i| to_value(spec, min + step * i as f64)).collect()
+        },
+    };
+
+    // 整数参数取整后去重
+    let mut seen = HashSet::new();
+    let values: Vec<Value> = values.into_iter().filter(|v| seen.insert(v.to_string())).collect();
+    if values.is_empty() {
+        return Err(format!("参数{}没有取值", name));
+    }
+    Ok(values)
+}
+
+// 生成待回测的参数组合, 每组都按参数定义校验
+pub fn candidates(params: &StrategyParams, config: &OptimizationConfig) -> Result<Vec<HashMap<String, Value>>, String> {
+    if config.ranges.is_empty() {
+        return Err("没有指定参数范围".to_string());
+    }
+    let schema = param_schema(params.strategy_type);
+    let mut specs = Vec::new();
+    for (name, range) in &config.ranges {
+        let spec = schema.iter().find(|s| s.name == name.as_str())
+            .ok_or_else(|| format!("策略类型{:?}没有参数{}", params.strategy_type, name))?;
+        specs.push((name, spec, range));
+    }
+
+    let mut combinations: Vec<Vec<(String, Value)>> = Vec::new();
+    match config.method {
+        SearchMethod::Grid => {
+            combinations.push(Vec::new());
+            for (name, spec, range) in &specs {
+                let values = grid_values(name, spec, range)?;
+                if combinations.len() * values.len() > MAX_CANDIDATES {
+                    return Err(format!("参数组合超过{}个, 请缩小范围或使用随机搜索", MAX_CANDIDATES));
+                }
+                combinations = combinations.iter()
+                    .flat_map(|combination| values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.push((name.to_string(), value.clone()));
+                        combination
+                    }))
+                    .collect();
+            }
+        },
+        SearchMethod::Random { samples, seed } => {
+            if samples == 0 || samples > MAX_CANDIDATES {
+                return Err(format!("随机搜索的样本数应在1到{}之间", MAX_CANDIDATES));
+            }
+            let seed = seed.unwrap_or_else(|| chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64);
+            let mut rng = SplitMix64(seed);
+            for _ in 0..samples {
+                let mut combination = Vec::new();
+                for (name, spec, range) in &specs {
+                    let value = match range {
+                        ParamRange::Values { values } if !values.is_empty() => values[(rng.next_u64() % values.len() as u64) as usize].clone(),
+                        ParamRange::Range { min, max, .. } if max >= min => to_value(spec, min + (max - min) * rng.next_f64()),
+                        _ => return Err(format!("参数{}的范围无效", name)),
+                    };
+                    combination.push((name.to_string(), value));
+                }
+                combinations.push(combination);
+            }
+        },
+    }
+
+    let mut seen = HashSet::new();
+    let mut result = Vec::new();
+    for combination in combinations {
+        let mut candidate = params.params.clone();
+        candidate.extend(combination);
+        let candidate = validate_params(params.strategy_type, &candidate)?;
+        let mut key: Vec<String> = candidate.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        key.sort();
+        if seen.insert(key.join(",")) {
+            result.push(candidate);
+        }
+    }
+    Ok(result)
+}
+
+// 用一组参数回测
+fn evaluate(params: &StrategyParams, candidate: &HashMap<String, Value>, history: &[MarketData], config: &OptimizationConfig) -> OptimizationRun {
+    let mut params = params.clone();
+    params.params = candidate.clone();
+    let result = create_strategy(&params).and_then(|strategy| run_backtest(strategy, &params, history, &config.backtest));
+
+    match result {
+        Ok(result) => OptimizationRun {
+            rank: 0,
+            params: candidate.clone(),
+            score: config.objective.score(&result),
+            total_return: result.total_return,
+            annualized_return: result.annualized_return,
+            sharpe_ratio: result.sharpe_ratio,
+            max_drawdown: result.max_drawdown,
+            win_rate: result.win_rate,
+            total_trades: result.total_trades,
+            error: None,
+        },
+        Err(e) => OptimizationRun {
+            rank: 0,
+            params: candidate.clone(),
+            score: 0.0,
+            total_return: 0.0,
+            annualized_return: 0.0,
+            sharpe_ratio: 0.0,
+            max_drawdown: 0.0,
+            win_rate: 0.0,
+            total_trades: 0,
+            error: Some(e),
+        },
+    }
+}
+
+// 多线程回测全部参数组合, 每完成一组progress加1; 结果按目标从高到低排名, 失败的排在最后
+pub fn run_optimization(
+    params: &StrategyParams,
+    candidates: &[HashMap<String, Value>],
+    history: &[MarketData],
+    config: &OptimizationConfig,
+    progress: &AtomicUsize,
+) -> Vec<OptimizationRun> {
+    // 线程数不超过CPU核数和参数组合数
+    let available = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let threads = config.threads
+        .unwrap_or(available)
+        .clamp(1, available.min(candidates.len()).max(1));
+    info!("参数优化: {} 组参数, {} 个线程", candidates.len(), threads);
+
+    let next = AtomicUsize::new(0);
+    let runs = Mutex::new(Vec::with_capacity(candidates.len()));
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let candidate = match candidates.get(index) {
+                    Some(candidate) => candidate,
+                    None => break,
+                };
+                let run = evaluate(params, candidate, history, config);
+                runs.lock().unwrap().push(run);
+                progress.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+    });
+
+    let mut runs = runs.into_inner().unwrap_or_default();
+    runs.sort_by(|a, b| a.error.is_some().cmp(&b.error.is_some()).then(b.score.total_cmp(&a.score)));
+    for (i, run) in runs.iter_mut().enumerate() {
+        run.rank = i + 1;
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::models::StrategyType;
+    use crate::testing::bar;
+
+    #[test]
+    fn test_grid_and_random_search() {
+        let params = StrategyParams::new("momentum".to_string(), StrategyType::Momentum);
+        let history: Vec<MarketData> = (0..40).map(|i| bar(i, 10.0 + (i as f64 * 0.5).sin() + i as f64 * 0.1)).collect();
+        let mut config = OptimizationConfig {
+            ranges: BTreeMap::from([
+                ("lookback_period".to_string(), ParamRange::Range { min: 2.0, max: 10.0, step: Some(4.0) }),
+                ("threshold".to_string(), ParamRange::Values { values: vec![json!(0.01), json!(0.05)] }),
+            ]),
+            method: SearchMethod::Grid,
+            objective: Objective::TotalReturn,
+            backtest: BacktestConfig::default(),
+            threads: Some(3),
+        };
+
+        let grid = candidates(&params, &config).unwrap();
+        assert_eq!(grid.len(), 6);
+        assert!(grid.iter().all(|c| c["lookback_period"].is_i64()));
+
+        let progress = AtomicUsize::new(0);
+        let runs = run_optimization(&params, &grid, &history, &config, &progress);
+        assert_eq!(progress.load(Ordering::Relaxed), 6);
+        assert_eq!(runs.len(), 6);
+        assert_eq!(runs[0].rank, 1);
+        assert!(runs.windows(2).all(|w| w[0].score >= w[1].score));
+        assert!(runs.iter().all(|r| r.error.is_none()));
+
+        // 固定种子的随机搜索可复现, 取值在范围内
+        config.method = SearchMethod::Random { samples: 20, seed: Some(7) };
+        let first = candidates(&params, &config).unwrap();
+        assert_eq!(first, candidates(&params, &config).unwrap());
+        assert!(first.iter().all(|c| (2..=10).contains(&c["lookback_period"].as_i64().unwrap())));
+
+        // 超出参数定义的范围
+        config.ranges.insert("lookback_period".to_string(), ParamRange::Values { values: vec![json!(0)] });
+        assert!(candidates(&params, &config).is_err());
+        config.method = SearchMethod::Grid;
+        config.ranges.insert("lookback_period".to_string(), ParamRange::Range { min: 2.0, max: 10.0, step: None });
+        assert!(candidates(&params, &config).is_err());
+    }
+}
@@ -0,0 +1,525 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use position::market::round_lot;
+use crate::context::StrategyContext;
+use crate::models::{MarketData, Signal, SignalAction, StrategyParams, StrategyType};
+use crate::sizing::SizingInput;
+use crate::strategy::Strategy;
+
+// 配对筛选至少需要的共同行情数
+pub const MIN_PAIR_BARS: usize = 30;
+
+// Engle-Granger检验 (两个变量, 含常数项) 的MacKinnon临界值
+const EG_CRITICAL_VALUES: [(f64, f64); 3] = [(0.01, -3.90), (0.05, -3.34), (0.10, -3.04)];
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// 一元最小二乘回归 y = alpha + beta * x, x没有波动时返回None
+pub fn ols(y: &[f64], x: &[f64]) -> Option<(f64, f64)> {
+    if y.len() != x.len() || y.len() < 2 {
+        return None;
+    }
+    let (mean_x, mean_y) = (mean(x), mean(y));
+    let var_x: f64 = x.iter().map(|v| (v - mean_x).powi(2)).sum();
+    if var_x == 0.0 {
+        return None;
+    }
+    let cov: f64 = x.iter().zip(y).map(|(a, b)| (a - mean_x) * (b - mean_y)).sum();
+    let beta = cov / var_x;
+    Some((mean_y - beta * mean_x, beta))
+}
+
+// 对数收益率的相关系数
+pub fn correlation(a: &[f64], b: &[f64]) -> Option<f64> {
+    let returns = |prices: &[f64]| -> Vec<f64> { prices.windows(2).map(|w| (w[1] / w[0]).ln()).collect() };
+    let (ra, rb) = (returns(a), returns(b));
+    if ra.len() != rb.len() || ra.len() < 2 {
+        return None;
+    }
+    let (mean_a, mean_b) = (mean(&ra), mean(&rb));
+    let cov: f64 = ra.iter().zip(&rb).map(|(x, y)| (x - mean_a) * (y - mean_b)).sum();
+    let var_a: f64 = ra.iter().map(|x| (x - mean_a).powi(2)).sum();
+    let var_b: f64 = rb.iter().map(|y| (y - mean_b).powi(2)).sum();
+    if var_a == 0.0 || var_b == 0.0 {
+        return None;
+    }
+    Some(cov / (var_a * var_b).sqrt())
+}
+
+// 协整检验结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cointegration {
+    pub alpha: f64,
+    // 对冲比例: 对数价格 ln(y) = alpha + hedge_ratio * ln(x) + 残差
+    pub hedge_ratio: f64,
+    // 残差的Dickey-Fuller t统计量, 越小越显著
+    pub adf_statistic: f64,
+    // 通过检验的最小显著性水平 (0.01/0.05/0.10), 未通过为None
+    pub significance: Option<f64>,
+    // 残差回归均值的半衰期 (行情数)
+    pub half_life: Option<f64>,
+}
+
+impl Cointegration {
+    // 按5%显著性水平判断是否协整
+    pub fn is_cointegrated(&self) -> bool {
+        self.significance.is_some_and(|level| level <= 0.05)
+    }
+}
+
+// Engle-Granger两步法: 先回归对数价格, 再对残差做Dickey-Fuller检验
+pub fn engle_granger(y: &[f64], x: &[f64]) -> Option<Cointegration> {
+    if y.len() != x.len() || y.len() < MIN_PAIR_BARS || y.iter().chain(x).any(|p| *p <= 0.0) {
+        return None;
+    }
+    let (log_y, log_x): (Vec<f64>, Vec<f64>) = (y.iter().map(|p| p.ln()).collect(), x.iter().map(|p| p.ln()).collect());
+    let (alpha, hedge_ratio) = ols(&log_y, &log_x)?;
+    let residuals: Vec<f64> = log_y.iter().zip(&log_x).map(|(a, b)| a - alpha - hedge_ratio * b).collect();
+
+    // Δe(t) = gamma * e(t-1) + u(t)
+    let lagged = &residuals[..residuals.len() - 1];
+    let diffs: Vec<f64> = residuals.windows(2).map(|w| w[1] - w[0]).collect();
+    let sum_sq: f64 = lagged.iter().map(|e| e * e).sum();
+    if sum_sq == 0.0 {
+        return None;
+    }
+    let gamma = lagged.iter().zip(&diffs).map(|(e, d)| e * d).sum::<f64>() / sum_sq;
+    let sse: f64 = lagged.iter().zip(&diffs).map(|(e, d)| (d - gamma * e).powi(2)).sum();
+    let standard_error = (sse / (diffs.len() - 1) as f64 / sum_sq).sqrt();
+    let adf_statistic = if standard_error > 0.0 { gamma / standard_error } else { f64::NEG_INFINITY };
+
+    Some(Cointegration {
+        alpha,
+        hedge_ratio,
+        adf_statistic,
+        significance: EG_CRITICAL_VALUES.iter().find(|(_, critical)| adf_statistic < *critical).map(|(level, _)| *level),
+        half_life: (gamma < 0.0 && gamma > -1.0).then(|| -(2f64).ln() / (1.0 + gamma).ln()),
+    })
+}
+
+// 候选配对
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairCandidate {
+    // 被解释的股票, 即 ln(stock_a) = alpha + hedge_ratio * ln(stock_b)
+    pub stock_a: String,
+    pub stock_b: String,
+    pub sector: String,
+    // 参与计算的共同行情数
+    pub bars: usize,
+    pub correlation: f64,
+    pub cointegration: Cointegration,
+}
+
+// 在同一行业内两两计算收益率相关系数和协整检验, 按检验统计量从显著到不显著排序
+pub fn find_pairs(history: &[MarketData], min_correlation: f64) -> Vec<PairCandidate> {
+    let mut bars: Vec<&MarketData> = history.iter().collect();
+    bars.sort_by_key(|d| d.timestamp);
+
+    // 股票行业取最近一次行情, 价格序列按行情对齐, 缺失为None
+    let mut sectors: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut seen: HashSet<&str> = HashSet::new();
+    for data in bars.iter().rev() {
+        for (code, snapshot) in &data.stocks {
+            if seen.insert(code.as_str()) {
+                if let Some(sector) = &snapshot.fundamentals.sector {
+                    sectors.entry(sector.clone()).or_default().push(code.clone());
+                }
+            }
+        }
+    }
+    let prices = |code: &str| -> Vec<Option<f64>> {
+        bars.iter().map(|d| d.stocks.get(code).map(|s| s.price).filter(|p| *p > 0.0)).collect()
+    };
+
+    let mut candidates = Vec::new();
+    for (sector, codes) in sectors.iter_mut() {
+        codes.sort();
+        let series: Vec<Vec<Option<f64>>> = codes.iter().map(|code| prices(code)).collect();
+        for i in 0..codes.len() {
+            for j in i + 1..codes.len() {
+                let (a, b): (Vec<f64>, Vec<f64>) = series[i].iter().zip(&series[j])
+                    .filter_map(|(a, b)| Some(((*a)?, (*b)?)))
+                    .unzip();
+                if a.len() < MIN_PAIR_BARS {
+                    continue;
+                }
+                let correlation = match correlation(&a, &b) {
+                    Some(c) if c >= min_correlation => c,
+                    _ => continue,
+                };
+                // 两个方向分别检验, 取更显著的一个
+                let forward = engle_granger(&a, &b).map(|c| (codes[i].clone(), codes[j].clone(), c));
+                let backward = engle_granger(&b, &a).map(|c| (codes[j].clone(), codes[i].clone(), c));
+                let best = [forward, backward].into_iter().flatten()
+                    .min_by(|x, y| x.2.adf_statistic.total_cmp(&y.2.adf_statistic));
+                if let Some((stock_a, stock_b, cointegration)) = best {
+                    candidates.push(PairCandidate {
+                        stock_a,
+                        stock_b,
+                        sector: sector.clone(),
+                        bars: a.len(),
+                        correlation,
+                        cointegration,
+                    });
+                }
+            }
+        }
+    }
+    candidates.sort_by(|x, y| x.cointegration.adf_statistic.total_cmp(&y.cointegration.adf_statistic));
+    candidates
+}
+
+// 配对持仓方向
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PairState {
+    Flat,
+    // 价差偏低: 做多stock_a, 做空stock_b
+    LongSpread,
+    // 价差偏高: 做空stock_a, 做多stock_b
+    ShortSpread,
+}
+
+// 配对交易策略: 滚动回归对数价格得到对冲比例, 按价差Z值开仓, 回归时平仓, 继续偏离时止损
+// 做多腿按仓位模型定量, 做空腿按对冲比例配平; 开通信用账户时做空腿融券卖出,
+// 否则只能卖出已有的可卖持仓, 可卖数量不足时不开仓, 避免只剩单边多头
+pub struct PairTradingStrategy {
+    params: StrategyParams,
+    stock_a: String,
+    stock_b: String,
+    lookback_period: usize,
+    entry_z: f64,
+    exit_z: f64,
+    stop_z: f64,
+    state: PairState,
+    // 开仓前两条腿的持仓 (融券时为融券负债), 平仓时只处理开仓后的变化
+    long_basis: i64,
+    short_basis: i64,
+    // 做空腿是否为融券卖出
+    short_on_margin: bool,
+    // 止损后等价差回归再重新开仓
+    stopped: bool,
+}
+
+impl PairTradingStrategy {
+    pub fn new(name: &str) -> Self {
+        Self::with_params(StrategyParams::new(name.to_string(), StrategyType::PairTrading))
+    }
+
+    // 按策略参数创建, 参数按参数定义校验
+    pub fn from_params(params: &StrategyParams) -> Result<Self, String> {
+        let strategy = Self::with_params(params.validated()?);
+        if strategy.stock_a.is_empty() || strategy.stock_b.is_empty() || strategy.stock_a == strategy.stock_b {
+            return Err("配对交易需要设置两只不同的股票stock_a和stock_b".to_string());
+        }
+        if !(strategy.exit_z < strategy.entry_z && strategy.entry_z < strategy.stop_z) {
+            return Err("配对交易的Z值应满足exit_z < entry_z < stop_z".to_string());
+        }
+        Ok(strategy)
+    }
+
+    fn with_params(params: StrategyParams) -> Self {
+        Self {
+            stock_a: params.get_param("stock_a").unwrap_or_default(),
+            stock_b: params.get_param("stock_b").unwrap_or_default(),
+            lookback_period: params.get_param("lookback_period").unwrap_or(60),
+            entry_z: params.get_param("entry_z").unwrap_or(2.0),
+            exit_z: params.get_param("exit_z").unwrap_or(0.5),
+            stop_z: params.get_param("stop_z").unwrap_or(4.0),
+            params,
+            state: PairState::Flat,
+            long_basis: 0,
+            short_basis: 0,
+            short_on_margin: false,
+            stopped: false,
+        }
+    }
+
+    // 滚动窗口内的对冲比例和当前价差Z值
+    fn spread(&self, ctx: &StrategyContext) -> Option<(f64, f64)> {
+        let a = ctx.history.closes(&self.stock_a, self.lookback_period);
+        let b = ctx.history.closes(&self.stock_b, self.lookback_period);
+        if a.len() < self.lookback_period || b.len() < self.lookback_period || a.iter().chain(&b).any(|p| *p <= 0.0) {
+            return None;
+        }
+        let (log_a, log_b): (Vec<f64>, Vec<f64>) = (a.iter().map(|p| p.ln()).collect(), b.iter().map(|p| p.ln()).collect());
+        let (alpha, beta) = ols(&log_a, &log_b)?;
+        let residuals: Vec<f64> = log_a.iter().zip(&log_b).map(|(a, b)| a - alpha - beta * b).collect();
+        let std_dev = (residuals.iter().map(|e| e * e).sum::<f64>() / residuals.len() as f64).sqrt();
+        if std_dev == 0.0 {
+            return None;
+        }
+        Some((beta, residuals[residuals.len() - 1] / std_dev))
+    }
+
+    fn signal(&self, ctx: &StrategyContext, code: &str, action: SignalAction, strength: f64, reason: String) -> Signal {
+        Signal {
+            code: code.to_string(),
+            timestamp: ctx.now,
+            action,
+            price: ctx.data.stocks.get(code).map(|s| s.price),
+            amount: None,
+            reason,
+            strength,
+        }
+    }
+
+    // 两条腿分别为(做多, 做空)
+    fn legs(&self, state: PairState) -> (&str, &str) {
+        match state {
+            PairState::ShortSpread => (&self.stock_b, &self.stock_a),
+            _ => (&self.stock_a, &self.stock_b),
+        }
+    }
+
+    // 融券负债数量
+    fn short_owed(ctx: &StrategyContext, code: &str) -> i64 {
+        ctx.portfolio.margin.as_ref()
+            .and_then(|m| m.shorts.get(code))
+            .map(|s| s.amount)
+            .unwrap_or(0)
+    }
+
+    fn close(&mut self, ctx: &StrategyContext, reason: &str) -> Vec<Signal> {
+        let (long, short) = self.legs(self.state);
+        let mut signals = Vec::new();
+        let bought = ctx.position(long) - self.long_basis;
+        if bought > 0 {
+            signals.push(self.signal(ctx, long, SignalAction::Sell, 1.0, format!("配对平仓: {}", reason)).with_shares(bought));
+        }
+        if self.short_on_margin {
+            let shorted = Self::short_owed(ctx, short) - self.short_basis;
+            if shorted > 0 {
+                signals.push(self.signal(ctx, short, SignalAction::Cover, 1.0, format!("配对平仓: {}", reason)).with_shares(shorted));
+            }
+        } else {
+            let sold = self.short_basis - ctx.position(short);
+            if sold > 0 {
+                signals.push(self.signal(ctx, short, SignalAction::Buy, 1.0, format!("配对平仓: {}", reason)).with_shares(sold));
+            }
+        }
+        self.state = PairState::Flat;
+        signals
+    }
+}
+
+impl Strategy for PairTradingStrategy {
+    fn name(&self) -> &str {
+        &self.params.name
+    }
+
+    fn strategy_type(&self) -> StrategyType {
+        self.params.strategy_type
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.params.enabled
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.params.enabled = enabled;
+    }
+
+    fn history_window(&self) -> usize {
+        self.lookback_period
+    }
+
+    fn on_start(&mut self, _ctx: &StrategyContext) {
+        self.state = PairState::Flat;
+        self.stopped = false;
+    }
+
+    fn on_bar(&mut self, ctx: &StrategyContext) -> Vec<Signal> {
+        let (beta, z) = match self.spread(ctx) {
+            Some(spread) => spread,
+            None => return Vec::new(),
+        };
+        let (price_a, price_b) = match (ctx.data.stocks.get(&self.stock_a), ctx.data.stocks.get(&self.stock_b)) {
+            (Some(a), Some(b)) => (a.price, b.price),
+            _ => return Vec::new(),
+        };
+
+        match self.state {
+            PairState::LongSpread if z >= -self.exit_z => return self.close(ctx, &format!("价差Z值{:.2}已回归", z)),
+            PairState::ShortSpread if z <= self.exit_z => return self.close(ctx, &format!("价差Z值{:.2}已回归", z)),
+            PairState::LongSpread | PairState::ShortSpread if z.abs() >= self.stop_z => {
+                self.stopped = true;
+                return self.close(ctx, &format!("价差Z值{:.2}超过止损线", z));
+            },
+            PairState::LongSpread | PairState::ShortSpread => return Vec::new(),
+            PairState::Flat => {},
+        }
+
+        if self.stopped {
+            self.stopped = z.abs() > self.exit_z;
+            return Vec::new();
+        }
+        // 负的对冲比例不构成对冲
+        if beta <= 0.0 || z.abs() < self.entry_z || z.abs() >= self.stop_z {
+            return Vec::new();
+        }
+
+        let state = if z > 0.0 { PairState::ShortSpread } else { PairState::LongSpread };
+        let (long, short) = self.legs(state);
+        let (long, short) = (long.to_string(), short.to_string());
+
+        // 对数价格回归的对冲比例是金额比例 (A的金额 : B的金额 = 1 : beta), 合计为仓位模型的一份
+        let value_a = 1.0;
+        let value_b = beta;
+        let (long_weight, short_weight, long_price, short_price) = if long == self.stock_a {
+            (value_a / (value_a + value_b), value_b / (value_a + value_b), price_a, price_b)
+        } else {
+            (value_b / (value_a + value_b), value_a / (value_a + value_b), price_b, price_a)
+        };
+        let reason = format!("价差Z值{:.2}, 对冲比例{:.2}", z, beta);
+        let mut long_signal = self.signal(ctx, &long, SignalAction::Buy, long_weight, format!("配对做多: {}", reason));
+
+        // 做多腿按仓位模型定量, 做空腿按金额配平
+        let long_quantity = match ctx.data.stocks.get(&long) {
+            Some(snapshot) => self.params.sizing.quantity(&long_signal, &SizingInput::from(snapshot), ctx.portfolio),
+            None => 0,
+        };
+        let short_quantity = round_lot(long_quantity as f64 * long_price * short_weight / long_weight / short_price);
+        if long_quantity <= 0 || short_quantity <= 0 {
+            return Vec::new();
+        }
+
+        // 没有信用账户时做空腿只能卖出已有持仓, 两条腿都能成交才开仓
+        let short_on_margin = ctx.portfolio.margin.is_some();
+        if !short_on_margin && ctx.portfolio.sellable_amount(&short, ctx.now) < short_quantity {
+            return Vec::new();
+        }
+
+        self.state = state;
+        self.short_on_margin = short_on_margin;
+        self.long_basis = ctx.position(&long);
+        self.short_basis = if short_on_margin { Self::short_owed(ctx, &short) } else { ctx.position(&short) };
+
+        long_signal.set_shares(long_quantity);
+        let short_action = if short_on_margin { SignalAction::Short } else { SignalAction::Sell };
+        vec![
+            long_signal,
+            self.signal(ctx, &short, short_action, short_weight, format!("配对做空: {}", reason)).with_shares(short_quantity),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use chrono::{Duration, TimeZone, Utc};
+    use position::margin::MarginConfig;
+    use position::money::{money, Money};
+    use position::models::{Portfolio, Position, Transaction, TransactionType};
+    use crate::context::StrategyRunner;
+    use crate::models::{Fundamentals, StockSnapshot};
+    use crate::sizing::SizingModel;
+    use crate::strategy::create_strategy;
+    use crate::testing::snapshot;
+
+    fn stock(code: &str, sector: &str, price: f64) -> StockSnapshot {
+        snapshot(code, price, 0.0, Fundamentals { sector: Some(sector.to_string()), ..Default::default() })
+    }
+
+    // 固定种子的随机游走
+    fn random_walk(seed: u64, n: usize) -> Vec<f64> {
+        let mut state = seed;
+        let mut price = 10.0;
+        (0..n).map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            price *= 1.0 + ((state >> 33) as f64 / (1u64 << 31) as f64 - 0.5) * 0.04;
+            price
+        }).collect()
+    }
+
+    #[test]
+    fn test_cointegration_and_pair_signals() {
+        let n = 120;
+        let b = random_walk(7, n);
+        // A与B协整: ln(A) = 0.5 + ln(B) + 平稳噪声
+        let a: Vec<f64> = b.iter().enumerate().map(|(i, p)| p * (0.5 + 0.004 * (i as f64 * 1.3).sin()).exp()).collect();
+        let c = random_walk(11, n);
+
+        let test = engle_granger(&a, &b).unwrap();
+        assert!((test.hedge_ratio - 1.0).abs() < 0.05);
+        assert!(test.is_cointegrated());
+        assert!(!engle_granger(&a, &c).unwrap().is_cointegrated());
+        assert!(correlation(&a, &b).unwrap() > 0.9);
+
+        // 只在同一行业内配对
+        let history: Vec<MarketData> = (0..n).map(|i| MarketData {
+            timestamp: Utc.with_ymd_and_hms(2024, 1, 1, 7, 0, 0).unwrap() + Duration::days(i as i64),
+            stocks: HashMap::from([
+                ("A".to_string(), stock("A", "银行", a[i])),
+                ("B".to_string(), stock("B", "银行", b[i])),
+                ("C".to_string(), stock("C", "科技", c[i])),
+            ]),
+        }).collect();
+        let pairs = find_pairs(&history, 0.5);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].sector, "银行");
+        assert!(pairs[0].cointegration.is_cointegrated());
+
+        let mut params = StrategyParams::new("pair".to_string(), StrategyType::PairTrading);
+        assert!(create_strategy(&params).is_err());
+        params.update_params(&HashMap::from([
+            ("stock_a".to_string(), serde_json::json!("A")),
+            ("stock_b".to_string(), serde_json::json!("B")),
+            ("lookback_period".to_string(), serde_json::json!(30)),
+        ])).unwrap();
+        params.sizing = SizingModel::FixedCash { cash: 20000.0 };
+        let mut runner = StrategyRunner::new(create_strategy(&params).unwrap(), SizingModel::FixedCash { cash: 20000.0 });
+        let mut portfolio = Portfolio::new("test".to_string(), Money::from(100000));
+        portfolio.positions.insert("A".to_string(), Position::new("A".to_string(), "A".to_string(), 2000, Money::from(10)));
+        runner.warm_up(&history[..40]);
+
+        // A相对B上涨, 价差偏高: 卖出A, 买入B
+        let mut spike = history[40].clone();
+        spike.stocks.insert("A".to_string(), stock("A", "银行", a[40] * 1.01));
+        let signals = runner.on_bar(&spike, &portfolio);
+        assert_eq!(signals.len(), 2);
+        assert_eq!((signals[0].code.as_str(), signals[0].action), ("B", SignalAction::Buy));
+        assert_eq!((signals[1].code.as_str(), signals[1].action), ("A", SignalAction::Sell));
+        assert!(signals.iter().all(|s| s.shares().is_some_and(|shares| shares > 0)));
+
+        // 成交后价差回归, 卖出买入的B, 买回卖出的A
+        let sold = signals[1].shares().unwrap();
+        portfolio.positions.get_mut("A").unwrap().amount -= sold;
+        portfolio.positions.insert("B".to_string(), Position::new("B".to_string(), "B".to_string(), 1000, Money::from(10)));
+        let signals = runner.on_bar(&history[41], &portfolio);
+        assert_eq!(signals.len(), 2);
+        assert_eq!((signals[0].code.as_str(), signals[0].action, signals[0].shares()), ("B", SignalAction::Sell, Some(1000)));
+        assert_eq!((signals[1].code.as_str(), signals[1].action, signals[1].shares()), ("A", SignalAction::Buy, Some(sold)));
+        assert!(runner.on_bar(&history[42], &portfolio).is_empty());
+
+        // 两只股票都没有持仓: 没有信用账户时不开仓, 避免只买入单边
+        let mut runner = StrategyRunner::new(create_strategy(&params).unwrap(), params.sizing.clone());
+        let mut portfolio = Portfolio::new("test".to_string(), Money::from(100000));
+        runner.warm_up(&history[..40]);
+        assert!(runner.on_bar(&spike, &portfolio).is_empty());
+
+        // 开通信用账户后做空腿融券卖出, 两条腿金额按对冲比例配平
+        let mut runner = StrategyRunner::new(create_strategy(&params).unwrap(), params.sizing.clone());
+        runner.warm_up(&history[..40]);
+        portfolio.open_margin(MarginConfig::default()).unwrap();
+        let signals = runner.on_bar(&spike, &portfolio);
+        assert_eq!(signals.len(), 2);
+        assert_eq!((signals[0].code.as_str(), signals[0].action), ("B", SignalAction::Buy));
+        assert_eq!((signals[1].code.as_str(), signals[1].action), ("A", SignalAction::Short));
+        let long_value = signals[0].shares().unwrap() as f64 * spike.stocks["B"].price;
+        let short_value = signals[1].shares().unwrap() as f64 * spike.stocks["A"].price;
+        assert!(long_value > 0.0 && (short_value / long_value - 1.0).abs() < 0.1);
+
+        // 成交后价差回归, 卖出B并买券还券
+        let bought = signals[0].shares().unwrap();
+        let shorted = signals[1].shares().unwrap();
+        portfolio.add_transaction(Transaction::new("B".to_string(), TransactionType::Buy, bought, money(spike.stocks["B"].price))).unwrap();
+        portfolio.short_sell("A", shorted, money(spike.stocks["A"].price)).unwrap();
+        let signals = runner.on_bar(&history[41], &portfolio);
+        assert_eq!(signals.len(), 2);
+        assert_eq!((signals[0].code.as_str(), signals[0].action, signals[0].shares()), ("B", SignalAction::Sell, Some(bought)));
+        assert_eq!((signals[1].code.as_str(), signals[1].action, signals[1].shares()), ("A", SignalAction::Cover, Some(shorted)));
+    }
+}
@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use chrono::Utc;
+use crate::factor::Factor;
+use crate::models::{StrategyParams, StrategyType};
+
+// 参数类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ParamType {
+    Integer,
+    Float,
+    Boolean,
+    Text,
+}
+
+// 策略参数定义
+#[derive(Debug, Clone, Serialize)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub param_type: ParamType,
+    pub default: Value,
+    // 取值范围 (含边界)
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub description: &'static str,
+}
+
+impl ParamSpec {
+    fn integer(name: &'static str, default: i64, min: i64, max: i64, description: &'static str) -> Self {
+        Self {
+            name,
+            param_type: ParamType::Integer,
+            default: Value::from(default),
+            min: Some(min as f64),
+            max: Some(max as f64),
+            description,
+        }
+    }
+
+    fn float(name: &'static str, default: f64, min: f64, max: f64, description: &'static str) -> Self {
+        Self {
+            name,
+            param_type: ParamType::Float,
+            default: Value::from(default),
+            min: Some(min),
+            max: Some(max),
+            description,
+        }
+    }
+
+    fn boolean(name: &'static str, default: bool, description: &'static str) -> Self {
+        Self {
+            name,
+            param_type: ParamType::Boolean,
+            default: Value::from(default),
+            min: None,
+            max: None,
+            description,
+        }
+    }
+
+    fn text(name: &'static str, default: &str, description: &'static str) -> Self {
+        Self {
+            name,
+            param_type: ParamType::Text,
+            default: Value::from(default),
+            min: None,
+            max: None,
+            description,
+        }
+    }
+
+    // 检查参数值, 返回规范化后的值
+    pub fn check(&self, value: &Value) -> Result<Value, String> {
+        let (number, normalized) = match self.param_type {
+            ParamType::Integer => {
+                let number = value.as_i64()
+                    .or_else(|| value.as_f64().filter(|v| v.fract() == 0.0).map(|v| v as i64))
+                    .ok_or_else(|| format!("参数{}应为整数", self.name))?;
+                (number as f64, Value::from(number))
+            },
+            ParamType::Float => {
+                let number = value.as_f64().ok_or_else(|| format!("参数{}应为数字", self.name))?;
+                (number, Value::from(number))
+            },
+            ParamType::Boolean => {
+                let flag = value.as_bool().ok_or_else(|| format!("参数{}应为布尔值", self.name))?;
+                return Ok(Value::from(flag));
+            },
+            // 股票代码等文本参数, 客户端可能把纯数字代码按数字传递
+            ParamType::Text => {
+                let text = value.as_str().map(|s| s.trim().to_string())
+                    .or_else(|| value.as_u64().map(|v| v.to_string()))
+                    .ok_or_else(|| format!("参数{}应为文本", self.name))?;
+                return Ok(Value::from(text));
+            },
+        };
+        if self.min.is_some_and(|min| number < min) || self.max.is_some_and(|max| number > max) {
+            return Err(format!("参数{}={}超出范围[{}, {}]", self.name, number,
+                self.min.map(|v| v.to_string()).unwrap_or_default(),
+                self.max.map(|v| v.to_string()).unwrap_or_default()));
+        }
+        Ok(normalized)
+    }
+}
+
+// 策略类型的参数定义
+pub fn param_schema(strategy_type: StrategyType) -> Vec<ParamSpec> {
+    match strategy_type {
+        StrategyType::Momentum => vec![
+            ParamSpec::integer("lookback_period", 20, 1, 250, "回看期 (交易日)"),
+            ParamSpec::float("threshold", 0.05, 0.001, 1.0, "回看期收益率的买卖阈值"),
+        ],
+        StrategyType::MeanReversion => vec![
+            ParamSpec::integer("ma_period", 20, 2, 250, "均线和标准差的计算周期 (交易日)"),
+            ParamSpec::float("std_dev_multiplier", 2.0, 0.1, 5.0, "布林带宽度 (标准差倍数)"),
+        ],
+        StrategyType::FactorModel => {
+            let mut specs: Vec<ParamSpec> = Factor::ALL.iter()
+                .map(|f| ParamSpec::float(f.weight_param(), f.default_weight(), -1.0, 1.0, "因子权重, 为0时不使用该因子"))
+                .collect();
+            specs.extend([
+                ParamSpec::integer("top_n", 10, 1, 200, "持有综合得分最高的股票数"),
+                ParamSpec::integer("rebalance_bars", 20, 1, 250, "调仓间隔 (交易日)"),
+                ParamSpec::float("winsorize", 0.05, 0.0, 0.25, "缩尾分位数, 两端各截断该比例的极端值"),
+                ParamSpec::boolean("sector_neutral", false, "是否在行业内标准化因子"),
+            ]);
+            specs
+        },
+        StrategyType::PairTrading => vec![
+            ParamSpec::text("stock_a", "", "配对的第一只股票代码"),
+            ParamSpec::text("stock_b", "", "配对的第二只股票代码"),
+            ParamSpec::integer("lookback_period", 60, 20, 500, "滚动回归和价差标准化的窗口 (交易日)"),
+            ParamSpec::float("entry_z", 2.0, 0.5, 5.0, "价差偏离超过该Z值时开仓"),
+            ParamSpec::float("exit_z", 0.5, 0.0, 3.0, "价差回归到该Z值以内时平仓"),
+            ParamSpec::float("stop_z", 4.0, 1.0, 10.0, "价差继续偏离超过该Z值时止损"),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+// 策略类型的默认参数
+pub fn default_params(strategy_type: StrategyType) -> HashMap<String, Value> {
+    param_schema(strategy_type).into_iter()
+        .map(|spec| (spec.name.to_string(), spec.default))
+        .collect()
+}
+
+// 按参数定义校验, 返回补齐默认值后的完整参数; 未定义的参数视为错误
+pub fn validate_params(strategy_type: StrategyType, params: &HashMap<String, Value>) -> Result<HashMap<String, Value>, String> {
+    let schema = param_schema(strategy_type);
+    if let Some(unknown) = params.keys().find(|key| !schema.iter().any(|spec| spec.name == key.as_str())) {
+        return Err(format!("策略类型{:?}没有参数{}", strategy_type, unknown));
+    }
+
+    let mut validated = HashMap::new();
+    for spec in &schema {
+        let value = match params.get(spec.name) {
+            Some(value) => spec.check(value)?,
+            None => spec.default.clone(),
+        };
+        validated.insert(spec.name.to_string(), value);
+    }
+    Ok(validated)
+}
+
+impl StrategyParams {
+    // 校验策略特定参数和风控参数
+    pub fn validate(&self) -> Result<(), String> {
+        validate_params(self.strategy_type, &self.params)?;
+        for (name, value) in [("max_position_size", self.max_position_size), ("max_drawdown", self.max_drawdown)] {
+            if !(value > 0.0 && value <= 1.0) {
+                return Err(format!("{}应在(0, 1]之间", name));
+            }
+        }
+        for (name, value) in [("stop_loss", self.stop_loss), ("take_profit", self.take_profit)] {
+            if !(0.0..=10.0).contains(&value) {
+                return Err(format!("{}应在[0, 10]之间", name));
+            }
+        }
+        Ok(())
+    }
+
+    // 校验并补齐默认值后的参数
+    pub fn validated(&self) -> Result<StrategyParams, String> {
+        self.validate()?;
+        let mut params = self.clone();
+        params.params = validate_params(self.strategy_type, &self.params)?;
+        Ok(params)
+    }
+
+    // 合并更新策略特定参数, 校验失败时保持原参数不变
+    pub fn update_params(&mut self, updates: &HashMap<String, Value>) -> Result<(), String> {
+        let mut merged = self.params.clone();
+        merged.extend(updates.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self.params = validate_params(self.strategy_type, &merged)?;
+        self.updated_at = Utc::now();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_and_update_params() {
+        let mut params = StrategyParams::new("momentum".to_string(), StrategyType::Momentum);
+        assert_eq!(params.params.get("lookback_period"), Some(&json!(20)));
+        assert!(params.validate().is_ok());
+
+        // 整数参数接受整数值的浮点数
+        params.update_params(&HashMap::from([("lookback_period".to_string(), json!(10.0))])).unwrap();
+        assert_eq!(params.get_param::<usize>("lookback_period"), Some(10));
+        assert_eq!(params.get_param::<f64>("threshold"), Some(0.05));
+
+        // 校验失败时参数不变
+        for (key, value) in [("lookback_period", json!(0)), ("lookback_period", json!(2.5)), ("threshold", json!("high")), ("window", json!(5))] {
+            assert!(params.update_params(&HashMap::from([(key.to_string(), value)])).is_err());
+        }
+        assert_eq!(params.get_param::<usize>("lookback_period"), Some(10));
+
+        params.stop_loss = -0.1;
+        assert!(params.validate().is_err());
+    }
+}
@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use position::market::{round_lot, LOT_SIZE};
+use position::models::Portfolio;
+use position::money::{money, to_f64, Money, Shares};
+use position::order::OrderSide;
+use position::performance::TRADING_DAYS_PER_YEAR;
+use crate::models::{MarketData, Signal, SignalAction, StockSnapshot};
+
+// 仓位模型
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum SizingModel {
+    // 每笔固定金额
+    FixedCash { cash: f64 },
+    // 按总资产比例
+    PercentOfEquity { percent: f64 },
+    // 波动率目标: 按年化目标波动率与股票波动率之比确定权重
+    VolatilityTarget { target_volatility: f64, max_weight: f64 },
+    // 按ATR止损距离控制每笔风险
+    AtrRisk { risk_per_trade: f64, atr_multiple: f64 },
+    // 分数凯利
+    Kelly { win_rate: f64, payoff_ratio: f64, fraction: f64, max_weight: f64 },
+}
+
+impl Default for SizingModel {
+    fn default() -> Self {
+        SizingModel::PercentOfEquity { percent: 0.1 }
+    }
+}
+
+// 计算仓位所需的行情信息
+#[derive(Debug, Clone, Default)]
+pub struct SizingInput {
+    pub price: f64,
+    // 近一月日波动率 (百分比)
+    pub volatility_1m: Option<f64>,
+    // 平均真实波幅
+    pub atr: Option<f64>,
+}
+
+impl From<&StockSnapshot> for SizingInput {
+    fn from(snapshot: &StockSnapshot) -> Self {
+        let range = snapshot.high - snapshot.low;
+        Self {
+            price: snapshot.price,
+            volatility_1m: Some(snapshot.volatility_1m).filter(|v| *v > 0.0),
+            // 没有ATR时用当日振幅近似
+            atr: snapshot.atr.or(if range > 0.0 { Some(range) } else { None }),
+        }
+    }
+}
+
+impl SizingModel {
+    // 满仓强度下的目标买入金额
+    fn target_value(&self, input: &SizingInput, equity: f64) -> f64 {
+        match self {
+            SizingModel::FixedCash { cash } => *cash,
+            SizingModel::PercentOfEquity { percent } => equity * percent,
+            SizingModel::VolatilityTarget { target_volatility, max_weight } => {
+                match input.volatility_1m {
+                    Some(volatility) => {
+                        let annualized = volatility / 100.0 * TRADING_DAYS_PER_YEAR.sqrt();
+                        equity * (target_volatility / annualized).min(*max_weight)
+                    },
+                    None => 0.0,
+                }
+            },
+            SizingModel::AtrRisk { risk_per_trade, atr_multiple } => {
+                match input.atr.filter(|a| *a > 0.0) {
+                    Some(atr) => equity * risk_per_trade / (atr * atr_multiple) * input.price,
+                    None => 0.0,
+                }
+            },
+            SizingModel::Kelly { win_rate, payoff_ratio, fraction, max_weight } => {
+                if *payoff_ratio <= 0.0 {
+                    return 0.0;
+                }
+                let kelly = win_rate - (1.0 - win_rate) / payoff_ratio;
+                equity * (kelly * fraction).clamp(0.0, *max_weight)
+            },
+        }
+    }
+
+    // 按信号方向和强度计算股数, 买入按整手且不超过可用现金, 卖出不超过持仓
+    pub fn quantity(&self, signal: &Signal, input: &SizingInput, portfolio: &Portfolio) -> Shares {
+        let price = signal.price.unwrap_or(input.price);
+        if price <= 0.0 {
+            return 0;
+        }
+
+        let strength = signal.strength.clamp(0.0, 1.0);
+        let value = self.target_value(input, portfolio.total_market_value()) * strength;
+        let quantity = round_lot(value / price);
+
+        match signal.action {
+            SignalAction::Buy => {
+                // 买入金额加估算费用不超过可用现金, 超出时逐手减少
+                let cash = portfolio.available_cash() - portfolio.reserved_cash();
+                let fees = portfolio.fee_schedule();
+                let price = money(price);
+                let mut quantity = quantity.min(round_lot(to_f64(cash.max(Money::ZERO) / price)));
+                while quantity > 0 {
+                    let value = Money::from(quantity) * price;
+                    let (fee, tax) = fees.costs(OrderSide::Buy, value);
+                    if value + fee + tax <= cash {
+                        break;
+                    }
+                    quantity -= LOT_SIZE;
+                }
+                quantity
+            },
+            SignalAction::Sell => {
+                let held = portfolio.positions.get(&signal.code).map(|p| p.amount).unwrap_or(0);
+                // 剩余不足一手时全部卖出
+                if held - quantity < LOT_SIZE {
+                    held
+                } else {
+                    quantity
+                }
+            },
+            // 保证金在下单时检查
+            SignalAction::Short => quantity,
+            SignalAction::Cover => {
+                let owed = portfolio.margin.as_ref()
+                    .and_then(|m| m.shorts.get(&signal.code))
+                    .map(|s| s.amount)
+                    .unwrap_or(0);
+                quantity.min(owed)
+            },
+            SignalAction::Hold => 0,
+        }
+    }
+}
+
+// 用仓位模型填充信号的交易数量, 策略已指定数量的信号保持不变
+pub fn apply_sizing(signals: &mut [Signal], data: &MarketData, portfolio: &Portfolio, model: &SizingModel) {
+    for signal in signals.iter_mut().filter(|s| s.shares().is_none()) {
+        let input = match data.stocks.get(&signal.code) {
+            Some(snapshot) => SizingInput::from(snapshot),
+            None => SizingInput { price: signal.price.unwrap_or(0.0), ..Default::default() },
+        };
+        let shares = model.quantity(signal, &input, portfolio);
+        signal.set_shares(shares);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use position::fees::FeeSchedule;
+    use position::margin::MarginConfig;
+
+    fn signal(action: SignalAction, strength: f64) -> Signal {
+        Signal {
+            code: "600000".to_string(),
+            timestamp: Utc::now(),
+            action,
+            price: Some(10.0),
+            amount: None,
+            reason: String::new(),
+            strength,
+        }
+    }
+
+    #[test]
+    fn test_sizing_models() {
+        let portfolio = Portfolio::new("test".to_string(), Money::from(100000));
+        let input = SizingInput { price: 10.0, volatility_1m: Some(2.0), atr: Some(0.5) };
+        let buy = signal(SignalAction::Buy, 1.0);
+
+        assert_eq!(SizingModel::FixedCash { cash: 12345.0 }.quantity(&buy, &input, &portfolio), 1200);
+        assert_eq!(SizingModel::PercentOfEquity { percent: 0.1 }.quantity(&signal(SignalAction::Buy, 0.5), &input, &portfolio), 500);
+
+        // 每笔风险1000元, 止损距离1元, 买入1000股
+        let atr = SizingModel::AtrRisk { risk_per_trade: 0.01, atr_multiple: 2.0 };
+        assert_eq!(atr.quantity(&buy, &input, &portfolio), 1000);
+
+        // 凯利比例 0.55 - 0.45 / 1.5 = 0.25, 取一半为12.5%
+        let kelly = SizingModel::Kelly { win_rate: 0.55, payoff_ratio: 1.5, fraction: 0.5, max_weight: 0.2 };
+        assert_eq!(kelly.quantity(&buy, &input, &portfolio), 1200);
+
+        // 年化波动率约31.7%, 目标15%, 权重约47%, 受上限30%约束
+        let vol = SizingModel::VolatilityTarget { target_volatility: 0.15, max_weight: 0.3 };
+        assert_eq!(vol.quantity(&buy, &input, &portfolio), 3000);
+
+        // 超出现金时按可用现金买入, 扣除佣金后10000股买不起, 减少一手
+        assert_eq!(SizingModel::FixedCash { cash: 1e7 }.quantity(&buy, &input, &portfolio), 9900);
+        assert_eq!(SizingModel::default().quantity(&signal(SignalAction::Sell, 1.0), &input, &portfolio), 0);
+
+        // 融券卖出所得不能用于买入
+        let mut portfolio = portfolio;
+        let fees = FeeSchedule::zero();
+        portfolio.open_margin(MarginConfig { fees, ..MarginConfig::default() }).unwrap();
+        portfolio.short_sell("601398", 5000, Money::from(10)).unwrap();
+        // 与融券前相同
+        assert_eq!(SizingModel::FixedCash { cash: 1e7 }.quantity(&buy, &input, &portfolio), 9900);
+    }
+}
@@ -1,35 +1,56 @@
-use crate::models::{StrategyParams, StrategyType, Signal, SignalAction, MarketData};
-use log::info;
+use crate::context::{StrategyContext, DEFAULT_HISTORY_WINDOW};
+use crate::factor::FactorModelStrategy;
+use crate::pairs::PairTradingStrategy;
+use crate::indicators::{Bollinger, PerSymbol};
+use position::money::to_f64;
+use crate::models::{StrategyParams, StrategyType, Signal, SignalAction};
 use std::collections::HashMap;
 
-// 策略特征
-pub trait Strategy {
+// 策略特征: 有状态的策略, 由StrategyRunner按生命周期驱动, 回测和实盘共用
+pub trait Strategy: Send {
     fn name(&self) -> &str;
     fn strategy_type(&self) -> StrategyType;
     fn is_enabled(&self) -> bool;
     fn set_enabled(&mut self, enabled: bool);
-    fn generate_signals(&self, data: &MarketData) -> Vec<Signal>;
+
+    // 需要保留的历史行情数量
+    fn history_window(&self) -> usize {
+        DEFAULT_HISTORY_WINDOW
+    }
+
+    // 第一根行情到达时调用
+    fn on_start(&mut self, _ctx: &StrategyContext) {}
+
+    // 每根行情收盘后调用, 返回交易信号
+    fn on_bar(&mut self, ctx: &StrategyContext) -> Vec<Signal>;
+
+    // 运行结束时调用
+    fn on_end(&mut self, _ctx: &StrategyContext) {}
 }
 
-// 动量策略
+// 动量策略: 回看期收益率超过阈值时买入, 低于负阈值时卖出
 pub struct MomentumStrategy {
     params: StrategyParams,
-    lookback_period: i32,
+    lookback_period: usize,
     threshold: f64,
 }
 
 impl MomentumStrategy {
     pub fn new(name: &str) -> Self {
-        let mut params = StrategyParams::new(name.to_string(), StrategyType::Momentum);
-        
-        // 设置默认参数
-        let _ = params.set_param("lookback_period", 20);
-        let _ = params.set_param("threshold", 0.05);
-        
+        Self::with_params(StrategyParams::new(name.to_string(), StrategyType::Momentum))
+    }
+
+    // 按策略参数创建, 参数按参数定义校验
+    pub fn from_params(params: &StrategyParams) -> Result<Self, String> {
+        Ok(Self::with_params(params.validated()?))
+    }
+
+    // 参数已校验并补齐默认值
+    fn with_params(params: StrategyParams) -> Self {
         Self {
+            lookback_period: params.get_param("lookback_period").unwrap_or_default(),
+            threshold: params.get_param("threshold").unwrap_or_default(),
             params,
-            lookback_period: 20,
-            threshold: 0.05,
         }
     }
 }
@@ -50,36 +71,42 @@ impl Strategy for MomentumStrategy {
     fn set_enabled(&mut self, enabled: bool) {
         self.params.enabled = enabled;
     }
+
+    fn history_window(&self) -> usize {
+        self.lookback_period + 1
+    }
     
-    fn generate_signals(&self, data: &MarketData) -> Vec<Signal> {
-        info!("生成动量策略信号");
-        
-        // 这里只是创建一个模拟的信号
-        // 实际应用中需要实现真正的策略逻辑
-        
+    fn on_bar(&mut self, ctx: &StrategyContext) -> Vec<Signal> {
         let mut signals = Vec::new();
         
-        // 假设根据股票涨幅生成信号
-        for (code, snapshot) in &data.stocks {
-            if snapshot.change_percent > self.threshold {
+        for (code, snapshot) in &ctx.data.stocks {
+            // 回看期数据不足时不产生信号
+            let base = match ctx.history.close_ago(code, self.lookback_period) {
+                Some(base) if base > 0.0 => base,
+                _ => continue,
+            };
+            let momentum = snapshot.price / base - 1.0;
+            let strength = (momentum.abs() / (self.threshold * 2.0)).min(1.0);
+
+            if momentum > self.threshold {
                 signals.push(Signal {
                     code: code.clone(),
-                    timestamp: data.timestamp,
+                    timestamp: ctx.now,
                     action: SignalAction::Buy,
                     price: Some(snapshot.price),
-                    amount: Some(100.0),
-                    reason: format!("涨幅超过阈值 {}%", self.threshold * 100.0),
-                    strength: snapshot.change_percent / 10.0, // 信号强度根据涨幅计算
+                    amount: None, // 由仓位模型确定
+                    reason: format!("{}日涨幅{:.2}%超过阈值{}%", self.lookback_period, momentum * 100.0, self.threshold * 100.0),
+                    strength,
                 });
-            } else if snapshot.change_percent < -self.threshold {
+            } else if momentum < -self.threshold && ctx.position(code) > 0 {
                 signals.push(Signal {
                     code: code.clone(),
-                    timestamp: data.timestamp,
+                    timestamp: ctx.now,
                     action: SignalAction::Sell,
                     price: Some(snapshot.price),
-                    amount: Some(100.0),
-                    reason: format!("跌幅超过阈值 {}%", self.threshold * 100.0),
-                    strength: -snapshot.change_percent / 10.0,
+                    amount: None, // 由仓位模型确定
+                    reason: format!("{}日跌幅{:.2}%超过阈值{}%", self.lookback_period, -momentum * 100.0, self.threshold * 100.0),
+                    strength,
                 });
             }
         }
@@ -88,25 +115,48 @@ impl Strategy for MomentumStrategy {
     }
 }
 
-// 均值回归策略
+// 均值回归策略: 价格跌破布林带下轨时买入, 回归均线或触发止损止盈时卖出
+// 只做多, 价格高于上轨时不开空
 pub struct MeanReversionStrategy {
     params: StrategyParams,
-    ma_period: i32,
+    ma_period: usize,
     std_dev_multiplier: f64,
+    bands: PerSymbol<Bollinger>,
 }
 
 impl MeanReversionStrategy {
     pub fn new(name: &str) -> Self {
-        let mut params = StrategyParams::new(name.to_string(), StrategyType::MeanReversion);
-        
-        // 设置默认参数
-        let _ = params.set_param("ma_period", 20);
-        let _ = params.set_param("std_dev_multiplier", 2.0);
-        
+        Self::with_params(StrategyParams::new(name.to_string(), StrategyType::MeanReversion))
+    }
+
+    // 按策略参数创建, 参数按参数定义校验
+    pub fn from_params(params: &StrategyParams) -> Result<Self, String> {
+        Ok(Self::with_params(params.validated()?))
+    }
+
+    // 参数已校验并补齐默认值
+    fn with_params(params: StrategyParams) -> Self {
+        let ma_period: usize = params.get_param("ma_period").unwrap_or_default();
+        let std_dev_multiplier: f64 = params.get_param("std_dev_multiplier").unwrap_or_default();
         Self {
             params,
-            ma_period: 20,
-            std_dev_multiplier: 2.0,
+            ma_period,
+            std_dev_multiplier,
+            bands: PerSymbol::new(Bollinger::new(ma_period, std_dev_multiplier)),
+        }
+    }
+
+    // 持仓的卖出原因: 止损, 止盈或回归均线
+    fn exit_reason(&self, price: f64, cost: f64, middle: f64) -> Option<String> {
+        let change = if cost > 0.0 { price / cost - 1.0 } else { 0.0 };
+        if self.params.stop_loss > 0.0 && change <= -self.params.stop_loss {
+            Some(format!("止损, 亏损{:.2}%", -change * 100.0))
+        } else if self.params.take_profit > 0.0 && change >= self.params.take_profit {
+            Some(format!("止盈, 盈利{:.2}%", change * 100.0))
+        } else if price >= middle {
+            Some(format!("价格回归{}日均线{:.2}", self.ma_period, middle))
+        } else {
+            None
         }
     }
 }
@@ -127,37 +177,58 @@ impl Strategy for MeanReversionStrategy {
     fn set_enabled(&mut self, enabled: bool) {
         self.params.enabled = enabled;
     }
+
+    fn history_window(&self) -> usize {
+        self.ma_period
+    }
+
+    // 用预热的历史行情初始化布林带, 当前行情留给on_bar
+    fn on_start(&mut self, ctx: &StrategyContext) {
+        self.bands = PerSymbol::new(Bollinger::new(self.ma_period, self.std_dev_multiplier));
+        for code in ctx.history.codes() {
+            let bars = match ctx.history.bars(code) {
+                Some(bars) => bars,
+                None => continue,
+            };
+            let warm = bars.len() - usize::from(ctx.data.stocks.contains_key(code));
+            for bar in bars.iter().take(warm) {
+                self.bands.update(bar);
+            }
+        }
+    }
     
-    fn generate_signals(&self, data: &MarketData) -> Vec<Signal> {
-        info!("生成均值回归策略信号");
-        
-        // 这里只是创建一个模拟的信号
-        // 实际应用中需要实现真正的策略逻辑
-        
+    fn on_bar(&mut self, ctx: &StrategyContext) -> Vec<Signal> {
         let mut signals = Vec::new();
         
-        // 假设根据股票价格与均值的偏离生成信号
-        for (code, snapshot) in &data.stocks {
-            // 假设当前价格偏离均值过大时生成信号
-            if snapshot.price > snapshot.price * 1.1 { // 价格高于均值10%
-                signals.push(Signal {
-                    code: code.clone(),
-                    timestamp: data.timestamp,
-                    action: SignalAction::Sell,
-                    price: Some(snapshot.price),
-                    amount: Some(100.0),
-                    reason: "价格高于均值过多".to_string(),
-                    strength: 0.7,
-                });
-            } else if snapshot.price < snapshot.price * 0.9 { // 价格低于均值10%
+        for (code, snapshot) in &ctx.data.stocks {
+            let band = match self.bands.update(snapshot) {
+                Some(band) if band.std_dev > 0.0 => band,
+                _ => continue,
+            };
+
+            if let Some(position) = ctx.portfolio.positions.get(code).filter(|p| p.amount > 0) {
+                if let Some(reason) = self.exit_reason(snapshot.price, to_f64(position.cost), band.middle) {
+                    signals.push(Signal {
+                        code: code.clone(),
+                        timestamp: ctx.now,
+                        action: SignalAction::Sell,
+                        price: Some(snapshot.price),
+                        amount: Some(position.amount as f64), // 全部卖出
+                        reason,
+                        strength: 1.0,
+                    });
+                }
+            } else if snapshot.price < band.lower {
+                // 偏离越多信号越强, 在下轨处为0.5
+                let deviation = (band.middle - snapshot.price) / band.std_dev;
                 signals.push(Signal {
                     code: code.clone(),
-                    timestamp: data.timestamp,
+                    timestamp: ctx.now,
                     action: SignalAction::Buy,
                     price: Some(snapshot.price),
-                    amount: Some(100.0),
-                    reason: "价格低于均值过多".to_string(),
-                    strength: 0.7,
+                    amount: None, // 由仓位模型确定
+                    reason: format!("价格低于{}日均线{:.2}个标准差", self.ma_period, deviation),
+                    strength: (deviation / (2.0 * self.std_dev_multiplier)).min(1.0),
                 });
             }
         }
@@ -166,6 +237,17 @@ impl Strategy for MeanReversionStrategy {
     }
 }
 
+// 按策略参数创建策略实例
+pub fn create_strategy(params: &StrategyParams) -> Result<Box<dyn Strategy>, String> {
+    match params.strategy_type {
+        StrategyType::Momentum => Ok(Box::new(MomentumStrategy::from_params(params)?)),
+        StrategyType::MeanReversion => Ok(Box::new(MeanReversionStrategy::from_params(params)?)),
+        StrategyType::FactorModel => Ok(Box::new(FactorModelStrategy::from_params(params)?)),
+        StrategyType::PairTrading => Ok(Box::new(PairTradingStrategy::from_params(params)?)),
+        other => Err(format!("策略类型{:?}尚未实现", other)),
+    }
+}
+
 // 策略工厂
 pub struct StrategyFactory {
     strategies: HashMap<String, Box<dyn Strategy>>,
@@ -195,4 +277,45 @@ impl StrategyFactory {
     pub fn get_strategy_names(&self) -> Vec<String> {
         self.strategies.keys().cloned().collect()
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use position::fees::FeeSchedule;
+    use crate::backtest::{run_backtest, BacktestConfig};
+    use crate::models::MarketData;
+    use crate::testing::bar_with_open as bar;
+
+    #[test]
+    fn test_mean_reversion_enters_below_band_and_exits_at_mean() {
+        let mut params = StrategyParams::new("mean".to_string(), StrategyType::MeanReversion);
+        params.set_param("ma_period", 5).unwrap();
+        params.set_param("std_dev_multiplier", 1.5).unwrap();
+        let config = BacktestConfig {
+            fees: FeeSchedule::zero(),
+            slippage: 0.0,
+            ..BacktestConfig::default()
+        };
+        let closes = [10.0, 10.2, 9.8, 10.1, 9.9, 9.0, 9.5, 10.2, 10.2];
+        let mut history: Vec<MarketData> = closes.iter().enumerate().map(|(i, &c)| bar(i as i64, c, c)).collect();
+        // 第七天开盘9.5买入
+        history[6] = bar(6, 9.5, 9.5);
+
+        let strategy = create_strategy(&params).unwrap();
+        let result = run_backtest(strategy, &params, &history, &config).unwrap();
+
+        // 第六天收盘9.0跌破下轨9.16, 第八天收盘10.2回到均线9.74之上, 第九天开盘卖出
+        assert_eq!(result.total_trades, 1);
+        let trade = &result.trades[0];
+        assert_eq!(trade.entry_price, 9.5);
+        assert_eq!(trade.exit_price, Some(10.2));
+        assert!(trade.exit_reason.as_ref().unwrap().starts_with("价格回归5日均线"));
+        assert_eq!(trade.exit_amount, Some(trade.entry_amount));
+
+        // 止损和止盈优先于均线回归
+        let strategy = MeanReversionStrategy::from_params(&params).unwrap();
+        assert!(strategy.exit_reason(9.0, 10.0, 8.0).unwrap().starts_with("止损"));
+        assert!(strategy.exit_reason(11.5, 10.0, 12.0).unwrap().starts_with("止盈"));
+        assert_eq!(strategy.exit_reason(9.8, 10.0, 10.0), None);
+    }
+}
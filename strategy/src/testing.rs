@@ -0,0 +1,40 @@
+use chrono::{Duration, TimeZone, Utc};
+use std::collections::HashMap;
+use crate::models::{Fundamentals, MarketData, StockSnapshot};
+
+// 测试用股票快照, 开盘价、最高价和最低价都等于收盘价
+pub(crate) fn snapshot(code: &str, price: f64, volume: f64, fundamentals: Fundamentals) -> StockSnapshot {
+    StockSnapshot {
+        code: code.to_string(),
+        name: code.to_string(),
+        price,
+        open: price,
+        high: price,
+        low: price,
+        volume,
+        turnover: 0.0,
+        change_percent: 0.0,
+        volatility_1m: 0.0,
+        atr: None,
+        fundamentals,
+    }
+}
+
+// 测试用单只股票 (600000) 的日线行情, day为距2024-01-01的天数
+pub(crate) fn bar_with_open(day: i64, open: f64, price: f64) -> MarketData {
+    let snapshot = StockSnapshot {
+        open,
+        high: open.max(price),
+        low: open.min(price),
+        ..snapshot("600000", price, 0.0, Fundamentals::default())
+    };
+    MarketData {
+        timestamp: Utc.with_ymd_and_hms(2024, 1, 1, 7, 0, 0).unwrap() + Duration::days(day),
+        stocks: HashMap::from([("600000".to_string(), snapshot)]),
+    }
+}
+
+// 开盘价等于收盘价的行情
+pub(crate) fn bar(day: i64, price: f64) -> MarketData {
+    bar_with_open(day, price, price)
+}
@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::AtomicUsize;
+use log::info;
+use crate::backtest::{run_backtest_with_warmup, BacktestConfig};
+use crate::metrics::{compute_metrics, PerformanceMetrics};
+use crate::models::{BacktestTrade, MarketData, StrategyParams};
+use crate::optimizer::{candidates, run_optimization, OptimizationConfig};
+use crate::strategy::create_strategy;
+
+// 滚动窗口设置 (按行情数量)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalkForwardConfig {
+    // 样本内窗口长度
+    pub in_sample_bars: usize,
+    // 样本外窗口长度, 也是窗口向前滚动的步长
+    pub out_of_sample_bars: usize,
+    // 锚定模式: 样本内窗口起点固定, 长度逐步扩大
+    #[serde(default)]
+    pub anchored: bool,
+    // 样本内优化设置, 其中的回测设置也用于样本外
+    pub optimization: OptimizationConfig,
+}
+
+// 单个窗口的样本内外表现
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalkForwardWindow {
+    pub in_sample_start: DateTime<Utc>,
+    pub in_sample_end: DateTime<Utc>,
+    pub out_of_sample_start: DateTime<Utc>,
+    pub out_of_sample_end: DateTime<Utc>,
+    // 样本内最优参数
+    pub params: HashMap<String, Value>,
+    pub in_sample_score: f64,
+    pub out_of_sample_score: f64,
+    pub in_sample_return: f64,
+    pub out_of_sample_return: f64,
+    pub in_sample_annualized_return: f64,
+    pub out_of_sample_annualized_return: f64,
+    pub in_sample_sharpe: f64,
+    pub out_of_sample_sharpe: f64,
+}
+
+// 滚动前向分析结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalkForwardResult {
+    pub strategy_name: String,
+    pub windows: Vec<WalkForwardWindow>,
+    // 拼接的样本外净值曲线, 每个窗口以上一窗口的期末净值开始
+    pub equity_curve: Vec<(DateTime<Utc>, f64)>,
+    pub trades: Vec<BacktestTrade>,
+    // 拼接后样本外的整体绩效
+    pub out_of_sample: PerformanceMetrics,
+    pub mean_in_sample_score: f64,
+    pub mean_out_of_sample_score: f64,
+    // 前向效率: 样本外平均年化收益 / 样本内平均年化收益, 明显低于1说明存在过拟合
+    pub efficiency: f64,
+    // 样本外盈利的窗口数
+    pub profitable_windows: usize,
+    // 各窗口选出的不同参数组数, 越少说明参数越稳定
+    pub distinct_param_sets: usize,
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), v| (sum + v, count + 1));
+    if count == 0 { 0.0 } else { sum / count as f64 }
+}
+
+// 在滚动的样本内窗口优化参数, 用最优参数回测紧随其后的样本外窗口
+pub fn walk_forward(
+    params: &StrategyParams,
+    history: &[MarketData],
+    config: &WalkForwardConfig,
+    progress: &AtomicUsize,
+) -> Result<WalkForwardResult, String> {
+    if config.in_sample_bars == 0 || config.out_of_sample_bars == 0 {
+        return Err("样本内和样本外窗口长度必须大于0".to_string());
+    }
+    let mut bars: Vec<MarketData> = history.to_vec();
+    bars.sort_by_key(|d| d.timestamp);
+    if bars.len() <= config.in_sample_bars {
+        return Err(format!("行情数量{}不足, 至少需要{}", bars.len(), config.in_sample_bars + 1));
+    }
+
+    let candidates = candidates(params, &config.optimization)?;
+    let mut capital = config.optimization.backtest.initial_capital;
+    let mut windows = Vec::new();
+    let mut equity_curve: Vec<(DateTime<Utc>, f64)> = Vec::new();
+    let mut trades = Vec::new();
+
+    let mut split = config.in_sample_bars;
+    while split < bars.len() {
+        let start = if config.anchored { 0 } else { split - config.in_sample_bars };
+        let end = (split + config.out_of_sample_bars).min(bars.len());
+        let (in_sample, out_of_sample) = (&bars[start..split], &bars[split..end]);
+
+        let runs = run_optimization(params, &candidates, in_sample, &config.optimization, progress);
+        let best = runs.into_iter().find(|r| r.error.is_none())
+            .ok_or_else(|| format!("窗口{}没有成功的样本内回测", windows.len() + 1))?;
+
+        let mut window_params = params.clone();
+        window_params.params = best.params.clone();
+        let backtest = BacktestConfig {
+            initial_capital: capital,
+            start_date: None,
+            end_date: None,
+            ..config.optimization.backtest.clone()
+        };
+        // 样本外回测用样本内行情预热历史窗口
+        let strategy = create_strategy(&window_params)?;
+        let result = run_backtest_with_warmup(strategy, &window_params, in_sample, out_of_sample, &backtest)?;
+        info!("前向窗口{}: 样本内{:.4}, 样本外{:.4}", windows.len() + 1, best.score, config.optimization.objective.score(&result));
+
+        capital = result.final_capital;
+        equity_curve.extend(result.equity_curve.iter().cloned());
+        windows.push(WalkForwardWindow {
+            in_sample_start: in_sample[0].timestamp,
+            in_sample_end: in_sample[in_sample.len() - 1].timestamp,
+            out_of_sample_start: result.start_date,
+            out_of_sample_end: result.end_date,
+            params: best.params,
+            in_sample_score: best.score,
+            out_of_sample_score: config.optimization.objective.score(&result),
+            in_sample_return: best.total_return,
+            out_of_sample_return: result.total_return,
+            in_sample_annualized_return: best.annualized_return,
+            out_of_sample_annualized_return: result.annualized_return,
+            in_sample_sharpe: best.sharpe_ratio,
+            out_of_sample_sharpe: result.sharpe_ratio,
+        });
+        trades.extend(result.trades);
+        split = end;
+    }
+
+    let out_of_sample = compute_metrics(&equity_curve, &trades, 0.0);
+    let mean_is_return = mean(windows.iter().map(|w| w.in_sample_annualized_return));
+    let mean_oos_return = mean(windows.iter().map(|w| w.out_of_sample_annualized_return));
+    let distinct: HashSet<String> = windows.iter()
+        .map(|w| {
+            let mut pairs: Vec<String> = w.params.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            pairs.sort();
+            pairs.join(",")
+        })
+        .collect();
+
+    Ok(WalkForwardResult {
+        strategy_name: params.name.clone(),
+        mean_in_sample_score: mean(windows.iter().map(|w| w.in_sample_score)),
+        mean_out_of_sample_score: mean(windows.iter().map(|w| w.out_of_sample_score)),
+        efficiency: if mean_is_return > 0.0 { mean_oos_return / mean_is_return } else { 0.0 },
+        profitable_windows: windows.iter().filter(|w| w.out_of_sample_return > 0.0).count(),
+        distinct_param_sets: distinct.len(),
+        windows,
+        equity_curve,
+        trades,
+        out_of_sample,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use crate::models::StrategyType;
+    use crate::optimizer::{Objective, ParamRange, SearchMethod};
+    use crate::testing::bar;
+
+    #[test]
+    fn test_walk_forward_stitches_out_of_sample() {
+        let params = StrategyParams::new("momentum".to_string(), StrategyType::Momentum);
+        let history: Vec<MarketData> = (0..70).map(|i| bar(i, 10.0 + (i as f64 * 0.3).sin() * 1.5 + i as f64 * 0.05)).collect();
+        let mut config = WalkForwardConfig {
+            in_sample_bars: 30,
+            out_of_sample_bars: 15,
+            anchored: false,
+            optimization: OptimizationConfig {
+                ranges: BTreeMap::from([
+                    ("lookback_period".to_string(), ParamRange::Values { values: vec![Value::from(3), Value::from(5), Value::from(8)] }),
+                ]),
+                method: SearchMethod::Grid,
+                objective: Objective::TotalReturn,
+                backtest: BacktestConfig::default(),
+                threads: Some(2),
+            },
+        };
+
+        let progress = AtomicUsize::new(0);
+        let result = walk_forward(&params, &history, &config, &progress).unwrap();
+        // 样本外窗口: 30-44, 45-59, 60-69
+        assert_eq!(result.windows.len(), 3);
+        assert_eq!(progress.load(std::sync::atomic::Ordering::Relaxed), 9);
+        assert_eq!(result.windows[0].in_sample_start, history[0].timestamp);
+        assert_eq!(result.windows[1].in_sample_start, history[15].timestamp);
+        assert_eq!(result.windows[2].out_of_sample_start, history[60].timestamp);
+        assert_eq!(result.equity_curve.len(), 40);
+        assert_eq!(result.equity_curve[0].0, history[30].timestamp);
+        assert!(result.distinct_param_sets >= 1 && result.distinct_param_sets <= 3);
+
+        // 净值在窗口之间连续
+        let first_end = result.equity_curve[14].1;
+        let second_return = result.windows[1].out_of_sample_return;
+        assert!((result.equity_curve[29].1 - first_end * (1.0 + second_return)).abs() < 1e-6);
+
+        // 锚定模式的样本内窗口都从头开始
+        config.anchored = true;
+        let result = walk_forward(&params, &history, &config, &progress).unwrap();
+        assert!(result.windows.iter().all(|w| w.in_sample_start == history[0].timestamp));
+
+        config.in_sample_bars = 70;
+        assert!(walk_forward(&params, &history, &config, &progress).is_err());
+    }
+}